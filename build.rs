@@ -6,6 +6,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         // .build_client(false)
         // .out_dir(".") // if you want to peek at the generated code
-        .compile(&["proto/grpc.proto", "proto/multisig.proto"], &["proto"])?;
+        .compile(
+            &[
+                "proto/grpc.proto",
+                "proto/multisig.proto",
+                "admin-proto/admin.proto",
+                "keys-proto/gg20_keys.proto",
+            ],
+            &["proto", "admin-proto", "keys-proto"],
+        )?;
     Ok(())
 }
@@ -5,7 +5,7 @@ set -e
 OK=0
 ERR=1
 
-# create: create a new mnemonic and export it to $EXPORT_PATH
+# create: create a new mnemonic and export it to $EXPORT_PATH, or to the path given as first argument
 create_mnemonic() {
     echo "Creating mnemonic ..."
 
@@ -15,7 +15,7 @@ create_mnemonic() {
         return $ERR
     fi
 
-    (echo ${PASSWORD} | tofnd ${ARGS} -m create) && echo "... ok" && return $OK
+    (echo ${PASSWORD} | tofnd ${ARGS} mnemonic create --export-path "${1:-$EXPORT_PATH}") && echo "... ok" && return $OK
     return $ERR
 }
 
@@ -35,10 +35,10 @@ import_mnemonic() {
 
     if [ -n "${NOPASSWORD}" ]; then \
         echo "No password"
-        (cat $IMPORT_PATH | tofnd ${ARGS} -m import) || return $ERR
+        tofnd ${ARGS} mnemonic import --import-path "$IMPORT_PATH" || return $ERR
     else
         echo "With password"
-        ((echo $PASSWORD && cat $IMPORT_PATH) | tofnd ${ARGS} -m import) || return $ERR
+        (echo $PASSWORD | tofnd ${ARGS} mnemonic import --import-path "$IMPORT_PATH") || return $ERR
     fi
 
     echo "... ok"
@@ -48,7 +48,7 @@ import_mnemonic() {
 # export: export the mnemonic to $EXPORT_PATH
 export_mnemonic() {
     echo "Exporting mnemonic ..."
-    echo ${PASSWORD} | tofnd ${ARGS} -m export || return $ERR
+    echo ${PASSWORD} | tofnd ${ARGS} mnemonic export --export-path "$EXPORT_PATH" || return $ERR
     echo "... ok"
     return $OK
 }
@@ -76,16 +76,25 @@ ARGS=${UNSAFE:+"${ARGS} --unsafe"}
 if [ -n "${MNEMONIC_CMD}" ]; then \
 
     case ${MNEMONIC_CMD} in
-        # auto: try to set up tofnd and then spin up tofnd with the existing mnemonic.
-        # Order of set up: 1) import mnemonic, 2) create mnemonic.
-        # If 2) then move the mnemonic to $IMPORT_PATH so that tofnd will not complain
+        # auto: set up a mnemonic if needed and spin up tofnd; see the `auto` mnemonic command of tofnd
+        # extra arguments may hold a top-level subcommand like `malicious R1BadProof 0`, which can't follow `run`,
+        # so they keep the deprecated `-m` flag
         auto)
-            echo "Trying import" && import_mnemonic \
-            || (echo "... skipping. Trying to create" && create_mnemonic && mv $EXPORT_PATH $IMPORT_PATH) \
-            || echo "... skipping"
+            echo "Using auto mnemonic ..."
+            if [ $# -gt 0 ]; then \
+                ARGS="${ARGS} -m auto --import-path $IMPORT_PATH"
+            else
+                ARGS="${ARGS} run --auto --import-path $IMPORT_PATH"
+            fi
             ;;
 
         existing)
+            echo "Using existing mnemonic ..."
+            if [ $# -gt 0 ]; then \
+                ARGS="${ARGS} -m existing"
+            else
+                ARGS="${ARGS} run"
+            fi
             ;;
 
         create)
@@ -109,8 +118,6 @@ if [ -n "${MNEMONIC_CMD}" ]; then \
             ;;
     esac
 
-    echo "Using existing mnemonic ..."
-    ARGS="${ARGS} -m existing"
 fi
 
 # execute tofnd daemon
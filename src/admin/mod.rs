@@ -0,0 +1,13 @@
+//! [proto::admin_server::Admin] gRPC server API, for operators of a running tofnd daemon.
+//! Available gRPCs are:
+//!     [rotate] - Rotates out the current mnemonic for a new one without restarting tofnd.
+//!
+//! The service is only served if an admin token is configured, and every request
+//! has to carry the token in the [service::ADMIN_TOKEN_KEY] metadata.
+//! The same token guards the requests of [crate::gg20] that delete keys or rebuild their recovery info.
+
+mod rotate;
+pub mod service;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,24 @@
+//! This module handles the rotate mnemonic gRPC.
+//! The mnemonic is rotated while the daemon keeps serving. Keygens that start after the rotation use the new mnemonic,
+//! while sessions that already hold the seed of the old one finish with it.
+//! The new phrase is only written to a sealed export file of its own.
+
+use super::service::AdminService;
+use crate::{error::ServiceResult, proto};
+
+impl AdminService {
+    pub(super) async fn handle_rotate_mnemonic(
+        &self,
+    ) -> ServiceResult<proto::RotateMnemonicResponse> {
+        let rotated = self.kv_manager.rotate_online(&self.mnemonic_config).await?;
+        let export_path = self
+            .mnemonic_config
+            .rotation_export_path(self.kv_manager.io(), &rotated.rotated_out);
+
+        Ok(proto::RotateMnemonicResponse {
+            rotated_out: rotated.rotated_out,
+            fingerprint: rotated.fingerprint.to_string(),
+            export_path: export_path.to_string_lossy().into_owned(),
+        })
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use subtle::ConstantTimeEq;
+use tonic::{metadata::MetadataMap, Request, Response, Status};
+
+use crate::{
+    error::{ServiceError::Unauthenticated, ServiceResult},
+    gg20::types::Password,
+    kv_manager::KvManager,
+    mnemonic::MnemonicConfig,
+    proto, TofndResult,
+};
+
+use anyhow::anyhow;
+use tracing::{error, info};
+
+/// metadata key of the admin token
+pub const ADMIN_TOKEN_KEY: &str = "tofnd-admin-token";
+
+/// AdminService
+#[derive(Clone)]
+pub struct AdminService {
+    pub(super) kv_manager: KvManager,
+    /// options of the sealed exports of rotated mnemonics
+    pub(super) mnemonic_config: MnemonicConfig,
+    token: Password,
+}
+
+/// create a new Admin gRPC server
+pub fn new_service(
+    kv_manager: KvManager,
+    mnemonic_config: MnemonicConfig,
+    token: Password,
+) -> impl proto::admin_server::Admin {
+    AdminService {
+        kv_manager,
+        mnemonic_config,
+        token,
+    }
+}
+
+/// Reads the admin token from the file at `path`, without its line ending
+pub fn read_token(path: &Path) -> TofndResult<Password> {
+    let mut token = Password(std::fs::read_to_string(path)?);
+    let len = token.0.trim_end_matches(&['\r', '\n'][..]).len();
+    token.0.truncate(len);
+
+    if token.0.is_empty() {
+        return Err(anyhow!("admin token file {:?} is empty", path));
+    }
+    Ok(token)
+}
+
+/// Checks that `metadata` carries the admin token `token`. Tokens are compared in constant time.
+pub(crate) fn authenticate(token: &Password, metadata: &MetadataMap) -> ServiceResult<()> {
+    let request_token = metadata
+        .get(ADMIN_TOKEN_KEY)
+        .ok_or_else(|| Unauthenticated("missing admin token".to_string()))?;
+
+    match bool::from(request_token.as_bytes().ct_eq(token.0.as_bytes())) {
+        true => Ok(()),
+        false => Err(Unauthenticated("wrong admin token".to_string())),
+    }
+}
+
+#[tonic::async_trait]
+impl proto::admin_server::Admin for AdminService {
+    async fn rotate_mnemonic(
+        &self,
+        request: Request<proto::RotateMnemonicRequest>,
+    ) -> Result<Response<proto::RotateMnemonicResponse>, Status> {
+        if let Err(err) = authenticate(&self.token, request.metadata()) {
+            error!("Rejected admin request: {}", err.to_response_string());
+            return Err(err.into());
+        }
+
+        match self.handle_rotate_mnemonic().await {
+            Ok(response) => {
+                info!(
+                    "Mnemonic rotated out to '{}'. New mnemonic has fingerprint {}",
+                    response.rotated_out, response.fingerprint
+                );
+                Ok(Response::new(response))
+            }
+            Err(err) => {
+                error!("Unable to rotate mnemonic: {}", err.to_response_string());
+                Err(err.into())
+            }
+        }
+    }
+}
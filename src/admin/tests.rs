@@ -0,0 +1,208 @@
+use crate::{
+    addr,
+    encrypted_sled::get_test_password,
+    gg20::types::Password,
+    kv_manager::KvManager,
+    mnemonic::{Cmd, MnemonicConfig, MNEMONIC_KEY},
+    tests::{DEFAULT_TEST_IP, DEFAULT_TEST_PORT},
+};
+use std::path::PathBuf;
+use tokio::{
+    self,
+    net::TcpListener,
+    sync::oneshot::{channel, Sender},
+};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{transport::Channel, Code, Request};
+
+use super::service::{new_service, ADMIN_TOKEN_KEY};
+
+use testdir::testdir;
+use tracing_test::traced_test;
+
+use crate::proto::{admin_client::AdminClient, admin_server::AdminServer, RotateMnemonicRequest};
+
+const TOKEN: &str = "admin token";
+
+// set up tests
+async fn spin_test_service_and_client(
+    root: PathBuf,
+    mnemonic_config: MnemonicConfig,
+) -> (AdminClient<Channel>, KvManager, Sender<()>) {
+    // create a kv_manager
+    let kv_manager = KvManager::new(root.join("kv"), get_test_password())
+        .unwrap()
+        .handle_mnemonic(
+            &Cmd::Create,
+            &MnemonicConfig {
+                export_path: Some(root.join("created")),
+                ..MnemonicConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    // create service
+    let service = new_service(
+        kv_manager.clone(),
+        mnemonic_config,
+        Password(TOKEN.to_string()),
+    );
+    let service = AdminServer::new(service);
+
+    // create incoming tcp server for service
+    let incoming = TcpListener::bind(addr(DEFAULT_TEST_IP, DEFAULT_TEST_PORT).unwrap())
+        .await
+        .unwrap();
+
+    // create shutdown channels
+    let (shutdown_sender, shutdown_receiver) = channel::<()>();
+
+    // get server's address
+    let server_addr = incoming.local_addr().unwrap();
+
+    // spin up admin gRPC server with incoming shutdown
+    tokio::spawn(async move {
+        tonic::transport::Server::builder()
+            .add_service(service)
+            .serve_with_incoming_shutdown(TcpListenerStream::new(incoming), async {
+                shutdown_receiver.await.unwrap();
+            })
+            .await
+            .unwrap();
+    });
+
+    // create a client to admin service
+    let client = AdminClient::connect(format!("http://{}", server_addr))
+        .await
+        .unwrap();
+
+    // return the client, the kv store and the shutdown channel for the service
+    (client, kv_manager, shutdown_sender)
+}
+
+// a rotate request with `token` in its metadata
+fn rotate_request(token: Option<&str>) -> Request<RotateMnemonicRequest> {
+    let mut request = Request::new(RotateMnemonicRequest {});
+    if let Some(token) = token {
+        request
+            .metadata_mut()
+            .insert(ADMIN_TOKEN_KEY, token.parse().unwrap());
+    }
+    request
+}
+
+// config that exports rotated mnemonics sealed to `root`
+fn sealed_config(root: &std::path::Path) -> MnemonicConfig {
+    let export_passphrase_file = root.join("export_passphrase");
+    std::fs::write(&export_passphrase_file, "export passphrase").unwrap();
+    MnemonicConfig {
+        seal_export: true,
+        export_passphrase_file: Some(export_passphrase_file),
+        export_path: Some(root.join("rotated.sealed")),
+        ..MnemonicConfig::default()
+    }
+}
+
+#[traced_test]
+#[tokio::test]
+async fn test_rotate_mnemonic() {
+    let root = testdir!();
+    let (mut client, kv_manager, shutdown_sender) =
+        spin_test_service_and_client(root.clone(), sealed_config(&root)).await;
+    let old_seed = kv_manager.seed().await.unwrap();
+
+    let response = client
+        .rotate_mnemonic(rotate_request(Some(TOKEN)))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.rotated_out, "mnemonic_1");
+    assert_eq!(
+        PathBuf::from(&response.export_path),
+        root.join("rotated.sealed.mnemonic_1")
+    );
+    assert!(root.join("rotated.sealed.mnemonic_1").exists());
+
+    // the daemon switched to the new mnemonic, and kept the old one
+    assert_eq!(
+        kv_manager.seed_key_iter().await.unwrap(),
+        vec![MNEMONIC_KEY.to_owned(), "mnemonic_1".to_owned()]
+    );
+    assert_eq!(
+        format!("{:?}", kv_manager.get_seed("mnemonic_1").await.unwrap()),
+        format!("{:?}", old_seed)
+    );
+    assert_eq!(
+        crate::mnemonic::mnemonic_fingerprint(&kv_manager.seed().await.unwrap())
+            .unwrap()
+            .to_string(),
+        response.fingerprint
+    );
+
+    // the next rotation is exported to its own file
+    let response = client
+        .rotate_mnemonic(rotate_request(Some(TOKEN)))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.rotated_out, "mnemonic_2");
+    assert!(root.join("rotated.sealed.mnemonic_1").exists());
+    assert!(root.join("rotated.sealed.mnemonic_2").exists());
+
+    // an existing export is never overwritten
+    std::fs::write(root.join("rotated.sealed.mnemonic_3"), "stale export").unwrap();
+    let status = client
+        .rotate_mnemonic(rotate_request(Some(TOKEN)))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::FailedPrecondition);
+    assert_eq!(kv_manager.seed_key_iter().await.unwrap().len(), 3);
+
+    shutdown_sender.send(()).unwrap();
+}
+
+#[traced_test]
+#[tokio::test]
+async fn test_rotate_mnemonic_unauthenticated() {
+    let root = testdir!();
+    let (mut client, kv_manager, shutdown_sender) =
+        spin_test_service_and_client(root.clone(), sealed_config(&root)).await;
+
+    for token in [None, Some("wrong token")] {
+        let status = client
+            .rotate_mnemonic(rotate_request(token))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    // nothing was rotated
+    assert_eq!(
+        kv_manager.seed_key_iter().await.unwrap(),
+        vec![MNEMONIC_KEY.to_owned()]
+    );
+    assert!(!root.join("rotated.sealed.mnemonic_1").exists());
+
+    shutdown_sender.send(()).unwrap();
+}
+
+#[traced_test]
+#[tokio::test]
+async fn test_rotate_mnemonic_plaintext_export() {
+    let root = testdir!();
+    let (mut client, kv_manager, shutdown_sender) =
+        spin_test_service_and_client(root.clone(), MnemonicConfig::default()).await;
+
+    // the phrase is never exported in plaintext by a running daemon
+    assert!(client
+        .rotate_mnemonic(rotate_request(Some(TOKEN)))
+        .await
+        .is_err());
+    assert_eq!(
+        kv_manager.seed_key_iter().await.unwrap(),
+        vec![MNEMONIC_KEY.to_owned()]
+    );
+
+    shutdown_sender.send(()).unwrap();
+}
@@ -10,7 +10,7 @@ use tofn::{
     },
 };
 
-use clap::App;
+use clap::ArgMatches;
 
 pub(super) type Behaviours = crate::gg20::service::malicious::Behaviours;
 
@@ -37,13 +37,11 @@ pub(super) const AVAILABLE_BEHAVIOURS: [&str; 20] = [
     "R7BadSI",
 ];
 
-pub fn get_behaviour_matches(app: App) -> TofndResult<Behaviours> {
+pub fn get_behaviour_matches(matches: &ArgMatches) -> TofndResult<Behaviours> {
     // TODO: if we want to read all available behaviours from tofn automatically,
     // we should add strum (https://docs.rs/strum) to iterate over enums and
     // print their names, but it has to be imported in tofn.
 
-    let matches = app.get_matches();
-
     // Set a default behaviour
     let mut sign_behaviour = "Honest";
     let mut victim = 0;
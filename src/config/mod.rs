@@ -1,18 +1,74 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
-use clap::{crate_version, App, Arg};
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches};
 
 // error handling
-use crate::{encrypted_sled::PasswordMethod, mnemonic::Cmd, TofndResult};
+use crate::{
+    encrypted_sled::PasswordMethod,
+    keys::KeysCmd,
+    kv_manager::ReservationsCmd,
+    mnemonic::{Cmd, MnemonicConfig, MnemonicLanguage, ShareScheme},
+    TofndResult,
+};
 use anyhow::anyhow;
 
 // TODO: move these into constants.rs
 const DEFAULT_PATH_ROOT: &str = ".tofnd";
 const TOFND_HOME_ENV_VAR: &str = "TOFND_HOME";
+const MNEMONIC_PASSPHRASE_FILE_ENV_VAR: &str = "TOFND_MNEMONIC_PASSPHRASE_FILE";
+const EXPORT_PASSPHRASE_FILE_ENV_VAR: &str = "TOFND_EXPORT_PASSPHRASE_FILE";
+const ADMIN_TOKEN_FILE_ENV_VAR: &str = "TOFND_ADMIN_TOKEN_FILE";
 const DEFAULT_MNEMONIC_CMD: &str = "existing";
+const DEFAULT_OUTPUT_FORMAT: &str = "text";
 const DEFAULT_IP: &str = "127.0.0.1";
-const DEFAULT_PORT: u16 = 50051;
-const AVAILABLE_MNEMONIC_CMDS: &[&str] = &["existing", "create", "import", "export", "rotate"];
+const DEFAULT_PORT: &str = "50051";
+/// one week
+const DEFAULT_UNDELETE_WINDOW_HOURS: &str = "168";
+const AVAILABLE_MNEMONIC_CMDS: &[&str] = &[
+    "existing", "auto", "create", "import", "export", "rotate", "list", "prune", "verify",
+];
+const AVAILABLE_RESERVATIONS_CMDS: &[&str] = &["list", "clear"];
+const AVAILABLE_OUTPUT_FORMATS: &[&str] = &["text", "json"];
+/// subcommands of `tofnd mnemonic`, along with their help
+const MNEMONIC_SUBCOMMANDS: &[(&str, &str)] = &[
+    ("create", "Create a new mnemonic, export it and exit"),
+    ("import", "Import a mnemonic and exit"),
+    (
+        "export",
+        "Export the current or a rotated out mnemonic and exit",
+    ),
+    (
+        "rotate",
+        "Rotate out the current mnemonic for a new one, export it and exit",
+    ),
+    (
+        "list",
+        "List the current and rotated out mnemonics and exit",
+    ),
+    (
+        "prune",
+        "Delete a rotated out mnemonic that no key depends on and exit",
+    ),
+    (
+        "verify",
+        "Check that a backup matches a stored mnemonic and exit",
+    ),
+];
+const AVAILABLE_MNEMONIC_WORDS: &[&str] = &["12", "15", "18", "21", "24"];
+
+/// parses a share scheme of the form `<threshold>-of-<count>`
+fn parse_share_scheme(scheme: &str) -> TofndResult<ShareScheme> {
+    let (threshold, count) = scheme.split_once("-of-").ok_or_else(|| {
+        anyhow!(
+            "share scheme {} is not of the form <threshold>-of-<count>",
+            scheme
+        )
+    })?;
+    Ok(ShareScheme {
+        threshold: threshold.parse()?,
+        count: count.parse()?,
+    })
+}
 
 #[cfg(feature = "malicious")]
 mod malicious;
@@ -26,28 +82,53 @@ fn default_tofnd_dir() -> TofndResult<PathBuf> {
         .join(DEFAULT_PATH_ROOT))
 }
 
+/// Format of the results of commands that exit tofnd
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// results are logged
+    Text,
+    /// results are printed to stdout as a json object, and logs are written to stderr
+    Json,
+}
+
 // TODO: move to types.rs
 #[derive(Clone, Debug)]
 pub struct Config {
     pub ip: String,
     pub port: u16,
     pub safe_keygen: bool,
+    /// gg20 keys generated with unsafe primes are only used for signing if this is set
+    pub allow_unsafe_keys: bool,
     pub mnemonic_cmd: Cmd,
+    pub mnemonic_config: MnemonicConfig,
     pub tofnd_path: PathBuf,
     pub password_method: PasswordMethod,
+    pub reservations_cmd: Option<ReservationsCmd>,
+    pub keys_cmd: Option<KeysCmd>,
+    /// file of the token of the admin service; the admin service is only served if it is set
+    pub admin_token_file: Option<PathBuf>,
+    /// deleted gg20 keys are purged after this period
+    pub undelete_window: Duration,
+    pub output: OutputFormat,
     #[cfg(feature = "malicious")]
     pub behaviours: Behaviours,
 }
 
 pub fn parse_args() -> TofndResult<Config> {
-    // need to use let to avoid dropping temporary value
-    let ip = &DEFAULT_IP.to_string();
-    let port = &DEFAULT_PORT.to_string();
     let default_dir = default_tofnd_dir()?;
     let default_dir = default_dir
         .to_str()
         .ok_or_else(|| anyhow!("can't convert default dir to str"))?;
 
+    let matches = app(default_dir).get_matches();
+    config_from_matches(&matches)
+}
+
+/// tofnd runs the gRPC daemon with the `run` subcommand.
+/// Maintenance tasks are run by the `mnemonic`, `db` and `keys` subcommands, which exit after the task.
+/// Without a subcommand, the deprecated `--mnemonic` and `--reservations` flags select what tofnd does.
+/// Options are global, so that they can be given before or after a subcommand.
+fn app(default_dir: &str) -> App<'_> {
     let app = App::new("tofnd")
         .about("A threshold signature scheme daemon")
         .version(crate_version!())
@@ -56,14 +137,16 @@ pub fn parse_args() -> TofndResult<Config> {
                 .long("address")
                 .short('a')
                 .required(false)
-                .default_value(ip),
+                .global(true)
+                .default_value(DEFAULT_IP),
         )
         .arg(
             Arg::new("port")
                 .long("port")
                 .short('p')
                 .required(false)
-                .default_value(port),
+                .global(true)
+                .default_value(DEFAULT_PORT),
         )
         .arg(
             // TODO: change to something like `--unsafe-primes`
@@ -73,9 +156,20 @@ pub fn parse_args() -> TofndResult<Config> {
                 )
                 .long("unsafe")
                 .required(false)
+                .global(true)
                 .takes_value(false)
                 .display_order(0),
         )
+        .arg(
+            Arg::new("allow-unsafe-keys")
+                .help(
+                    "Sign with keys that were generated with unsafe primes. Implied by '--unsafe'. (default: deactivated) **Security warning:** Keys of unsafe primes must not secure real value.",
+                )
+                .long("allow-unsafe-keys")
+                .required(false)
+                .global(true)
+                .takes_value(false),
+        )
         .arg(
             Arg::new("no-password")
                 .help(
@@ -83,24 +177,238 @@ pub fn parse_args() -> TofndResult<Config> {
                 )
                 .long("no-password")
                 .required(false)
+                .global(true)
                 .takes_value(false)
                 .display_order(0),
         )
         .arg(
-            Arg::new("mnemonic")
+            Arg::new("mnemonic-cmd")
+                .help(
+                    "Deprecated: use `tofnd run` to run the daemon and `tofnd mnemonic <command>` for mnemonic commands.",
+                )
                 .long("mnemonic")
                 .short('m')
                 .required(false)
                 .default_value(DEFAULT_MNEMONIC_CMD)
                 .possible_values(AVAILABLE_MNEMONIC_CMDS),
         )
+        .arg(
+            Arg::new("mnemonic-index")
+                .help(
+                    "Index of the rotated out mnemonic `mnemonic_<index>` to export with `mnemonic export`, list with `mnemonic list` or delete with `mnemonic prune`. (default: the current mnemonic for export, all mnemonics for list)",
+                )
+                .long("mnemonic-index")
+                .required(false)
+                .global(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("mnemonic-language")
+                .help(
+                    "Language of mnemonics created by `mnemonic create` or `mnemonic rotate`, and of phrases written by `mnemonic export`. (default: English for new mnemonics, the stored language for exports) The language of imported phrases is detected.",
+                )
+                .long("mnemonic-language")
+                .required(false)
+                .global(true)
+                .possible_values(&MnemonicLanguage::CODES),
+        )
+        .arg(
+            Arg::new("mnemonic-words")
+                .help(
+                    "Number of words of mnemonics created by `mnemonic create` or `mnemonic rotate`. (default: 24) Imported phrases may have any of these lengths.",
+                )
+                .long("mnemonic-words")
+                .required(false)
+                .global(true)
+                .possible_values(AVAILABLE_MNEMONIC_WORDS),
+        )
+        .arg(
+            Arg::new("mnemonic-passphrase")
+                .help(
+                    "Protect mnemonics created by `mnemonic create` or imported by `mnemonic import` with a BIP39 passphrase. (default: disabled) The passphrase is needed to start tofnd and is not part of exported phrases.",
+                )
+                .long("mnemonic-passphrase")
+                .required(false)
+                .global(true)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::new("mnemonic-passphrase-file")
+                .help(
+                    "Read the mnemonic passphrase from this file instead of prompting for it.",
+                )
+                .long("mnemonic-passphrase-file")
+                .required(false)
+                .global(true)
+                .env(MNEMONIC_PASSPHRASE_FILE_ENV_VAR),
+        )
+        .arg(
+            Arg::new("mnemonic-shares")
+                .help(
+                    "Write mnemonics as SLIP-39 shares instead of a single phrase, given as <threshold>-of-<count>, e.g. 2-of-3. Each share is written to its own file `export_share_<i>`.",
+                )
+                .long("mnemonic-shares")
+                .required(false)
+                .global(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::new("mnemonic-share-file")
+                .help(
+                    "Import the mnemonic from SLIP-39 share files instead of prompting for a phrase. Repeat for each share.",
+                )
+                .long("mnemonic-share-file")
+                .required(false)
+                .global(true)
+                .takes_value(true)
+                .multiple_occurrences(true),
+        )
+        .arg(
+            Arg::new("mnemonic-seal")
+                .help(
+                    "Seal mnemonics written by `mnemonic create`, `mnemonic rotate` or `mnemonic export` with an export passphrase, in the file `export.sealed`. (default: disabled) Unlike a plaintext export, a sealed export does not stop tofnd from starting.",
+                )
+                .long("mnemonic-seal")
+                .required(false)
+                .global(true)
+                .takes_value(false)
+                .conflicts_with("mnemonic-shares"),
+        )
+        .arg(
+            Arg::new("export-path")
+                .help(
+                    "Write the mnemonic exported by `mnemonic create`, `mnemonic rotate` or `mnemonic export` to this file instead of the tofnd directory. Existing files are not overwritten.",
+                )
+                .long("export-path")
+                .required(false)
+                .global(true)
+                .takes_value(true)
+                .conflicts_with("mnemonic-shares"),
+        )
+        .arg(
+            Arg::new("to-stdout")
+                .help(
+                    "Write the mnemonic exported by `mnemonic create`, `mnemonic rotate` or `mnemonic export` to stdout instead of a file. Logs are written to stderr.",
+                )
+                .long("to-stdout")
+                .required(false)
+                .global(true)
+                .takes_value(false)
+                .conflicts_with_all(&["mnemonic-shares", "export-path"]),
+        )
+        .arg(
+            Arg::new("import-path")
+                .help(
+                    "Read the mnemonic of `mnemonic import` or `mnemonic verify` from this file instead of prompting for a phrase. The file holds a phrase or a sealed export.",
+                )
+                .long("import-path")
+                .required(false)
+                .global(true)
+                .takes_value(true)
+                .conflicts_with("mnemonic-share-file"),
+        )
+        .arg(
+            Arg::new("export-passphrase-file")
+                .help("Read the export passphrase from this file instead of prompting for it.")
+                .long("export-passphrase-file")
+                .required(false)
+                .global(true)
+                .env(EXPORT_PASSPHRASE_FILE_ENV_VAR),
+        )
+        .arg(
+            Arg::new("reservations-cmd")
+                .help("Deprecated: use `tofnd db reservations <command>`.")
+                .long("reservations")
+                .required(false)
+                .possible_values(AVAILABLE_RESERVATIONS_CMDS),
+        )
+        .arg(
+            Arg::new("admin-token-file")
+                .help(
+                    "Serve the admin gRPC service, authenticating requests with the token in this file. Rotated mnemonics are exported sealed, so `--mnemonic-seal` and `--export-passphrase-file` are required.",
+                )
+                .long("admin-token-file")
+                .required(false)
+                .global(true)
+                .env(ADMIN_TOKEN_FILE_ENV_VAR),
+        )
+        .arg(
+            Arg::new("undelete-window")
+                .help(
+                    "Hours during which a deleted gg20 key can be undeleted. Afterwards, the key is purged from the kv store.",
+                )
+                .long("undelete-window")
+                .required(false)
+                .global(true)
+                .default_value(DEFAULT_UNDELETE_WINDOW_HOURS),
+        )
         .arg(
             Arg::new("directory")
                 .long("directory")
                 .short('d')
                 .required(false)
+                .global(true)
                 .env(TOFND_HOME_ENV_VAR)
                 .default_value(default_dir),
+        )
+        .arg(
+            Arg::new("output")
+                .help(
+                    "Format of the results of commands that exit tofnd. With `json`, the result is printed to stdout as a json object and logs are written to stderr.",
+                )
+                .long("output")
+                .required(false)
+                .global(true)
+                .default_value(DEFAULT_OUTPUT_FORMAT)
+                .possible_values(AVAILABLE_OUTPUT_FORMATS),
+        )
+        .subcommand(
+            App::new("run").about("Run the gRPC daemon").arg(
+                Arg::new("auto")
+                    .help(
+                        "Import a mnemonic from the import file, or create one, if no mnemonic exists. See the `auto` mnemonic command.",
+                    )
+                    .long("auto")
+                    .required(false)
+                    .takes_value(false),
+            ),
+        )
+        .subcommand(
+            App::new("mnemonic")
+                .about("Manage the mnemonics of the kv store")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommands(
+                    MNEMONIC_SUBCOMMANDS
+                        .iter()
+                        .map(|(name, about)| App::new(*name).about(*about)),
+                ),
+        )
+        .subcommand(
+            App::new("db")
+                .about("Maintain the kv store")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    App::new("reservations")
+                        .about(
+                            "List or clear key reservations left in the kv store and exit. Reservations from previous runs are otherwise removed at startup.",
+                        )
+                        .arg(
+                            Arg::new("command")
+                                .required(true)
+                                .possible_values(AVAILABLE_RESERVATIONS_CMDS),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("keys")
+                .about("Inspect the keys of the kv store. Only public key data is shown.")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(App::new("list").about("List all keys"))
+                .subcommand(
+                    App::new("show")
+                        .about("Show the keys of a key uid")
+                        .arg(Arg::new("key-uid").required(true)),
+                ),
         );
 
     #[cfg(feature = "malicious")]
@@ -115,10 +423,89 @@ pub fn parse_args() -> TofndResult<Config> {
             )
             .arg(Arg::new("victim").required(true).help("victim")),
     );
-    #[cfg(feature = "malicious")]
-    let behaviours = get_behaviour_matches(app.clone())?;
+    app
+}
 
-    let matches = app.get_matches();
+/// parses the reservations command `cmd`
+fn parse_reservations_cmd(cmd: &str) -> TofndResult<ReservationsCmd> {
+    match cmd {
+        "list" => Ok(ReservationsCmd::List),
+        "clear" => Ok(ReservationsCmd::Clear),
+        _ => Err(anyhow!("unknown reservations command {}", cmd)),
+    }
+}
+
+/// Selects the mnemonic command and the reservations command from the subcommand of `matches`,
+/// or from the deprecated flags if no subcommand is given.
+fn parse_cmds(matches: &ArgMatches) -> TofndResult<(Cmd, Option<ReservationsCmd>)> {
+    let subcommand = match matches.subcommand() {
+        // the malicious subcommand runs the daemon like no subcommand
+        Some(("malicious", _)) | None => {
+            let mnemonic_cmd = matches
+                .value_of("mnemonic-cmd")
+                .ok_or_else(|| anyhow!("cmd value"))?;
+            let reservations_cmd = match matches.value_of("reservations-cmd") {
+                Some(cmd) => Some(parse_reservations_cmd(cmd)?),
+                None => None,
+            };
+            return Ok((Cmd::from_string(mnemonic_cmd)?, reservations_cmd));
+        }
+        Some(subcommand) => subcommand,
+    };
+
+    if matches.occurrences_of("mnemonic-cmd") > 0 || matches.is_present("reservations-cmd") {
+        return Err(anyhow!(
+            "the deprecated --mnemonic and --reservations flags can't be combined with subcommands"
+        ));
+    }
+
+    match subcommand {
+        ("run", run_matches) => match run_matches.is_present("auto") {
+            true => Ok((Cmd::Auto, None)),
+            false => Ok((Cmd::Existing, None)),
+        },
+        ("mnemonic", mnemonic_matches) => {
+            let (cmd, _) = mnemonic_matches
+                .subcommand()
+                .ok_or_else(|| anyhow!("missing mnemonic command"))?;
+            Ok((Cmd::from_string(cmd)?, None))
+        }
+        ("db", db_matches) => match db_matches.subcommand() {
+            Some(("reservations", reservations_matches)) => {
+                let cmd = reservations_matches
+                    .value_of("command")
+                    .ok_or_else(|| anyhow!("reservations command value"))?;
+                Ok((Cmd::Existing, Some(parse_reservations_cmd(cmd)?)))
+            }
+            _ => Err(anyhow!("missing db command")),
+        },
+        // keys commands are parsed by [parse_keys_cmd]
+        ("keys", _) => Ok((Cmd::Existing, None)),
+        (subcommand, _) => Err(anyhow!("unknown subcommand {}", subcommand)),
+    }
+}
+
+/// parses the command of the `keys` subcommand of `matches`, if any
+fn parse_keys_cmd(matches: &ArgMatches) -> TofndResult<Option<KeysCmd>> {
+    let keys_matches = match matches.subcommand() {
+        Some(("keys", keys_matches)) => keys_matches,
+        _ => return Ok(None),
+    };
+    match keys_matches.subcommand() {
+        Some(("list", _)) => Ok(Some(KeysCmd::List)),
+        Some(("show", show_matches)) => {
+            let key_uid = show_matches
+                .value_of("key-uid")
+                .ok_or_else(|| anyhow!("key uid value"))?;
+            Ok(Some(KeysCmd::Show(key_uid.to_owned())))
+        }
+        _ => Err(anyhow!("missing keys command")),
+    }
+}
+
+fn config_from_matches(matches: &ArgMatches) -> TofndResult<Config> {
+    #[cfg(feature = "malicious")]
+    let behaviours = get_behaviour_matches(matches)?;
 
     let ip = matches
         .value_of("ip")
@@ -129,11 +516,46 @@ pub fn parse_args() -> TofndResult<Config> {
         .ok_or_else(|| anyhow!("port value"))?
         .parse::<u16>()?;
     let safe_keygen = !matches.is_present("unsafe");
-    let mnemonic_cmd = matches
-        .value_of("mnemonic")
-        .ok_or_else(|| anyhow!("cmd value"))?
-        .to_string();
-    let mnemonic_cmd = Cmd::from_string(&mnemonic_cmd)?;
+    // a node that generates keys of unsafe primes signs with them too
+    let allow_unsafe_keys = !safe_keygen || matches.is_present("allow-unsafe-keys");
+    let (mnemonic_cmd, reservations_cmd) = parse_cmds(matches)?;
+    let keys_cmd = parse_keys_cmd(matches)?;
+    let mnemonic_config = MnemonicConfig {
+        language: match matches.value_of("mnemonic-language") {
+            Some(code) => Some(
+                MnemonicLanguage::from_code(code)
+                    .ok_or_else(|| anyhow!("unknown mnemonic language {}", code))?,
+            ),
+            None => None,
+        },
+        word_count: match matches.value_of("mnemonic-words") {
+            Some(words) => Some(words.parse::<usize>()?),
+            None => None,
+        },
+        use_passphrase: matches.is_present("mnemonic-passphrase"),
+        passphrase_file: matches
+            .value_of("mnemonic-passphrase-file")
+            .map(PathBuf::from),
+        shares: match matches.value_of("mnemonic-shares") {
+            Some(scheme) => Some(parse_share_scheme(scheme)?),
+            None => None,
+        },
+        share_files: matches
+            .values_of("mnemonic-share-file")
+            .map(|files| files.map(PathBuf::from).collect())
+            .unwrap_or_default(),
+        seal_export: matches.is_present("mnemonic-seal"),
+        export_path: matches.value_of("export-path").map(PathBuf::from),
+        to_stdout: matches.is_present("to-stdout"),
+        import_path: matches.value_of("import-path").map(PathBuf::from),
+        export_passphrase_file: matches
+            .value_of("export-passphrase-file")
+            .map(PathBuf::from),
+        index: match matches.value_of("mnemonic-index") {
+            Some(index) => Some(index.parse::<u32>()?),
+            None => None,
+        },
+    };
     let tofnd_path = matches
         .value_of("directory")
         .ok_or_else(|| anyhow!("directory value"))?
@@ -142,15 +564,131 @@ pub fn parse_args() -> TofndResult<Config> {
         true => PasswordMethod::NoPassword,
         false => PasswordMethod::Prompt,
     };
+    let admin_token_file = matches.value_of("admin-token-file").map(PathBuf::from);
+    let undelete_window = matches
+        .value_of("undelete-window")
+        .ok_or_else(|| anyhow!("undelete window value"))?
+        .parse::<u64>()?;
+    let undelete_window = undelete_window
+        .checked_mul(60 * 60)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("undelete window of {} hours is too long", undelete_window))?;
+    let output = match matches.value_of("output") {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
 
     Ok(Config {
         ip,
         port,
         safe_keygen,
+        allow_unsafe_keys,
         mnemonic_cmd,
+        mnemonic_config,
         tofnd_path,
         password_method,
+        reservations_cmd,
+        keys_cmd,
+        admin_token_file,
+        undelete_window,
+        output,
         #[cfg(feature = "malicious")]
         behaviours,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> TofndResult<Config> {
+        let matches = app("default_dir").try_get_matches_from(args)?;
+        config_from_matches(&matches)
+    }
+
+    #[test]
+    fn test_subcommands() {
+        let cfg = parse(&["tofnd", "run"]).unwrap();
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Existing));
+        assert!(cfg.reservations_cmd.is_none());
+        assert_eq!(cfg.output, OutputFormat::Text);
+
+        let cfg = parse(&["tofnd", "run", "--auto"]).unwrap();
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Auto));
+
+        let cfg = parse(&["tofnd", "mnemonic", "rotate"]).unwrap();
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Rotate));
+        assert!(cfg.mnemonic_cmd.exit_after_cmd());
+
+        let cfg = parse(&["tofnd", "db", "reservations", "clear"]).unwrap();
+        assert!(matches!(cfg.reservations_cmd, Some(ReservationsCmd::Clear)));
+        assert!(cfg.keys_cmd.is_none());
+
+        let cfg = parse(&["tofnd", "keys", "list"]).unwrap();
+        assert!(matches!(cfg.keys_cmd, Some(KeysCmd::List)));
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Existing));
+        let cfg = parse(&["tofnd", "keys", "show", "key"]).unwrap();
+        assert!(matches!(cfg.keys_cmd, Some(KeysCmd::Show(key_uid)) if key_uid == "key"));
+
+        // daemon commands are not mnemonic subcommands
+        assert!(parse(&["tofnd", "mnemonic", "existing"]).is_err());
+        assert!(parse(&["tofnd", "mnemonic"]).is_err());
+        assert!(parse(&["tofnd", "db", "reservations", "drop"]).is_err());
+        assert!(parse(&["tofnd", "keys", "show"]).is_err());
+    }
+
+    #[test]
+    fn test_global_options() {
+        // options are accepted before and after subcommands
+        for args in [
+            &[
+                "tofnd", "-d", "root", "--output", "json", "mnemonic", "list",
+            ][..],
+            &[
+                "tofnd", "mnemonic", "list", "-d", "root", "--output", "json",
+            ][..],
+        ] {
+            let cfg = parse(args).unwrap();
+            assert!(matches!(cfg.mnemonic_cmd, Cmd::List));
+            assert_eq!(cfg.tofnd_path, PathBuf::from("root"));
+            assert_eq!(cfg.output, OutputFormat::Json);
+        }
+
+        let cfg = parse(&["tofnd", "mnemonic", "export", "--mnemonic-index", "2"]).unwrap();
+        assert_eq!(cfg.mnemonic_config.index, Some(2));
+
+        let cfg = parse(&["tofnd", "run", "--undelete-window", "2"]).unwrap();
+        assert_eq!(cfg.undelete_window, Duration::from_secs(2 * 60 * 60));
+        assert!(parse(&["tofnd", "run", "--undelete-window", &u64::MAX.to_string()]).is_err());
+        let cfg = parse(&["tofnd", "run"]).unwrap();
+        assert!(cfg.safe_keygen && !cfg.allow_unsafe_keys);
+        let cfg = parse(&["tofnd", "run", "--allow-unsafe-keys"]).unwrap();
+        assert!(cfg.safe_keygen && cfg.allow_unsafe_keys);
+        let cfg = parse(&["tofnd", "run", "--unsafe"]).unwrap();
+        assert!(!cfg.safe_keygen && cfg.allow_unsafe_keys);
+    }
+
+    #[test]
+    fn test_deprecated_flags() {
+        let cfg = parse(&["tofnd"]).unwrap();
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Existing));
+
+        let cfg = parse(&["tofnd", "-m", "create"]).unwrap();
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Create));
+
+        // entrypoint.sh runs the daemon this way when it is given extra arguments
+        let cfg = parse(&["tofnd", "-m", "auto", "--import-path", "import"]).unwrap();
+        assert!(matches!(cfg.mnemonic_cmd, Cmd::Auto));
+        assert_eq!(
+            cfg.mnemonic_config.import_path,
+            Some(PathBuf::from("import"))
+        );
+
+        let cfg = parse(&["tofnd", "--reservations", "list"]).unwrap();
+        assert!(matches!(cfg.reservations_cmd, Some(ReservationsCmd::List)));
+
+        // the flags don't mix with subcommands
+        assert!(parse(&["tofnd", "-m", "create", "mnemonic", "rotate"]).is_err());
+        assert!(parse(&["tofnd", "--reservations", "list", "run"]).is_err());
+    }
+}
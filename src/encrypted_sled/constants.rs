@@ -3,3 +3,5 @@ pub(super) const PASSWORD_VERIFICATION_KEY: &str = "verification_key";
 pub(super) const PASSWORD_VERIFICATION_VALUE: &str = "verification_value";
 pub(super) const PASSWORD_SALT_KEY: &[u8] = b"password_salt_key";
 pub(super) const UNSAFE_PASSWORD: &str = "tofnd_unsafe_password";
+/// name of the tree that holds the password salt and verification entries
+pub(super) const ENCRYPTION_TREE: &str = "encryption";
@@ -3,8 +3,12 @@
 //! A new random [XChaCha20Nonce] is created every time a new value needs to be
 //! inserted, forming a [EncryptedRecord]:<encrypted value, nonce>. The nonce is later
 //! used to decrypt and retrieve the originally inserted value.
+//! Every [EncryptedDb] operates on a single [sled::Tree]; trees of the same
+//! database are encrypted with the same cipher. See [EncryptedDb::open_tree].
 
 use std::convert::TryInto;
+use std::ops::Bound;
+use std::sync::Arc;
 
 use chacha20poly1305::aead::{AeadInPlace, NewAead};
 use chacha20poly1305::{self, XChaCha20Poly1305};
@@ -20,12 +24,13 @@ use super::result::{EncryptedDbError::*, EncryptedDbResult};
 
 /// A [sled] kv store with [XChaCha20Poly1305] value encryption.
 pub struct EncryptedDb {
-    kv: sled::Db,
-    cipher: XChaCha20Poly1305,
+    db: sled::Db,
+    kv: sled::Tree,
+    cipher: Arc<XChaCha20Poly1305>,
 }
 
 impl EncryptedDb {
-    /// create a new [EncryptedDb] that wraps sled::open(db_name).
+    /// create a new [EncryptedDb] that wraps sled::open(db_name) and operates on its default tree.
     /// Retrieves [XChaCha20Entropy] from a password-based-key-derivation-function and
     /// verifies that the password is valid.
     /// See [crate::password] for more info on pdkdf.
@@ -33,40 +38,74 @@ impl EncryptedDb {
     where
         P: AsRef<std::path::Path>,
     {
-        let kv = sled::open(db_name).map_err(CorruptedKv)?;
+        let db = sled::open(db_name).map_err(CorruptedKv)?;
+        let meta = db.open_tree(ENCRYPTION_TREE)?;
+        Self::migrate_encryption_metadata(&db, &meta)?;
 
-        let password_salt: PasswordSalt = if kv.was_recovered() {
+        let password_salt: PasswordSalt = if db.was_recovered() {
             // existing kv: get the existing password salt
-            kv.get(PASSWORD_SALT_KEY)?
+            meta.get(PASSWORD_SALT_KEY)?
                 .ok_or(MissingPasswordSalt)?
                 .try_into()?
         } else {
             // new kv: choose a new password salt and store it
             let mut password_salt = [0u8; 32];
             rand::thread_rng().fill_bytes(&mut password_salt);
-            kv.insert(PASSWORD_SALT_KEY, &password_salt)?;
+            meta.insert(PASSWORD_SALT_KEY, &password_salt)?;
             password_salt.into()
         };
 
         // zeroize key since we are no longer using it after creating cipher
         let mut key = Self::chacha20poly1305_kdf(password, password_salt)?;
-        let cipher = XChaCha20Poly1305::new(&key);
+        let cipher = Arc::new(XChaCha20Poly1305::new(&key));
         key.zeroize();
 
-        let encrypted_db = EncryptedDb { kv, cipher };
+        let encrypted_meta = EncryptedDb {
+            db: db.clone(),
+            kv: meta,
+            cipher: cipher.clone(),
+        };
 
         // verify that [password] is correct
-        if encrypted_db.kv.was_recovered() {
+        if db.was_recovered() {
             // existing kv: can we decrypt the verification value?
-            encrypted_db
+            encrypted_meta
                 .get(PASSWORD_VERIFICATION_KEY)
                 .map_err(|_| WrongPassword)?;
         } else {
             // new kv: encrypt the verification value
-            encrypted_db.insert(PASSWORD_VERIFICATION_KEY, PASSWORD_VERIFICATION_VALUE)?;
+            encrypted_meta.insert(PASSWORD_VERIFICATION_KEY, PASSWORD_VERIFICATION_VALUE)?;
         }
 
-        Ok(encrypted_db)
+        let kv = sled::Tree::clone(&db);
+        Ok(EncryptedDb { db, kv, cipher })
+    }
+
+    /// Open the tree `name` of the same database. Values are encrypted with the same cipher.
+    pub fn open_tree(&self, name: &str) -> EncryptedDbResult<Self> {
+        if name == ENCRYPTION_TREE {
+            return Err(ReservedTree(name.to_string()));
+        }
+        Ok(EncryptedDb {
+            db: self.db.clone(),
+            kv: self.db.open_tree(name)?,
+            cipher: self.cipher.clone(),
+        })
+    }
+
+    /// Older versions of tofnd stored the password salt and verification entries
+    /// in the default tree. Move them to `meta`.
+    /// Entries are copied before they are removed, so an interrupted migration can be resumed.
+    fn migrate_encryption_metadata(db: &sled::Db, meta: &sled::Tree) -> EncryptedDbResult<()> {
+        for key in [PASSWORD_SALT_KEY, PASSWORD_VERIFICATION_KEY.as_bytes()] {
+            if let Some(value) = db.get(key)? {
+                if !meta.contains_key(key)? {
+                    meta.insert(key, value)?;
+                }
+                db.remove(key)?;
+            }
+        }
+        Ok(())
     }
 
     fn chacha20poly1305_kdf(
@@ -174,13 +213,77 @@ impl EncryptedDb {
         self.decrypt(prev_val)
     }
 
+    /// Iterate over all `(key, decrypted value)` pairs of the `Tree`.
+    pub fn iter(&self) -> impl Iterator<Item = EncryptedDbResult<(IVec, IVec)>> + '_ {
+        self.decrypt_entries(self.kv.iter())
+    }
+
+    /// Iterate over all `(key, decrypted value)` pairs whose key starts with `prefix`.
+    /// If `after` is given, iteration starts at the first key after it.
+    /// Values are decrypted lazily, one entry at a time.
+    pub fn scan_prefix(
+        &self,
+        prefix: &[u8],
+        after: Option<&[u8]>,
+    ) -> impl Iterator<Item = EncryptedDbResult<(IVec, IVec)>> + '_ {
+        self.decrypt_entries(self.range_with_prefix(prefix, after))
+    }
+
+    /// Iterate over all keys that start with `prefix`. No value is decrypted.
+    /// If `after` is given, iteration starts at the first key after it.
+    pub fn keys_with_prefix(
+        &self,
+        prefix: &[u8],
+        after: Option<&[u8]>,
+    ) -> impl Iterator<Item = EncryptedDbResult<IVec>> {
+        self.range_with_prefix(prefix, after)
+            .map(|res| res.map(|(key, _)| key).map_err(SledError))
+    }
+
+    /// the entries of the `Tree` whose key starts with `prefix`, starting after the key `after`, if any
+    fn range_with_prefix(
+        &self,
+        prefix: &[u8],
+        after: Option<&[u8]>,
+    ) -> impl Iterator<Item = sled::Result<(IVec, IVec)>> {
+        let start = match after {
+            Some(after) => Bound::Excluded(after.to_vec()),
+            None => Bound::Included(prefix.to_vec()),
+        };
+        let prefix = prefix.to_vec();
+        self.kv
+            .range::<Vec<u8>, _>((start, Bound::Unbounded))
+            .take_while(move |res| match res {
+                Ok((key, _)) => key.starts_with(&prefix),
+                Err(_) => true,
+            })
+    }
+
+    /// decrypt the values of an iterator over encrypted entries
+    fn decrypt_entries(
+        &self,
+        entries: impl Iterator<Item = sled::Result<(IVec, IVec)>>,
+    ) -> impl Iterator<Item = EncryptedDbResult<(IVec, IVec)>> + '_ {
+        entries.map(move |res| {
+            let (key, record_bytes) = res?;
+            let record = EncryptedRecord::from_bytes(&record_bytes)?;
+            Ok((key, self.decrypt_record_value(record)?))
+        })
+    }
+
+    /// Returns `true` if `key` was used for password salt or password verification data
+    /// in the default tree by older versions of tofnd.
+    pub fn is_encryption_metadata_key(key: &[u8]) -> bool {
+        key == PASSWORD_SALT_KEY || key == PASSWORD_VERIFICATION_KEY.as_bytes()
+    }
+
     /// Returns true if the database was recovered from a previous process.
     pub fn was_recovered(&self) -> bool {
-        self.kv.was_recovered()
+        self.db.was_recovered()
     }
 
     #[cfg(test)]
     pub fn flush(&self) -> EncryptedDbResult<usize> {
-        Ok(self.kv.flush()?)
+        Ok(self.db.flush()?)
     }
 }
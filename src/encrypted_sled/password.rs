@@ -54,7 +54,7 @@ impl PasswordMethod {
         Ok(match self {
             Self::NoPassword => Password(UNSAFE_PASSWORD.to_string()),
             Self::Prompt => {
-                println!("Please type your tofnd password:");
+                eprintln!("Please type your tofnd password:");
                 Password(read_password()?)
             }
         })
@@ -24,6 +24,8 @@ pub enum EncryptedDbError {
     WrongPassword,
     #[error("Missing password salt")]
     MissingPasswordSalt,
+    #[error("Tree <{0}> is reserved")]
+    ReservedTree(String),
     #[error("Malformed password salt: {0}")]
     MalformedPasswordSalt(#[from] std::array::TryFromSliceError),
 }
@@ -85,6 +85,124 @@ fn test_large_input() {
     assert_eq!(res, Some(sled::IVec::from(large_value)));
 }
 
+#[test]
+fn test_iter() {
+    let db_path = testdir!("iter");
+    let db = EncryptedDb::open(&db_path, get_test_password()).unwrap();
+
+    db.insert("key1", "value1").unwrap();
+    db.insert("key2", "value2").unwrap();
+
+    // password salt and verification entries are not returned
+    let res = db.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(
+        res,
+        vec![
+            (sled::IVec::from("key1"), sled::IVec::from("value1")),
+            (sled::IVec::from("key2"), sled::IVec::from("value2")),
+        ]
+    );
+}
+
+#[test]
+fn test_scan_prefix() {
+    let db_path = testdir!("scan_prefix");
+    let db = EncryptedDb::open(&db_path, get_test_password()).unwrap();
+
+    db.insert("a_1", "value1").unwrap();
+    db.insert("a_2", "value2").unwrap();
+    db.insert("b_1", "value3").unwrap();
+
+    let res = db
+        .scan_prefix(b"a_", None)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            (sled::IVec::from("a_1"), sled::IVec::from("value1")),
+            (sled::IVec::from("a_2"), sled::IVec::from("value2")),
+        ]
+    );
+
+    let res = db
+        .keys_with_prefix(b"b_", None)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(res, vec![sled::IVec::from("b_1")]);
+
+    // iteration resumes after a key
+    let res = db
+        .scan_prefix(b"a_", Some("a_1".as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![(sled::IVec::from("a_2"), sled::IVec::from("value2"))]
+    );
+    let res = db
+        .keys_with_prefix(b"a_", Some("a_2".as_bytes()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert!(res.is_empty());
+
+    // password salt and verification entries are not returned
+    let res = db
+        .keys_with_prefix(b"", None)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(res.len(), 3);
+}
+
+#[test]
+fn test_open_tree() {
+    let db_path = testdir!("open_tree");
+    let db = EncryptedDb::open(&db_path, get_test_password()).unwrap();
+    let tree = db.open_tree("tree").unwrap();
+
+    db.insert("key", "value1").unwrap();
+    tree.insert("key", "value2").unwrap();
+
+    // same key in different trees holds different values
+    assert_eq!(db.get("key").unwrap(), Some(sled::IVec::from("value1")));
+    assert_eq!(tree.get("key").unwrap(), Some(sled::IVec::from("value2")));
+
+    // encryption metadata tree can't be opened
+    assert!(matches!(
+        db.open_tree(super::constants::ENCRYPTION_TREE),
+        Err(super::result::EncryptedDbError::ReservedTree(_))
+    ));
+}
+
+#[test]
+fn test_migrate_encryption_metadata() {
+    let db_path = testdir!("migrate_encryption_metadata");
+    let db = EncryptedDb::open(&db_path, get_test_password()).unwrap();
+    db.flush().unwrap();
+    drop(db);
+
+    // move encryption metadata to the default tree, as stored by older versions
+    {
+        let raw_db = sled::open(&db_path).unwrap();
+        let meta = raw_db.open_tree(super::constants::ENCRYPTION_TREE).unwrap();
+        for (key, value) in meta.iter().map(|entry| entry.unwrap()) {
+            raw_db.insert(&key, value).unwrap();
+            meta.remove(&key).unwrap();
+        }
+        raw_db.flush().unwrap();
+    }
+
+    // metadata is migrated and the password is still verified
+    let db = EncryptedDb::open(&db_path, get_test_password()).unwrap();
+    assert_eq!(db.iter().count(), 0);
+    drop(db);
+
+    assert!(matches!(
+        EncryptedDb::open(&db_path, Password::from("wrong password")),
+        Err(super::result::EncryptedDbError::WrongPassword)
+    ));
+}
+
 pub fn get_test_password() -> Password {
     crate::encrypted_sled::PasswordMethod::NoPassword
         .execute()
@@ -0,0 +1,226 @@
+//! Errors of the gRPC services [crate::gg20] and [crate::multisig].
+//!
+//! Every [ServiceError] carries a stable [ServiceError::code] and maps to a [tonic::Code].
+//! Along with the message, the code is sent to the client as `tofnd-error-code` metadata,
+//! and `tofnd-retryable` tells the client whether the same request may succeed if retried.
+
+use crate::{
+    kv_manager::error::{InnerKvError, KvError},
+    mnemonic::{FileIoError, InnerMnemonicError},
+};
+use tonic::{
+    metadata::{MetadataMap, MetadataValue},
+    Code, Status,
+};
+
+/// metadata key of [ServiceError::code]
+pub const ERROR_CODE_KEY: &str = "tofnd-error-code";
+
+/// metadata key of [ServiceError::is_retryable]
+pub const RETRYABLE_KEY: &str = "tofnd-retryable";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceError {
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("key already exists: {0}")]
+    KeyExists(String),
+    #[error("key not found: {0}")]
+    KeyNotFound(String),
+    #[error("mnemonic unavailable: {0}")]
+    MnemonicUnavailable(String),
+    #[error("kv store error: {0}")]
+    KvStore(String),
+    #[error("protocol error: {0}")]
+    Protocol(String),
+    #[error("stream closed: {0}")]
+    StreamClosed(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+    #[error("unauthenticated: {0}")]
+    Unauthenticated(String),
+    #[error("unsafe key: {0}")]
+    UnsafeKey(String),
+    #[error("key reserved: {0}")]
+    KeyReserved(String),
+    #[error("incompatible value: {0}")]
+    IncompatibleValue(String),
+    #[error("export exists: {0}")]
+    ExportExists(String),
+}
+pub type ServiceResult<Success> = Result<Success, ServiceError>;
+
+use ServiceError::*;
+
+impl ServiceError {
+    /// Stable code of the error. Codes are never changed or reused.
+    pub fn code(&self) -> &'static str {
+        match self {
+            InvalidArgument(_) => "INVALID_ARGUMENT",
+            KeyExists(_) => "KEY_EXISTS",
+            KeyNotFound(_) => "KEY_NOT_FOUND",
+            MnemonicUnavailable(_) => "MNEMONIC_UNAVAILABLE",
+            KvStore(_) => "KV_STORE",
+            Protocol(_) => "PROTOCOL",
+            StreamClosed(_) => "STREAM_CLOSED",
+            Internal(_) => "INTERNAL",
+            Unauthenticated(_) => "UNAUTHENTICATED",
+            UnsafeKey(_) => "UNSAFE_KEY",
+            KeyReserved(_) => "KEY_RESERVED",
+            IncompatibleValue(_) => "INCOMPATIBLE_VALUE",
+            ExportExists(_) => "EXPORT_EXISTS",
+        }
+    }
+
+    /// gRPC status code of the error
+    pub fn grpc_code(&self) -> Code {
+        match self {
+            InvalidArgument(_) => Code::InvalidArgument,
+            KeyExists(_) => Code::AlreadyExists,
+            KeyNotFound(_) => Code::NotFound,
+            MnemonicUnavailable(_) => Code::FailedPrecondition,
+            KvStore(_) => Code::Unavailable,
+            Protocol(_) => Code::Aborted,
+            StreamClosed(_) => Code::Cancelled,
+            Internal(_) => Code::Internal,
+            Unauthenticated(_) => Code::Unauthenticated,
+            UnsafeKey(_) => Code::FailedPrecondition,
+            KeyReserved(_) => Code::Aborted,
+            IncompatibleValue(_) => Code::FailedPrecondition,
+            ExportExists(_) => Code::FailedPrecondition,
+        }
+    }
+
+    /// Returns `true` if the same request may succeed if it is retried.
+    /// Errors caused by the request itself or by the state of tofnd are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            KvStore(_) | Protocol(_) | StreamClosed(_) | KeyReserved(_)
+        )
+    }
+
+    /// Message with the code of the error, for responses that can't carry metadata
+    pub fn to_response_string(&self) -> String {
+        format!("[{}] {}", self.code(), self)
+    }
+}
+
+impl From<ServiceError> for Status {
+    fn from(err: ServiceError) -> Self {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(ERROR_CODE_KEY, MetadataValue::from_static(err.code()));
+        let retryable = if err.is_retryable() { "true" } else { "false" };
+        metadata.insert(RETRYABLE_KEY, MetadataValue::from_static(retryable));
+        Status::with_metadata(err.grpc_code(), err.to_string(), metadata)
+    }
+}
+
+impl From<KvError> for ServiceError {
+    fn from(err: KvError) -> Self {
+        match err {
+            KvError::ReserveErr(InnerKvError::ReservedNameErr(_)) => {
+                InvalidArgument(err.to_string())
+            }
+            KvError::ReserveErr(InnerKvError::LogicalErr(_)) => KeyExists(err.to_string()),
+            KvError::GetErr(InnerKvError::LogicalErr(_)) => KeyNotFound(err.to_string()),
+            KvError::GetErr(InnerKvError::ReservedKeyErr(_)) => KeyReserved(err.to_string()),
+            KvError::GetErr(InnerKvError::IncompatibleValueErr(_)) => {
+                IncompatibleValue(err.to_string())
+            }
+            KvError::GetErr(InnerKvError::DeserializationErr)
+            | KvError::PutErr(InnerKvError::SerializationErr) => Internal(err.to_string()),
+            _ => KvStore(err.to_string()),
+        }
+    }
+}
+
+impl From<InnerMnemonicError> for ServiceError {
+    fn from(err: InnerMnemonicError) -> Self {
+        match err {
+            InnerMnemonicError::KvErr(KvError::GetErr(InnerKvError::LogicalErr(_))) => {
+                MnemonicUnavailable(err.to_string())
+            }
+            InnerMnemonicError::MissingPassphrase | InnerMnemonicError::WrongPassphrase => {
+                MnemonicUnavailable(err.to_string())
+            }
+            InnerMnemonicError::FileIoErr(FileIoError::Exists(_)) => ExportExists(err.to_string()),
+            InnerMnemonicError::KvErr(err) => err.into(),
+            _ => Internal(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kv_errors() {
+        let err: ServiceError =
+            KvError::ReserveErr(InnerKvError::LogicalErr("reserved".to_string())).into();
+        assert!(matches!(err, KeyExists(_)));
+        let err: ServiceError =
+            KvError::GetErr(InnerKvError::LogicalErr("no value".to_string())).into();
+        assert!(matches!(err, KeyNotFound(_)));
+        let err: ServiceError = KvError::GetErr(InnerKvError::DeserializationErr).into();
+        assert!(matches!(err, Internal(_)));
+
+        // reserved names, reserved keys and incompatible values are neither existing nor missing keys
+        let err: ServiceError =
+            KvError::ReserveErr(InnerKvError::ReservedNameErr("mnemonic".to_string())).into();
+        assert!(matches!(err, InvalidArgument(_)));
+        let err: ServiceError =
+            KvError::GetErr(InnerKvError::ReservedKeyErr("reserved".to_string())).into();
+        assert!(matches!(err, KeyReserved(_)));
+        assert!(err.is_retryable());
+        let err: ServiceError =
+            KvError::GetErr(InnerKvError::IncompatibleValueErr("version".to_string())).into();
+        assert!(matches!(err, IncompatibleValue(_)));
+        assert_eq!(err.grpc_code(), Code::FailedPrecondition);
+        let err: ServiceError = KvError::SendErr("closed".to_string()).into();
+        assert!(matches!(err, KvStore(_)));
+
+        let err: ServiceError = InnerMnemonicError::KvErr(KvError::GetErr(
+            InnerKvError::LogicalErr("no value".to_string()),
+        ))
+        .into();
+        assert!(matches!(err, MnemonicUnavailable(_)));
+        let err: ServiceError = InnerMnemonicError::WrongPassphrase.into();
+        assert!(matches!(err, MnemonicUnavailable(_)));
+        let err: ServiceError =
+            InnerMnemonicError::FileIoErr(FileIoError::Exists("export".into())).into();
+        assert!(matches!(err, ExportExists(_)));
+        assert_eq!(err.grpc_code(), Code::FailedPrecondition);
+    }
+
+    #[test]
+    fn status() {
+        let status = Status::from(KeyExists("key".to_string()));
+        assert_eq!(status.code(), Code::AlreadyExists);
+        assert_eq!(status.message(), "key already exists: key");
+        assert_eq!(status.metadata().get(ERROR_CODE_KEY).unwrap(), "KEY_EXISTS");
+        assert_eq!(status.metadata().get(RETRYABLE_KEY).unwrap(), "false");
+
+        let status = Status::from(StreamClosed("closed".to_string()));
+        assert_eq!(status.code(), Code::Cancelled);
+        assert_eq!(
+            status.metadata().get(ERROR_CODE_KEY).unwrap(),
+            "STREAM_CLOSED"
+        );
+        assert_eq!(status.metadata().get(RETRYABLE_KEY).unwrap(), "true");
+
+        let status = Status::from(UnsafeKey("key".to_string()));
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(status.metadata().get(ERROR_CODE_KEY).unwrap(), "UNSAFE_KEY");
+        assert_eq!(status.metadata().get(RETRYABLE_KEY).unwrap(), "false");
+    }
+
+    #[test]
+    fn response_string() {
+        assert_eq!(
+            InvalidArgument("bad".to_string()).to_response_string(),
+            "[INVALID_ARGUMENT] invalid argument: bad"
+        );
+    }
+}
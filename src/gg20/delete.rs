@@ -0,0 +1,160 @@
+//! This module handles the DeleteKey and UndeleteKey gRPCs.
+//! A deleted key is moved to a [Gg20Tombstone] along with the time of deletion. Until the undelete window
+//! of [crate::config::Config] has passed, the key can be undeleted. Afterwards, it is purged for good.
+//! Expired tombstones are purged at startup, every [PURGE_INTERVAL] while tofnd is running,
+//! and before every deletion or undeletion.
+//! Deletions, undeletions and purges hold the deletion lock of [KvManager], so that they never interleave.
+
+use super::{
+    proto,
+    service::Gg20Service,
+    types::{Gg20Tombstone, PartyInfo},
+};
+use std::time::Duration;
+use tokio::time::{self, Instant};
+use tokio_stream::StreamExt;
+
+// logging
+use tracing::{info, warn};
+
+// error handling
+use crate::error::{ServiceError::*, ServiceResult};
+
+use crate::{
+    kv_manager::{
+        error::{InnerKvError, KvError},
+        unix_timestamp, KvManager,
+    },
+    mnemonic::KeyScheme,
+};
+
+/// how often a running tofnd purges the deleted keys whose undelete window has passed
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Gg20Service {
+    pub(super) async fn handle_delete_key(
+        &self,
+        request: proto::DeleteKeyRequest,
+    ) -> ServiceResult<proto::DeleteKeyResponse> {
+        let _guard = self.kv_manager.lock_deletions().await;
+        purge_expired_keys(&self.kv_manager, self.cfg.undelete_window).await?;
+
+        let key_uid = request.key_uid;
+        let party_info = self.kv_manager.get::<PartyInfo>(&key_uid).await?;
+
+        // the public key confirms that the intended key is deleted
+        if party_info.common.encoded_pubkey() != request.pub_key {
+            return Err(InvalidArgument(format!(
+                "public key does not match key {}",
+                key_uid
+            )));
+        }
+
+        // the key uid may have been used again since an earlier deletion
+        if self.kv_manager.exists::<Gg20Tombstone>(&key_uid).await? {
+            warn!(
+                "Replacing tombstone of an earlier deletion of key {}",
+                key_uid
+            );
+            self.kv_manager.delete::<Gg20Tombstone>(&key_uid).await?;
+        }
+
+        let deleted_at = unix_timestamp();
+        let tombstone = Gg20Tombstone::new(party_info, deleted_at);
+        let reservation = self.kv_manager.reserve::<Gg20Tombstone>(&key_uid).await?;
+        self.kv_manager.put(reservation, tombstone).await?;
+
+        // the shares are kept in the tombstone, so they can be deleted now
+        self.kv_manager.delete::<PartyInfo>(&key_uid).await?;
+
+        Ok(proto::DeleteKeyResponse {
+            deleted_at,
+            purge_after: deleted_at.saturating_add(self.cfg.undelete_window.as_secs()),
+        })
+    }
+
+    pub(super) async fn handle_undelete_key(
+        &self,
+        request: proto::UndeleteKeyRequest,
+    ) -> ServiceResult<proto::UndeleteKeyResponse> {
+        let _guard = self.kv_manager.lock_deletions().await;
+        purge_expired_keys(&self.kv_manager, self.cfg.undelete_window).await?;
+
+        let key_uid = request.key_uid;
+        if !self.kv_manager.exists::<Gg20Tombstone>(&key_uid).await? {
+            return Err(KeyNotFound(format!(
+                "key {} was not deleted within the undelete window",
+                key_uid
+            )));
+        }
+        let tombstone = self.kv_manager.get::<Gg20Tombstone>(&key_uid).await?;
+
+        // fails if the key uid was used again since the deletion
+        let reservation = self.kv_manager.reserve::<PartyInfo>(&key_uid).await?;
+        self.kv_manager
+            .put(reservation, tombstone.party_info)
+            .await?;
+
+        self.kv_manager.delete::<Gg20Tombstone>(&key_uid).await?;
+
+        Ok(proto::UndeleteKeyResponse {})
+    }
+}
+
+/// Purges the deleted keys of `kv` whose undelete `window` has passed every [PURGE_INTERVAL],
+/// for as long as tofnd is running
+pub fn spawn_purge_task(kv: KvManager, window: Duration) {
+    tokio::spawn(async move {
+        let mut interval = time::interval_at(Instant::now() + PURGE_INTERVAL, PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match purge_deleted_keys(&kv, window).await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} deleted keys", purged),
+                Err(err) => warn!("Could not purge deleted keys: {}", err),
+            }
+        }
+    });
+}
+
+/// Purges the deleted keys of `kv` whose undelete `window` has passed, along with their origins and usage.
+/// Returns the number of purged keys.
+pub async fn purge_deleted_keys(kv: &KvManager, window: Duration) -> ServiceResult<usize> {
+    let _guard = kv.lock_deletions().await;
+    purge_expired_keys(kv, window).await
+}
+
+/// purges like [purge_deleted_keys]; the caller holds the deletion lock
+async fn purge_expired_keys(kv: &KvManager, window: Duration) -> ServiceResult<usize> {
+    let mut key_uids = vec![];
+    let mut keys = kv.keys_with_prefix::<Gg20Tombstone>("");
+    while let Some(key) = keys.next().await {
+        key_uids.push(key?);
+    }
+
+    let now = unix_timestamp();
+    let mut purged = 0;
+    for key_uid in key_uids {
+        let tombstone = match kv.get::<Gg20Tombstone>(&key_uid).await {
+            Ok(tombstone) => tombstone,
+            // the tombstone is still being written
+            Err(KvError::GetErr(InnerKvError::ReservedKeyErr(_))) => continue,
+            Err(err) => return Err(err.into()),
+        };
+        if tombstone.deleted_at.saturating_add(window.as_secs()) > now {
+            continue;
+        }
+
+        kv.delete::<Gg20Tombstone>(&key_uid).await?;
+        // the key uid may have been used again since the deletion
+        if !kv.exists::<PartyInfo>(&key_uid).await? {
+            kv.forget_key_origin(KeyScheme::Gg20, &key_uid).await?;
+            kv.forget_key_usage(&key_uid).await?;
+        }
+
+        info!("Purged key {} deleted at {}", key_uid, tombstone.deleted_at);
+        purged += 1;
+    }
+
+    Ok(purged)
+}
@@ -0,0 +1,48 @@
+//! This module handles the GetKeyInfo gRPC.
+//! Returns the public information of a stored key, so that clients don't have to keep the keygen output.
+//! No secret material is part of the response. Metadata and usage of the key are included.
+
+use super::{
+    proto::{self, get_key_info_response::KeygenMode as ProtoKeygenMode},
+    service::Gg20Service,
+    types::{KeygenMode, PartyInfo},
+};
+
+// error handling
+use crate::error::{ServiceError::*, ServiceResult};
+
+impl Gg20Service {
+    pub(super) async fn handle_get_key_info(
+        &self,
+        request: proto::GetKeyInfoRequest,
+    ) -> ServiceResult<proto::GetKeyInfoResponse> {
+        let party_info = self.kv_manager.get::<PartyInfo>(&request.key_uid).await?;
+        let all_shares = party_info
+            .common
+            .all_shares_bytes()
+            .map_err(|_| Internal("unable to call all_shares_bytes()".to_string()))?;
+        let usage = self.kv_manager.key_usage(&request.key_uid).await?;
+        let info = party_info.key_info(&request.key_uid, usage);
+
+        let keygen_mode = match info.keygen_mode {
+            Some(KeygenMode::Safe) => ProtoKeygenMode::Safe,
+            Some(KeygenMode::Unsafe) => ProtoKeygenMode::Unsafe,
+            None => ProtoKeygenMode::Unknown,
+        };
+
+        // counts and indices were checked against the max share count at keygen, so they fit in u32
+        Ok(proto::GetKeyInfoResponse {
+            pub_key: info.pub_key,
+            threshold: info.threshold as u32,
+            party_uids: info.party_uids,
+            party_share_counts: info.share_counts.iter().map(|&c| c as u32).collect(),
+            my_party_index: info.index as u32,
+            all_shares,
+            created_at: info.created_at.unwrap_or_default(),
+            keygen_mode: keygen_mode as i32,
+            tofnd_version: info.tofnd_version.unwrap_or_default(),
+            sign_count: info.usage.sign_count,
+            last_signed_at: info.usage.last_signed_at.unwrap_or_default(),
+        })
+    }
+}
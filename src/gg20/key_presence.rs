@@ -2,24 +2,28 @@
 //! Request includes [proto::message_in::Data::KeyPresenceRequest] struct and encrypted recovery info.
 //! The recovery info is decrypted by party's mnemonic seed and saved in the KvStore.
 
-use super::{proto, service::Gg20Service};
+use super::{proto, service::Gg20Service, types::PartyInfo};
 
 // logging
 use tracing::info;
 
 // error handling
-use crate::TofndResult;
+use crate::error::ServiceResult;
 
 impl Gg20Service {
     pub(super) async fn handle_key_presence(
         &self,
         request: proto::KeyPresenceRequest,
-    ) -> TofndResult<proto::key_presence_response::Response> {
+    ) -> ServiceResult<proto::key_presence_response::Response> {
         // check if mnemonic is available
         let _ = self.kv_manager.seed().await?;
 
         // check if requested key exists
-        if self.kv_manager.kv().exists(&request.key_uid).await? {
+        if self
+            .kv_manager
+            .exists::<PartyInfo>(&request.key_uid)
+            .await?
+        {
             info!(
                 "Found session-id {} in kv store during key presence check",
                 request.key_uid
@@ -1,6 +1,6 @@
 //! This module creates and executes the keygen protocol
 //! On success it returns [super::TofnKeygenOutput]. A successful [Keygen] can produce either an Ok(SecretKeyShare) of an Err(Vec<Vec<Crime>>).
-//! On failure it returns [anyhow!] error if [Keygen] struct cannot be instantiated.
+//! On failure it returns a [crate::error::ServiceError] if [Keygen] struct cannot be instantiated.
 
 use super::{
     proto,
@@ -18,7 +18,7 @@ use tofn::{
 use tracing::{info, Span};
 
 // error handling
-use anyhow::anyhow;
+use crate::error::ServiceError;
 
 impl Gg20Service {
     /// create a new keygen.
@@ -41,7 +41,7 @@ impl Gg20Service {
     }
 
     /// create and execute keygen protocol and returning the result.
-    /// if the protocol cannot be instantiated, return a [anyhow!]
+    /// if the protocol cannot be instantiated, return a [ServiceError::Internal]
     pub(super) async fn execute_keygen(
         &self,
         chans: ProtocolCommunication<
@@ -56,7 +56,9 @@ impl Gg20Service {
         let keygen = self
             .new_keygen(party_share_counts, ctx)
             .await
-            .map_err(|_| anyhow!("keygen protocol instantiation failed"))?;
+            .map_err(|_| {
+                ServiceError::Internal("keygen protocol instantiation failed".to_string())
+            })?;
 
         // execute protocol and wait for completion
         let protocol_result = protocol::execute_protocol(
@@ -68,8 +70,9 @@ impl Gg20Service {
         )
         .await;
 
-        let res = protocol_result
-            .map_err(|err| anyhow!("Keygen was not completed due to error: {}", err))?;
+        let res = protocol_result.map_err(|err| {
+            ServiceError::Protocol(format!("Keygen was not completed due to error: {}", err))
+        })?;
 
         info!("Keygen completed");
         Ok(res)
@@ -9,45 +9,44 @@ use futures_util::StreamExt;
 use tracing::Span;
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::*, ServiceResult};
 
 use super::{
     proto,
     types::{KeygenInitSanitized, MAX_PARTY_SHARE_COUNT, MAX_TOTAL_SHARE_COUNT},
     Gg20Service,
 };
-use crate::kv_manager::KeyReservation;
+use crate::{gg20::types::PartyInfo, kv_manager::KeyReservation};
 
 impl Gg20Service {
     /// Receives a message from the stream and tries to handle keygen init operations.
     /// On success, it reserves a key in the KVStrore and returns a sanitized struct ready to be used by the protocol.
-    /// On failure, returns a [KeygenInitError] and no changes are been made in the KvStore.
+    /// On failure, returns a [crate::error::ServiceError] and no changes are been made in the KvStore.
     pub(super) async fn handle_keygen_init(
         &self,
         stream: &mut tonic::Streaming<proto::MessageIn>,
         keygen_span: Span,
-    ) -> TofndResult<(KeygenInitSanitized, KeyReservation)> {
+    ) -> ServiceResult<(KeygenInitSanitized, KeyReservation)> {
         // try to receive message
         let msg = stream
             .next()
             .await
-            .ok_or_else(|| anyhow!("stream closed by client"))?
-            .map_err(|e| anyhow!("stream closed by server: {}", e))?;
+            .ok_or_else(|| StreamClosed("stream closed by client".to_string()))?
+            .map_err(|e| StreamClosed(format!("stream closed by server: {}", e)))?;
 
         // try to get message data
         let msg_data = msg
             .data
-            .ok_or_else(|| anyhow!("received `None` message from client"))?;
+            .ok_or_else(|| InvalidArgument("received `None` message from client".to_string()))?;
 
         // check if message is of expected type
         let keygen_init = match msg_data {
             proto::message_in::Data::KeygenInit(k) => k,
             _ => {
-                return Err(anyhow!(
+                return Err(InvalidArgument(format!(
                     "wrong message type; expecting KeygenInit, got {:?}",
                     msg_data
-                ))
+                )))
             }
         };
 
@@ -66,18 +65,15 @@ impl Gg20Service {
     async fn process_keygen_init(
         &self,
         keygen_init: proto::KeygenInit,
-    ) -> TofndResult<(KeygenInitSanitized, KeyReservation)> {
+    ) -> ServiceResult<(KeygenInitSanitized, KeyReservation)> {
         // try to sanitize arguments
-        let keygen_init = Self::keygen_sanitize_args(keygen_init)
-            .map_err(|err| anyhow!("failed to sanitize KeygenInit: {}", err))?;
+        let keygen_init = Self::keygen_sanitize_args(keygen_init)?;
 
         // reserve key
         let key_uid_reservation = self
             .kv_manager
-            .kv()
-            .reserve_key(keygen_init.new_key_uid.clone())
-            .await
-            .map_err(|err| anyhow!("failed to reseve key: {}", err))?;
+            .reserve::<PartyInfo>(&keygen_init.new_key_uid)
+            .await?;
 
         // return sanitized keygen init and key reservation
         Ok((keygen_init, key_uid_reservation))
@@ -98,16 +94,19 @@ impl Gg20Service {
     ///   keygen_init.threshold = 1                    <- same as in input
     pub(crate) fn keygen_sanitize_args(
         args: proto::KeygenInit,
-    ) -> TofndResult<KeygenInitSanitized> {
+    ) -> ServiceResult<KeygenInitSanitized> {
         // convert `u32`s to `usize`s
         use std::convert::TryFrom;
-        let my_index = usize::try_from(args.my_party_index)?;
-        let threshold = usize::try_from(args.threshold)?;
+        let my_index = usize::try_from(args.my_party_index)
+            .map_err(|err| InvalidArgument(format!("my_party_index: {}", err)))?;
+        let threshold = usize::try_from(args.threshold)
+            .map_err(|err| InvalidArgument(format!("threshold: {}", err)))?;
         let mut party_share_counts = args
             .party_share_counts
             .iter()
             .map(|i| usize::try_from(*i))
-            .collect::<Result<Vec<usize>, _>>()?;
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|err| InvalidArgument(format!("party_share_counts: {}", err)))?;
 
         // if share_counts are not provided, fall back to 1 share per party
         if party_share_counts.is_empty() {
@@ -116,47 +115,44 @@ impl Gg20Service {
 
         // assert that uids and party shares are alligned
         if args.party_uids.len() != party_share_counts.len() {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "uid vector and share counts vector not alligned: {:?}, {:?}",
-                args.party_uids,
-                party_share_counts,
-            ));
+                args.party_uids, party_share_counts,
+            )));
         }
 
         // check if my_index is inside party_uids
         if my_index >= args.party_uids.len() {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "my index is {}, but there are only {} parties.",
                 my_index,
                 args.party_uids.len(),
-            ));
+            )));
         }
 
         // if party's shares are above max, return error
         for party_share_count in &party_share_counts {
             if *party_share_count > MAX_PARTY_SHARE_COUNT {
-                return Err(anyhow!(
+                return Err(InvalidArgument(format!(
                     "party {} has {} shares, but maximum number of shares per party is {}.",
                     args.party_uids[my_index],
                     args.party_share_counts[my_index],
                     MAX_PARTY_SHARE_COUNT,
-                ));
+                )));
             }
         }
 
         let total_shares = party_share_counts.iter().sum::<usize>();
         if total_shares <= threshold {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "threshold is not satisfied: t = {}, total number of shares = {}",
-                threshold,
-                total_shares,
-            ));
+                threshold, total_shares,
+            )));
         } else if total_shares > MAX_TOTAL_SHARE_COUNT {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "total shares count is {}, but maximum number of share count is {}.",
-                total_shares,
-                MAX_PARTY_SHARE_COUNT,
-            ));
+                total_shares, MAX_PARTY_SHARE_COUNT,
+            )));
         }
 
         // sort uids and share counts
@@ -180,11 +176,11 @@ fn sort_uids_and_shares(
     my_index: usize,
     uids: Vec<String>,
     share_counts: Vec<usize>,
-) -> TofndResult<(usize, Vec<String>, Vec<usize>)> {
+) -> ServiceResult<(usize, Vec<String>, Vec<usize>)> {
     // save my uid
     let my_uid = uids
         .get(my_index)
-        .ok_or_else(|| anyhow!("Error: Index out of bounds"))?
+        .ok_or_else(|| InvalidArgument("Error: Index out of bounds".to_string()))?
         .clone();
 
     // create a vec of (uid, share_count) and sort it
@@ -196,14 +192,16 @@ fn sort_uids_and_shares(
     let old_len = sorted_uids.len();
     sorted_uids.dedup();
     if old_len != sorted_uids.len() {
-        return Err(anyhow!("Error: party_uid vector contained a duplicate"));
+        return Err(InvalidArgument(
+            "Error: party_uid vector contained a duplicate".to_string(),
+        ));
     }
 
     // find my new index
     let my_index = sorted_uids
         .iter()
         .position(|x| x == &my_uid)
-        .ok_or_else(|| anyhow!("Error: Lost my uid after sorting uids"))?;
+        .ok_or_else(|| Internal("Error: Lost my uid after sorting uids".to_string()))?;
 
     Ok((my_index, sorted_uids, sorted_share_counts))
 }
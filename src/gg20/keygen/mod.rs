@@ -34,8 +34,9 @@ use tokio::sync::{mpsc, oneshot};
 use tracing::{info, span, Level, Span};
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::*, ServiceResult};
+
+use crate::mnemonic::mnemonic_fingerprint;
 
 pub mod types;
 use types::*;
@@ -50,7 +51,7 @@ impl Gg20Service {
         mut stream_in: tonic::Streaming<proto::MessageIn>,
         mut stream_out_sender: mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
         keygen_span: Span,
-    ) -> TofndResult<()> {
+    ) -> ServiceResult<()> {
         // 1. Receive KeygenInit, open message, sanitize arguments -> init mod
         // 2. Spawn N keygen threads to execute the protocol in parallel; one of each of our shares -> execute mod
         // 3. Spawn 1 router thread to route messages from client to the respective keygen thread -> routing mod
@@ -66,10 +67,10 @@ impl Gg20Service {
         // find my share count to allocate channel vectors
         let my_share_count = keygen_init.my_shares_count();
         if my_share_count == 0 {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "Party {} has 0 shares assigned",
                 keygen_init.my_index
-            ));
+            )));
         }
 
         // create in and out channels for each share, and spawn as many threads
@@ -77,7 +78,12 @@ impl Gg20Service {
         let mut aggregator_receivers = Vec::with_capacity(my_share_count);
 
         // computation of (party_keypair, party_zksetup) is intensive so we compute them here once
-        let secret_recovery_key = self.kv_manager.seed().await?;
+        let secret_recovery_key = {
+            // wait for a rotation of the mnemonic to complete
+            let _guard = self.kv_manager.read_mnemonics().await;
+            self.kv_manager.seed().await?
+        };
+        let mnemonic = mnemonic_fingerprint(&secret_recovery_key)?;
         let session_nonce = keygen_init.new_key_uid.as_bytes();
 
         info!("Generating keypair for party {} ...", keygen_init.my_index);
@@ -92,7 +98,7 @@ impl Gg20Service {
                 session_nonce,
             ),
         }
-        .map_err(|_| anyhow!("Party keypair generation failed"))?;
+        .map_err(|_| Internal("Party keypair generation failed".to_string()))?;
 
         info!(
             "Finished generating keypair for party {}",
@@ -146,6 +152,7 @@ impl Gg20Service {
             &mut stream_out_sender,
             key_uid_reservation,
             keygen_init,
+            mnemonic,
         )
         .await?;
 
@@ -11,7 +11,11 @@ use super::{
     types::{BytesVec, KeygenInitSanitized, TofnKeygenOutput, TofndKeygenOutput},
     Gg20Service,
 };
-use crate::{gg20::types::PartyInfo, kv_manager::KeyReservation};
+use crate::{
+    gg20::types::{KeygenMode, PartyInfo},
+    kv_manager::KeyReservation,
+    mnemonic::{KeyScheme, MnemonicFingerprint},
+};
 
 // tonic cruft
 use tokio::sync::{
@@ -21,43 +25,29 @@ use tokio::sync::{
 use tonic::Status;
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
-
-use std::convert::TryInto;
+use crate::error::{ServiceError::*, ServiceResult};
 
 impl Gg20Service {
     /// aggregate results from all keygen threads, create a record and insert it in the KvStore
+    /// along with the origin of the key, the mnemonic of `mnemonic`
     pub(super) async fn aggregate_results(
         &self,
         aggregator_receivers: Vec<oneshot::Receiver<TofndKeygenOutput>>,
         stream_out_sender: &mut mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
         key_uid_reservation: KeyReservation,
         keygen_init: KeygenInitSanitized,
-    ) -> TofndResult<()> {
+        mnemonic: MnemonicFingerprint,
+    ) -> ServiceResult<()> {
         // wait all keygen threads and aggregate results
-        // can't use `map_err` because of `.await` func :(
-        let keygen_outputs = match Self::aggregate_keygen_outputs(aggregator_receivers).await {
-            Ok(keygen_outputs) => keygen_outputs,
-            Err(err) => {
-                self.kv_manager
-                    .kv()
-                    .unreserve_key(key_uid_reservation)
-                    .await;
-                return Err(anyhow!(
-                    "Error at Keygen output aggregation. Unreserving key {}",
-                    err
-                ));
-            }
-        };
+        // on failure, `key_uid_reservation` is dropped and the key is unreserved
+        let keygen_outputs = Self::aggregate_keygen_outputs(aggregator_receivers).await?;
 
         // try to process keygen outputs
         let (pub_key, group_recover_info, secret_key_shares) =
             Self::process_keygen_outputs(&keygen_init, keygen_outputs, stream_out_sender)?;
 
         // try to retrieve private recovery info from all shares
-        let private_recover_info =
-            Self::get_private_recovery_data(&secret_key_shares).map_err(|err| anyhow!(err))?;
+        let private_recover_info = Self::get_private_recovery_data(&secret_key_shares)?;
 
         // combine responses from all keygen threads to a single struct
         let kv_data = PartyInfo::get_party_info(
@@ -65,26 +55,33 @@ impl Gg20Service {
             keygen_init.party_uids.clone(),
             keygen_init.party_share_counts.clone(),
             keygen_init.my_index,
+            KeygenMode::from_safe_keygen(self.cfg.safe_keygen),
         );
 
-        // try to put data inside kv store
+        // record the origin first, so that the mnemonic is never pruned while the key is stored
         self.kv_manager
-            .kv()
-            .put(key_uid_reservation, kv_data.try_into()?)
-            .await
-            .map_err(|err| anyhow!(err))?;
+            .record_key_origin(KeyScheme::Gg20, &keygen_init.new_key_uid, mnemonic)
+            .await?;
+
+        // the key uid may have belonged to a deleted key; the new key has not signed yet
+        self.kv_manager
+            .forget_key_usage(&keygen_init.new_key_uid)
+            .await?;
+
+        // try to put data inside kv store
+        self.kv_manager.put(key_uid_reservation, kv_data).await?;
 
         // try to send result
-        Ok(
-            stream_out_sender.send(Ok(proto::MessageOut::new_keygen_result(
+        stream_out_sender
+            .send(Ok(proto::MessageOut::new_keygen_result(
                 &keygen_init.party_uids,
                 Ok(proto::KeygenOutput {
                     pub_key,
                     group_recover_info,
                     private_recover_info,
                 }),
-            )))?,
-        )
+            )))
+            .map_err(|_| StreamClosed("could not send keygen result".to_string()))
     }
 
     /// iterate all keygen outputs, and return data that need to be permenantly stored
@@ -95,7 +92,7 @@ impl Gg20Service {
         keygen_init: &KeygenInitSanitized,
         keygen_outputs: Vec<TofnKeygenOutput>,
         stream_out_sender: &mut mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
-    ) -> TofndResult<(BytesVec, BytesVec, Vec<SecretKeyShare>)> {
+    ) -> ServiceResult<(BytesVec, BytesVec, Vec<SecretKeyShare>)> {
         // Collect all key shares unless there's a protocol fault
         let keygen_outputs = keygen_outputs
             .into_iter()
@@ -104,10 +101,10 @@ impl Gg20Service {
         match keygen_outputs {
             Ok(secret_key_shares) => {
                 if secret_key_shares.is_empty() {
-                    return Err(anyhow!(
+                    return Err(Internal(format!(
                         "Party {} created no secret key shares",
                         keygen_init.my_index
-                    ));
+                    )));
                 }
 
                 // check that all shares returned the same public key and group recover info
@@ -116,33 +113,33 @@ impl Gg20Service {
                 let group_info = secret_key_shares[0]
                     .group()
                     .all_shares_bytes()
-                    .map_err(|_| anyhow!("unable to call all_shares_bytes()"))?;
+                    .map_err(|_| Internal("unable to call all_shares_bytes()".to_string()))?;
 
                 // sanity check: pubkey and group recovery info should be the same across all shares
                 // Here we check that the first share produced the same info as the i-th.
                 for secret_key_share in &secret_key_shares[1..] {
                     // try to get pubkey of i-th share. Each share should produce the same pubkey
                     if pub_key != secret_key_share.group().encoded_pubkey() {
-                        return Err(anyhow!(
+                        return Err(Internal(format!(
                             "Party {}'s share {} and {} returned different public key",
                             keygen_init.my_index,
                             share_id,
                             secret_key_share.share().index()
-                        ));
+                        )));
                     }
 
                     // try to get group recovery info of i-th share. Each share should produce the same group info
                     let curr_group_info = secret_key_share
                         .group()
                         .all_shares_bytes()
-                        .map_err(|_| anyhow!("unable to call all_shares_bytes()"))?;
+                        .map_err(|_| Internal("unable to call all_shares_bytes()".to_string()))?;
                     if group_info != curr_group_info {
-                        return Err(anyhow!(
+                        return Err(Internal(format!(
                             "Party {}'s share {} and {} returned different group recovery info",
                             keygen_init.my_index,
                             share_id,
                             secret_key_share.share().index()
-                        ));
+                        )));
                     }
                 }
 
@@ -150,36 +147,39 @@ impl Gg20Service {
             }
             Err(crimes) => {
                 // send crimes and exit with an error
-                stream_out_sender.send(Ok(proto::MessageOut::new_keygen_result(
-                    &keygen_init.party_uids,
-                    Err(crimes.clone()),
-                )))?;
-
-                Err(anyhow!(
+                stream_out_sender
+                    .send(Ok(proto::MessageOut::new_keygen_result(
+                        &keygen_init.party_uids,
+                        Err(crimes.clone()),
+                    )))
+                    .map_err(|_| StreamClosed("could not send keygen crimes".to_string()))?;
+
+                Err(Protocol(format!(
                     "Party {} found crimes: {:?}",
-                    keygen_init.my_index,
-                    crimes
-                ))
+                    keygen_init.my_index, crimes
+                )))
             }
         }
     }
 
     /// Create private recovery info out of a vec with all parties' SecretKeyShares
-    fn get_private_recovery_data(secret_key_shares: &[SecretKeyShare]) -> TofndResult<BytesVec> {
+    pub(crate) fn get_private_recovery_data(
+        secret_key_shares: &[SecretKeyShare],
+    ) -> ServiceResult<BytesVec> {
         // try to retrieve private recovery info from all party's shares
         let private_infos = secret_key_shares
             .iter()
             .enumerate()
             .map(|(index, secret_key_share)| {
-                secret_key_share
-                    .recovery_info()
-                    .map_err(|_| anyhow!("Unable to get recovery info for share {}", index))
+                secret_key_share.recovery_info().map_err(|_| {
+                    Internal(format!("Unable to get recovery info for share {}", index))
+                })
             })
-            .collect::<TofndResult<Vec<_>>>()?;
+            .collect::<ServiceResult<Vec<_>>>()?;
 
         // We use an additional layer of serialization to simplify the protobuf definition
         let private_bytes = serialize(&private_infos)
-            .map_err(|_| anyhow!("Failed to serialize private recovery infos"))?;
+            .map_err(|_| Internal("Failed to serialize private recovery infos".to_string()))?;
 
         Ok(private_bytes)
     }
@@ -187,11 +187,13 @@ impl Gg20Service {
     /// wait all keygen threads and get keygen outputs
     async fn aggregate_keygen_outputs(
         aggregator_receivers: Vec<Receiver<TofndKeygenOutput>>,
-    ) -> TofndResult<Vec<TofnKeygenOutput>> {
+    ) -> ServiceResult<Vec<TofnKeygenOutput>> {
         let mut keygen_outputs = Vec::with_capacity(aggregator_receivers.len());
 
         for aggregator in aggregator_receivers {
-            let res = aggregator.await??;
+            let res = aggregator
+                .await
+                .map_err(|err| Internal(format!("keygen thread was dropped: {}", err)))??;
             keygen_outputs.push(res);
         }
 
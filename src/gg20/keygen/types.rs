@@ -10,14 +10,13 @@ pub(super) type PartyShareCounts = KeygenPartyShareCounts;
 pub const MAX_PARTY_SHARE_COUNT: usize = tofn::gg20::keygen::MAX_PARTY_SHARE_COUNT;
 pub const MAX_TOTAL_SHARE_COUNT: usize = tofn::gg20::keygen::MAX_TOTAL_SHARE_COUNT;
 
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::InvalidArgument, ServiceResult};
 use tracing::{info, span, Level, Span};
 
 /// tofn's ProtocolOutput for Keygen
 pub type TofnKeygenOutput = ProtocolOutput<SecretKeyShare, KeygenPartyId>;
 /// tofnd's ProtocolOutput for Keygen
-pub type TofndKeygenOutput = TofndResult<TofnKeygenOutput>;
+pub type TofndKeygenOutput = ServiceResult<TofnKeygenOutput>;
 /// type for bytes
 pub use tofn::sdk::api::BytesVec;
 
@@ -85,10 +84,12 @@ impl Context {
     }
 
     /// get share_counts in the form of tofn::PartyShareCounts
-    pub fn share_counts(&self) -> TofndResult<PartyShareCounts> {
+    pub fn share_counts(&self) -> ServiceResult<PartyShareCounts> {
         match PartyShareCounts::from_vec(self.share_counts.clone()) {
             Ok(party_share_counts) => Ok(party_share_counts),
-            Err(_) => Err(anyhow!("failed to create party_share_counts")),
+            Err(_) => Err(InvalidArgument(
+                "failed to create party_share_counts".to_string(),
+            )),
         }
     }
 
@@ -3,6 +3,15 @@
 //!     [recover] - Recovers private data of a party provided a mnemonic.
 //!     [keygen] - Starts keygen.
 //!     [sign] - Starts sing.
+//!
+//! [proto::gg20_keys_server::Gg20Keys] gRPC server API, for the management of stored keys.
+//! Available gRPCs are:
+//!     [delete] - Deletes a key, or undeletes it within the undelete window.
+//!     [key_info] - Returns the public information of a key.
+//!     [recovery_info] - Rebuilds the keygen output of a key, for recovery.
+//!
+//! Requests of [delete] and [recovery_info] have to carry the admin token of the
+//! [crate::admin] service, like admin requests.
 
 // tonic cruft
 use super::proto;
@@ -16,13 +25,17 @@ use tracing::{error, info, span, Level};
 
 // gRPC
 mod broadcast;
+pub mod delete;
+mod key_info;
 mod key_presence;
 mod keygen;
 mod protocol;
 mod recover;
+mod recovery_info;
 pub mod service;
 mod sign;
 pub mod types;
+mod usage;
 use types::*;
 
 #[tonic::async_trait]
@@ -44,7 +57,7 @@ impl proto::gg20_server::Gg20 for service::Gg20Service {
                 proto::recover_response::Response::Success
             }
             Err(err) => {
-                error!("Unable to complete recovery: {}", err);
+                error!("Unable to complete recovery: {}", err.to_response_string());
                 proto::recover_response::Response::Fail
             }
         };
@@ -68,7 +81,10 @@ impl proto::gg20_server::Gg20 for service::Gg20Service {
                 res
             }
             Err(err) => {
-                error!("Unable to complete key presence check: {}", err);
+                error!(
+                    "Unable to complete key presence check: {}",
+                    err.to_response_string()
+                );
                 proto::key_presence_response::Response::Fail
             }
         };
@@ -95,9 +111,9 @@ impl proto::gg20_server::Gg20 for service::Gg20Service {
         tokio::spawn(async move {
             // can't return an error from a spawned thread
             if let Err(e) = gg20.handle_keygen(stream_in, msg_sender.clone(), s).await {
-                error!("keygen failure: {:?}", e.to_string());
+                error!("keygen failure: {:?}", e.to_response_string());
                 // we can't handle errors in tokio threads. Log error if we are unable to send the status code to client.
-                if let Err(e) = msg_sender.send(Err(Status::invalid_argument(e.to_string()))) {
+                if let Err(e) = msg_sender.send(Err(Status::from(e))) {
                     error!("could not send error to client: {}", e.to_string());
                 }
             }
@@ -122,9 +138,9 @@ impl proto::gg20_server::Gg20 for service::Gg20Service {
         tokio::spawn(async move {
             // can't return an error from a spawned thread
             if let Err(e) = gg20.handle_sign(stream, msg_sender.clone(), s).await {
-                error!("sign failure: {:?}", e.to_string());
+                error!("sign failure: {:?}", e.to_response_string());
                 // we can't handle errors in tokio threads. Log error if we are unable to send the status code to client.
-                if let Err(e) = msg_sender.send(Err(Status::invalid_argument(e.to_string()))) {
+                if let Err(e) = msg_sender.send(Err(Status::from(e))) {
                     error!("could not send error to client: {}", e.to_string());
                 }
             }
@@ -132,3 +148,100 @@ impl proto::gg20_server::Gg20 for service::Gg20Service {
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
 }
+
+#[tonic::async_trait]
+impl proto::gg20_keys_server::Gg20Keys for service::Gg20Service {
+    /// DeleteKey unary gRPC. See [delete].
+    async fn delete_key(
+        &self,
+        request: Request<proto::DeleteKeyRequest>,
+    ) -> Result<Response<proto::DeleteKeyResponse>, Status> {
+        if let Err(err) = self.authenticate_admin(request.metadata()) {
+            error!("Rejected delete request: {}", err.to_response_string());
+            return Err(err.into());
+        }
+        let request = request.into_inner();
+        let key_uid = request.key_uid.clone();
+
+        match self.handle_delete_key(request).await {
+            Ok(response) => {
+                info!(
+                    "Deleted key {}. It can be undeleted until {}",
+                    key_uid, response.purge_after
+                );
+                Ok(Response::new(response))
+            }
+            Err(err) => {
+                error!("Unable to delete key: {}", err.to_response_string());
+                Err(err.into())
+            }
+        }
+    }
+
+    /// UndeleteKey unary gRPC. See [delete].
+    async fn undelete_key(
+        &self,
+        request: Request<proto::UndeleteKeyRequest>,
+    ) -> Result<Response<proto::UndeleteKeyResponse>, Status> {
+        if let Err(err) = self.authenticate_admin(request.metadata()) {
+            error!("Rejected undelete request: {}", err.to_response_string());
+            return Err(err.into());
+        }
+        let request = request.into_inner();
+        let key_uid = request.key_uid.clone();
+
+        match self.handle_undelete_key(request).await {
+            Ok(response) => {
+                info!("Undeleted key {}", key_uid);
+                Ok(Response::new(response))
+            }
+            Err(err) => {
+                error!("Unable to undelete key: {}", err.to_response_string());
+                Err(err.into())
+            }
+        }
+    }
+
+    /// GetKeyInfo unary gRPC. See [key_info].
+    async fn get_key_info(
+        &self,
+        request: Request<proto::GetKeyInfoRequest>,
+    ) -> Result<Response<proto::GetKeyInfoResponse>, Status> {
+        let request = request.into_inner();
+
+        match self.handle_get_key_info(request).await {
+            Ok(response) => Ok(Response::new(response)),
+            Err(err) => {
+                error!("Unable to get key info: {}", err.to_response_string());
+                Err(err.into())
+            }
+        }
+    }
+
+    /// GetRecoveryInfo unary gRPC. See [recovery_info].
+    async fn get_recovery_info(
+        &self,
+        request: Request<proto::GetRecoveryInfoRequest>,
+    ) -> Result<Response<proto::GetRecoveryInfoResponse>, Status> {
+        if let Err(err) = self.authenticate_admin(request.metadata()) {
+            error!(
+                "Rejected recovery info request: {}",
+                err.to_response_string()
+            );
+            return Err(err.into());
+        }
+        let request = request.into_inner();
+        let key_uid = request.key_uid.clone();
+
+        match self.handle_get_recovery_info(request).await {
+            Ok(response) => {
+                info!("Rebuilt recovery info of key {}", key_uid);
+                Ok(Response::new(response))
+            }
+            Err(err) => {
+                error!("Unable to get recovery info: {}", err.to_response_string());
+                Err(err.into())
+            }
+        }
+    }
+}
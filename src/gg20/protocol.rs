@@ -13,8 +13,7 @@ use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tracing::{debug, error, span, warn, Level, Span};
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError, ServiceResult};
 
 /// execute gg20 protocol
 pub(super) async fn execute_protocol<F, K, P, const MAX_MSG_IN_LEN: usize>(
@@ -26,7 +25,7 @@ pub(super) async fn execute_protocol<F, K, P, const MAX_MSG_IN_LEN: usize>(
     party_uids: &[String],
     party_share_counts: &[usize],
     span: Span,
-) -> TofndResult<ProtocolOutput<F, P>>
+) -> ServiceResult<ProtocolOutput<F, P>>
 where
     K: Clone,
 {
@@ -56,11 +55,13 @@ where
         // check if everything was ok this round
         party = round
             .execute_next_round()
-            .map_err(|_| anyhow!("Error in tofn::execute_next_round"))?;
+            .map_err(|_| ServiceError::Protocol("Error in tofn::execute_next_round".to_string()))?;
     }
 
     match party {
-        Protocol::NotDone(_) => Err(anyhow!("Protocol failed to complete")),
+        Protocol::NotDone(_) => Err(ServiceError::Protocol(
+            "Protocol failed to complete".to_string(),
+        )),
         Protocol::Done(result) => Ok(result),
     }
 }
@@ -71,7 +72,7 @@ fn handle_outgoing<F, K, P, const MAX_MSG_IN_LEN: usize>(
     party_uids: &[String],
     round_count: usize,
     span: Span,
-) -> TofndResult<()> {
+) -> ServiceResult<()> {
     let send_span = span!(parent: &span, Level::DEBUG, "outgoing", round = round_count);
     let _start = send_span.enter();
     debug!("begin");
@@ -79,7 +80,9 @@ fn handle_outgoing<F, K, P, const MAX_MSG_IN_LEN: usize>(
     if let Some(bcast) = round.bcast_out() {
         debug!("generating out bcast");
         // send message to gRPC client
-        sender.send(Ok(proto::MessageOut::new_bcast(bcast)))?
+        sender
+            .send(Ok(proto::MessageOut::new_bcast(bcast)))
+            .map_err(|_| ServiceError::StreamClosed("could not send bcast to client".to_string()))?
     }
     // send outgoing p2ps
     if let Some(p2ps_out) = round.p2ps_out() {
@@ -90,7 +93,9 @@ fn handle_outgoing<F, K, P, const MAX_MSG_IN_LEN: usize>(
                 .info()
                 .party_share_counts()
                 .share_to_party_id(i)
-                .map_err(|_| anyhow!("Unable to get tofnd index for party {}", i))?;
+                .map_err(|_| {
+                    ServiceError::Internal(format!("Unable to get tofnd index for party {}", i))
+                })?;
 
             debug!(
                 "out p2p to [{}] ({}/{})",
@@ -101,10 +106,14 @@ fn handle_outgoing<F, K, P, const MAX_MSG_IN_LEN: usize>(
             p2p_msg_count += 1;
 
             // send message to gRPC client
-            sender.send(Ok(proto::MessageOut::new_p2p(
-                &party_uids[tofnd_idx.as_usize()],
-                p2p,
-            )))?
+            sender
+                .send(Ok(proto::MessageOut::new_p2p(
+                    &party_uids[tofnd_idx.as_usize()],
+                    p2p,
+                )))
+                .map_err(|_| {
+                    ServiceError::StreamClosed("could not send p2p to client".to_string())
+                })?
         }
     }
     debug!("finished");
@@ -119,7 +128,7 @@ async fn handle_incoming<F, K, P, const MAX_MSG_IN_LEN: usize>(
     total_num_of_shares: usize,
     round_count: usize,
     span: Span,
-) -> TofndResult<()> {
+) -> ServiceResult<()> {
     let mut p2p_msg_count = 0;
     let mut bcast_msg_count = 0;
 
@@ -173,14 +182,19 @@ async fn handle_incoming<F, K, P, const MAX_MSG_IN_LEN: usize>(
         let from = party_uids
             .iter()
             .position(|uid| uid == &traffic.from_party_uid)
-            .ok_or_else(|| anyhow!("from uid does not exist in party uids"))?;
+            .ok_or_else(|| {
+                ServiceError::Protocol("from uid does not exist in party uids".to_string())
+            })?;
 
         // try to set a message
         if round
             .msg_in(TypedUsize::from_usize(from), &traffic.payload)
             .is_err()
         {
-            return Err(anyhow!("error calling tofn::msg_in with [from: {}]", from));
+            return Err(ServiceError::Protocol(format!(
+                "error calling tofn::msg_in with [from: {}]",
+                from
+            )));
         };
     }
 
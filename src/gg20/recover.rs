@@ -1,13 +1,25 @@
 //! This module handles the recover gRPC.
 //! Request includes [proto::message_in::Data::KeygenInit] struct and encrypted recovery info.
 //! The recovery info is decrypted by party's mnemonic seed and saved in the KvStore.
+//! The mnemonic recorded for the key is tried first, followed by all other stored mnemonics,
+//! so that keys generated before a rotation can still be recovered.
+//! Likewise, the primes of the keypair are the ones recorded for a deleted key, if any. Otherwise, the primes
+//! of new keys are tried first. The other primes are only tried with the mnemonic recorded for the key,
+//! or with every mnemonic if they are unsafe ones and the node allows unsafe keys, so that the costly
+//! recovery of safe primes is never run for every stored mnemonic. The primes that recovered the key
+//! are recorded with it.
 
-use super::{keygen::types::KeygenInitSanitized, proto, service::Gg20Service, types::PartyInfo};
+use super::{
+    keygen::types::KeygenInitSanitized,
+    proto,
+    service::Gg20Service,
+    types::{Gg20Tombstone, KeygenMode, PartyInfo},
+};
 use tofn::{
     collections::TypedUsize,
     gg20::keygen::{
-        recover_party_keypair, recover_party_keypair_unsafe, KeygenPartyId, SecretKeyShare,
-        SecretRecoveryKey,
+        recover_party_keypair, recover_party_keypair_unsafe, KeygenPartyId, KeygenPartyShareCounts,
+        SecretKeyShare, SecretRecoveryKey,
     },
     sdk::api::{deserialize, BytesVec, PartyShareCounts},
 };
@@ -16,32 +28,32 @@ use tofn::{
 use tracing::{info, warn};
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::*, ServiceResult};
+
+use crate::mnemonic::{rotated_mnemonic_index, KeyScheme};
 
-use std::convert::TryInto;
+/// the seed of a stored mnemonic, along with the kv-store key of the mnemonic
+type RecoverySeed = (String, SecretRecoveryKey);
 
 impl Gg20Service {
-    pub(super) async fn handle_recover(&self, request: proto::RecoverRequest) -> TofndResult<()> {
+    pub(super) async fn handle_recover(&self, request: proto::RecoverRequest) -> ServiceResult<()> {
         // get keygen init sanitized from request
         let keygen_init = {
-            let keygen_init = request
-                .keygen_init
-                .ok_or_else(|| anyhow!("missing keygen_init field in recovery request"))?;
+            let keygen_init = request.keygen_init.ok_or_else(|| {
+                InvalidArgument("missing keygen_init field in recovery request".to_string())
+            })?;
             Self::keygen_sanitize_args(keygen_init)?
         };
 
-        let keygen_output = request
-            .keygen_output
-            .ok_or_else(|| anyhow!("missing keygen_output field in recovery request"))?;
+        let keygen_output = request.keygen_output.ok_or_else(|| {
+            InvalidArgument("missing keygen_output field in recovery request".to_string())
+        })?;
 
         // check if key-uid already exists in kv-store. If yes, return success and don't update the kv-store
         if self
             .kv_manager
-            .kv()
-            .exists(&keygen_init.new_key_uid)
-            .await
-            .map_err(|err| anyhow!(err))?
+            .exists::<PartyInfo>(&keygen_init.new_key_uid)
+            .await?
         {
             warn!(
                 "Request to recover shares for [key {}, party {}] but shares already exist in kv-store. Abort request.",
@@ -50,71 +62,198 @@ impl Gg20Service {
             return Ok(());
         }
 
-        // recover secret key shares from request
-        // get mnemonic seed
-        let secret_recovery_key = self.kv_manager.seed().await?;
-        let secret_key_shares = self
-            .recover_secret_key_shares(&secret_recovery_key, &keygen_init, &keygen_output)
-            .map_err(|err| anyhow!("Failed to acquire secret key share {}", err))?;
+        // check the recovery infos once, before spending cycles on keypair recovery
+        let (party_share_counts, private_info_vec) =
+            Self::recovery_infos(&keygen_init, &keygen_output)?;
+
+        // the mnemonic of the key may have been rotated out since keygen, so try all stored mnemonics
+        let (origin_seeds, other_seeds) = self.recovery_seeds(&keygen_init.new_key_uid).await?;
+        let (first_mode, other_mode) = self.recovery_modes(&keygen_init.new_key_uid).await?;
 
-        self.update_share_kv_store(keygen_init, secret_key_shares)
-            .await
+        // the mnemonics recorded for the key are tried in both modes before any other mnemonic
+        let mut attempts = vec![];
+        for seed in &origin_seeds {
+            attempts.push((seed, first_mode));
+            attempts.extend(other_mode.map(|other_mode| (seed, other_mode)));
+        }
+        attempts.extend(other_seeds.iter().map(|seed| (seed, first_mode)));
+        // unsafe primes are cheap to recover, so they are tried with every mnemonic where unsafe keys are allowed
+        if other_mode == Some(KeygenMode::Unsafe) && self.cfg.allow_unsafe_keys {
+            attempts.extend(other_seeds.iter().map(|seed| (seed, KeygenMode::Unsafe)));
+        }
+
+        for ((seed_key, secret_recovery_key), mode) in attempts {
+            let secret_key_shares = match self.recover_secret_key_shares(
+                secret_recovery_key,
+                mode,
+                &keygen_init,
+                &keygen_output,
+                &party_share_counts,
+                &private_info_vec,
+            ) {
+                Ok(secret_key_shares) => secret_key_shares,
+                Err(err) => {
+                    info!(
+                        "Mnemonic '{}' with {} primes does not recover key {}: {}",
+                        seed_key,
+                        mode,
+                        keygen_init.new_key_uid,
+                        err.to_response_string()
+                    );
+                    continue;
+                }
+            };
+
+            match rotated_mnemonic_index(seed_key) {
+                Some(index) => info!(
+                    "Recovered key {} with rotated out mnemonic {} ('{}')",
+                    keygen_init.new_key_uid, index, seed_key
+                ),
+                None => info!(
+                    "Recovered key {} with the current mnemonic",
+                    keygen_init.new_key_uid
+                ),
+            }
+            if mode == KeygenMode::Unsafe {
+                warn!(
+                    "Key {} was generated with unsafe primes. Do not use it to secure real value.",
+                    keygen_init.new_key_uid
+                );
+            }
+
+            return self
+                .update_share_kv_store(
+                    keygen_init,
+                    secret_key_shares,
+                    mode,
+                    seed_key,
+                    secret_recovery_key,
+                )
+                .await;
+        }
+
+        Err(MnemonicUnavailable(format!(
+            "none of the {} stored mnemonics recovers key {}",
+            origin_seeds.len() + other_seeds.len(),
+            keygen_init.new_key_uid
+        )))
     }
 
-    /// get recovered secret key shares from serilized share recovery info
-    fn recover_secret_key_shares(
+    /// Get the seeds of all stored mnemonics: first the ones recorded for `key_uid`,
+    /// then the other current and rotated out mnemonics.
+    async fn recovery_seeds(
         &self,
-        secret_recovery_key: &SecretRecoveryKey,
+        key_uid: &str,
+    ) -> ServiceResult<(Vec<RecoverySeed>, Vec<RecoverySeed>)> {
+        // mnemonics are not rotated while they are read
+        let _guard = self.kv_manager.read_mnemonics().await;
+
+        let origin_seeds = self
+            .kv_manager
+            .origin_seeds(KeyScheme::Gg20, key_uid)
+            .await?;
+
+        let mut other_seeds = vec![];
+        for seed_key in self.kv_manager.seed_key_iter().await? {
+            if origin_seeds.iter().all(|(key, _)| *key != seed_key) {
+                let secret_recovery_key = self.kv_manager.get_seed(&seed_key).await?;
+                other_seeds.push((seed_key, secret_recovery_key));
+            }
+        }
+
+        Ok((origin_seeds, other_seeds))
+    }
+
+    /// Get the mode to recover the keypair of `key_uid` with, along with the mode to fall back to, if any.
+    /// A deleted key recorded its mode; otherwise, the mode of new keys is tried first.
+    async fn recovery_modes(
+        &self,
+        key_uid: &str,
+    ) -> ServiceResult<(KeygenMode, Option<KeygenMode>)> {
+        if self.kv_manager.exists::<Gg20Tombstone>(key_uid).await? {
+            let tombstone = self.kv_manager.get::<Gg20Tombstone>(key_uid).await?;
+            if let Some(mode) = tombstone.party_info.tofnd.keygen_mode {
+                return Ok((mode, None));
+            }
+        }
+
+        Ok(match self.cfg.safe_keygen {
+            true => (KeygenMode::Safe, Some(KeygenMode::Unsafe)),
+            false => (KeygenMode::Unsafe, Some(KeygenMode::Safe)),
+        })
+    }
+
+    /// check the share counts of the request and deserialize the private recovery infos of my shares
+    fn recovery_infos(
         init: &KeygenInitSanitized,
         output: &proto::KeygenOutput,
-    ) -> TofndResult<Vec<SecretKeyShare>> {
+    ) -> ServiceResult<(KeygenPartyShareCounts, Vec<BytesVec>)> {
         // get my share count safely
         let my_share_count = *init.party_share_counts.get(init.my_index).ok_or_else(|| {
-            anyhow!(
+            InvalidArgument(format!(
                 "index {} is out of party_share_counts bounds {}",
                 init.my_index,
                 init.party_share_counts.len()
-            )
+            ))
         })?;
         if my_share_count == 0 {
-            return Err(anyhow!("Party {} has 0 shares assigned", init.my_index));
+            return Err(InvalidArgument(format!(
+                "Party {} has 0 shares assigned",
+                init.my_index
+            )));
         }
 
         // check party share counts
         let party_share_counts = PartyShareCounts::from_vec(init.party_share_counts.to_owned())
             .map_err(|_| {
-                anyhow!(
+                InvalidArgument(format!(
                     "PartyCounts::from_vec() error for {:?}",
                     init.party_share_counts
-                )
+                ))
             })?;
 
         // check private recovery infos
         // use an additional layer of deserialization to simpify the protobuf definition
-        // deserialize recovery info here to catch errors before spending cycles on keypair recovery
         let private_info_vec: Vec<BytesVec> = deserialize(&output.private_recover_info)
-            .ok_or_else(|| anyhow!("Failed to deserialize private recovery infos"))?;
+            .ok_or_else(|| {
+                InvalidArgument("Failed to deserialize private recovery infos".to_string())
+            })?;
 
         if private_info_vec.len() != my_share_count {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "Party {} has {} shares assigned, but retrieved {} shares from client",
                 init.my_index,
                 my_share_count,
                 private_info_vec.len()
-            ));
+            )));
         }
 
+        Ok((party_share_counts, private_info_vec))
+    }
+
+    /// get recovered secret key shares from serilized share recovery info with a party keypair of `mode`
+    fn recover_secret_key_shares(
+        &self,
+        secret_recovery_key: &SecretRecoveryKey,
+        mode: KeygenMode,
+        init: &KeygenInitSanitized,
+        output: &proto::KeygenOutput,
+        party_share_counts: &KeygenPartyShareCounts,
+        private_info_vec: &[BytesVec],
+    ) -> ServiceResult<Vec<SecretKeyShare>> {
         info!("Recovering keypair for party {} ...", init.my_index);
 
         let party_id = TypedUsize::<KeygenPartyId>::from_usize(init.my_index);
 
         // try to recover keypairs
         let session_nonce = init.new_key_uid.as_bytes();
-        let party_keypair = match self.cfg.safe_keygen {
-            true => recover_party_keypair(party_id, secret_recovery_key, session_nonce),
-            false => recover_party_keypair_unsafe(party_id, secret_recovery_key, session_nonce),
+        let party_keypair = match mode {
+            KeygenMode::Safe => recover_party_keypair(party_id, secret_recovery_key, session_nonce),
+            KeygenMode::Unsafe => {
+                recover_party_keypair_unsafe(party_id, secret_recovery_key, session_nonce)
+            }
         }
-        .map_err(|_| anyhow!("party keypair recovery failed"))?;
+        .map_err(|_| Internal("party keypair recovery failed".to_string()))?;
 
         info!("Finished recovering keypair for party {}", init.my_index);
 
@@ -133,38 +272,51 @@ impl Gg20Service {
                     party_share_counts.clone(),
                     init.threshold,
                 )
-                .map_err(|_| anyhow!("Cannot recover share [{}] of party [{}]", i, party_id))
+                .map_err(|_| {
+                    InvalidArgument(format!(
+                        "Cannot recover share [{}] of party [{}]",
+                        i, party_id
+                    ))
+                })
             })
-            .collect::<TofndResult<_>>()?;
+            .collect::<ServiceResult<Vec<_>>>()?;
 
         Ok(secret_key_shares)
     }
 
-    /// attempt to write recovered secret key shares to the kv-store
+    /// attempt to write recovered secret key shares of `mode` to the kv-store,
+    /// along with the origin of the key, the mnemonic stored under `seed_key`
     async fn update_share_kv_store(
         &self,
         keygen_init_sanitized: KeygenInitSanitized,
         secret_key_shares: Vec<SecretKeyShare>,
-    ) -> TofndResult<()> {
+        mode: KeygenMode,
+        seed_key: &str,
+        secret_recovery_key: &SecretRecoveryKey,
+    ) -> ServiceResult<()> {
         // try to make a reservation
         let reservation = self
             .kv_manager
-            .kv()
-            .reserve_key(keygen_init_sanitized.new_key_uid)
-            .await
-            .map_err(|err| anyhow!("failed to complete reservation: {}", err))?;
+            .reserve::<PartyInfo>(&keygen_init_sanitized.new_key_uid)
+            .await?;
+        // record the origin first, so that the mnemonic is never pruned while the key is stored
+        self.kv_manager
+            .record_key_seed(
+                KeyScheme::Gg20,
+                &keygen_init_sanitized.new_key_uid,
+                seed_key,
+                secret_recovery_key,
+            )
+            .await?;
         // acquire kv-data
         let kv_data = PartyInfo::get_party_info(
             secret_key_shares,
             keygen_init_sanitized.party_uids,
             keygen_init_sanitized.party_share_counts,
             keygen_init_sanitized.my_index,
+            mode,
         );
         // try writing the data to the kv-store
-        self.kv_manager
-            .kv()
-            .put(reservation, kv_data.try_into()?)
-            .await
-            .map_err(|err| anyhow!("failed to update kv store: {}", err))
+        Ok(self.kv_manager.put(reservation, kv_data).await?)
     }
 }
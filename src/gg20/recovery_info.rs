@@ -0,0 +1,33 @@
+//! This module handles the GetRecoveryInfo gRPC.
+//! The keygen output, which is otherwise only sent once at the end of keygen, is rebuilt from the stored shares,
+//! so that a client that lost it can still recover the key after a loss of the kv store.
+
+use super::{proto, service::Gg20Service, types::PartyInfo};
+
+// error handling
+use crate::error::{ServiceError::*, ServiceResult};
+
+impl Gg20Service {
+    pub(super) async fn handle_get_recovery_info(
+        &self,
+        request: proto::GetRecoveryInfoRequest,
+    ) -> ServiceResult<proto::GetRecoveryInfoResponse> {
+        let party_info = self.kv_manager.get::<PartyInfo>(&request.key_uid).await?;
+
+        let pub_key = party_info.common.encoded_pubkey();
+        let group_recover_info = party_info
+            .common
+            .all_shares_bytes()
+            .map_err(|_| Internal("unable to call all_shares_bytes()".to_string()))?;
+        let private_recover_info =
+            Self::get_private_recovery_data(&party_info.secret_key_shares()?)?;
+
+        Ok(proto::GetRecoveryInfoResponse {
+            output: Some(proto::KeygenOutput {
+                pub_key,
+                group_recover_info,
+                private_recover_info,
+            }),
+        })
+    }
+}
@@ -1,8 +1,11 @@
 //! This mod includes the service implementation derived from
 
-use super::proto;
+use super::{proto, types::Password};
+use crate::admin::service::authenticate;
 use crate::config::Config;
+use crate::error::{ServiceError::Unauthenticated, ServiceResult};
 use crate::kv_manager::KvManager;
+use tonic::metadata::MetadataMap;
 
 #[cfg(feature = "malicious")]
 pub mod malicious;
@@ -12,9 +15,39 @@ pub mod malicious;
 pub struct Gg20Service {
     pub(super) kv_manager: KvManager,
     pub(super) cfg: Config,
+    /// admin token of the requests that delete keys or expose their recovery info
+    pub(super) admin_token: Option<Password>,
 }
 
 /// create a new Gg20 gRPC server
 pub fn new_service(cfg: Config, kv_manager: KvManager) -> impl proto::gg20_server::Gg20 {
-    Gg20Service { kv_manager, cfg }
+    Gg20Service {
+        kv_manager,
+        cfg,
+        admin_token: None,
+    }
+}
+
+/// create a new Gg20Keys gRPC server. Without an admin token, keys can't be deleted or undeleted,
+/// and their recovery info is not served.
+pub fn new_keys_service(
+    cfg: Config,
+    kv_manager: KvManager,
+    admin_token: Option<Password>,
+) -> impl proto::gg20_keys_server::Gg20Keys {
+    Gg20Service {
+        kv_manager,
+        cfg,
+        admin_token,
+    }
+}
+
+impl Gg20Service {
+    /// Checks that `metadata` carries the admin token. Fails if no admin token is configured.
+    pub(super) fn authenticate_admin(&self, metadata: &MetadataMap) -> ServiceResult<()> {
+        match &self.admin_token {
+            Some(token) => authenticate(token, metadata),
+            None => Err(Unauthenticated("no admin token is configured".to_string())),
+        }
+    }
 }
@@ -1,6 +1,6 @@
 //! This module creates and executes the sign protocol
 //! On success it returns [super::TofndSignOutput]. A successful sign execution can produce either an Ok(Vec<u8>) of an Err(Vec<Vec<Crime>>).
-//! On failure it returns [anyhow!] error if [Sign] struct cannot be instantiated.
+//! On failure it returns a [crate::error::ServiceError] if [Sign] struct cannot be instantiated.
 
 use super::{
     proto,
@@ -14,11 +14,11 @@ use tofn::gg20::sign::new_sign;
 use tracing::{info, Span};
 
 // error handling
-use anyhow::anyhow;
+use crate::error::ServiceError;
 
 impl Gg20Service {
     /// create and execute sign protocol and returning the result.
-    /// if the protocol cannot be instantiated, return an [anyhow!] error
+    /// if the protocol cannot be instantiated, return a [ServiceError::Internal]
     pub(super) async fn execute_sign(
         &self,
         chans: ProtocolCommunication<
@@ -37,7 +37,7 @@ impl Gg20Service {
             #[cfg(feature = "malicious")]
             self.cfg.behaviours.sign.clone(),
         )
-        .map_err(|_| anyhow!("sign instantiation failed"))?;
+        .map_err(|_| ServiceError::Internal("sign instantiation failed".to_string()))?;
 
         // execute protocol and wait for completion
         let protocol_result = protocol::execute_protocol(
@@ -50,8 +50,9 @@ impl Gg20Service {
         )
         .await;
 
-        let res = protocol_result
-            .map_err(|err| anyhow!("Sign was not completed due to error: {}", err))?;
+        let res = protocol_result.map_err(|err| {
+            ServiceError::Protocol(format!("Sign was not completed due to error: {}", err))
+        })?;
 
         info!("Sign completed");
         Ok(res)
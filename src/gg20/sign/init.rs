@@ -1,12 +1,13 @@
 //! This module handles the initialization of the Sign protocol.
 //! A [SignInitSanitized] struct is created out of the raw incoming [proto::SignInit] message and the session key is queried inside from KvStore.
-//! If [proto::SignInit] fails to be parsed, or no Keygen has been executed for the current session ID, an [anyhow!] error is returned
+//! If [proto::SignInit] fails to be parsed, or no Keygen has been executed for the current session ID, a [crate::error::ServiceError] is returned
+//! Keys generated with unsafe primes are refused, unless [crate::config::Config::allow_unsafe_keys] is set.
 
 // try_into() for MessageDigest
 use std::convert::TryInto;
 
 use super::{proto, types::SignInitSanitized, Gg20Service};
-use crate::gg20::types::PartyInfo;
+use crate::gg20::types::{KeygenMode, PartyInfo};
 
 // tonic cruft
 use futures_util::StreamExt;
@@ -14,45 +15,77 @@ use tokio::sync::mpsc;
 use tonic::Status;
 
 // logging
-use tracing::Span;
+use tracing::{warn, Span};
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError, ServiceError::*, ServiceResult};
 
 impl Gg20Service {
     /// Receives a message from the stream and tries to handle sign init operations.
     /// On success, it extracts the PartyInfo from the KVStrore and returns a sanitized struct ready to be used by the protocol.
-    /// On failure, returns an [anyhow!] error and no changes are been made in the KvStore.
+    /// On failure, returns a [crate::error::ServiceError] and no changes are been made in the KvStore.
     pub(super) async fn handle_sign_init(
         &self,
         in_stream: &mut tonic::Streaming<proto::MessageIn>,
         out_stream: &mut mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
         sign_span: Span,
-    ) -> TofndResult<(SignInitSanitized, PartyInfo)> {
+    ) -> ServiceResult<(SignInitSanitized, PartyInfo)> {
         let msg_type = in_stream
             .next()
             .await
-            .ok_or_else(|| anyhow!("sign: stream closed by client without sending a message"))??
+            .ok_or_else(|| {
+                StreamClosed("sign: stream closed by client without sending a message".to_string())
+            })?
+            .map_err(|e| StreamClosed(format!("sign: stream closed by server: {}", e)))?
             .data
-            .ok_or_else(|| anyhow!("sign: missing `data` field in client message"))?;
+            .ok_or_else(|| {
+                InvalidArgument("sign: missing `data` field in client message".to_string())
+            })?;
 
         let sign_init = match msg_type {
             proto::message_in::Data::SignInit(k) => k,
-            _ => return Err(anyhow!("Expected sign init message")),
+            _ => return Err(InvalidArgument("Expected sign init message".to_string())),
         };
 
         // try to get party info related to session id
-        let party_info: PartyInfo = match self.kv_manager.kv().get(&sign_init.key_uid).await {
-            Ok(value) => value.try_into()?,
+        let party_info: PartyInfo = match self.kv_manager.get::<PartyInfo>(&sign_init.key_uid).await
+        {
+            Ok(party_info) => party_info,
             Err(err) => {
                 // if no such session id exists, send a message to client that indicates that recovery is needed and stop sign
                 Self::send_kv_store_failure(out_stream)?;
-                let err = anyhow!("Unable to find session-id {} in kv store. Issuing share recovery and exit sign {:?}", sign_init.key_uid, err);
-                return Err(err);
+                return Err(match ServiceError::from(err) {
+                    KeyNotFound(msg) => KeyNotFound(format!(
+                        "Unable to find session-id {} in kv store. Issuing share recovery and exit sign: {}",
+                        sign_init.key_uid, msg
+                    )),
+                    err => err,
+                });
             }
         };
 
+        // keys of unsafe primes don't secure real value, so they only sign if allowed
+        match party_info.tofnd.keygen_mode {
+            Some(KeygenMode::Unsafe) => {
+                if !self.cfg.allow_unsafe_keys {
+                    return Err(UnsafeKey(format!(
+                        "key {} was generated with unsafe primes; start tofnd with '--allow-unsafe-keys' to sign with it",
+                        sign_init.key_uid
+                    )));
+                }
+                warn!(
+                    "Signing with key {}, which was generated with unsafe primes",
+                    sign_init.key_uid
+                );
+            }
+            // keys of older versions of tofnd did not record their primes, so they can't be refused
+            None => warn!(
+                "Signing with key {}, which was stored by an older version of tofnd and may have been generated with unsafe primes",
+                sign_init.key_uid
+            ),
+            Some(KeygenMode::Safe) => {}
+        }
+
         // try to sanitize arguments
         let sign_init = Self::sign_sanitize_args(sign_init, &party_info.tofnd.party_uids)?;
 
@@ -65,8 +98,10 @@ impl Gg20Service {
     /// send "need recover" message to client
     fn send_kv_store_failure(
         out_stream: &mut mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
-    ) -> TofndResult<()> {
-        Ok(out_stream.send(Ok(proto::MessageOut::need_recover()))?)
+    ) -> ServiceResult<()> {
+        out_stream
+            .send(Ok(proto::MessageOut::need_recover()))
+            .map_err(|_| StreamClosed("could not send need recover message".to_string()))
     }
 
     /// sanitize arguments of incoming message.
@@ -80,27 +115,31 @@ impl Gg20Service {
     fn sign_sanitize_args(
         sign_init: proto::SignInit,
         all_party_uids: &[String],
-    ) -> TofndResult<SignInitSanitized> {
+    ) -> ServiceResult<SignInitSanitized> {
         // create a vector of the tofnd indices of the participant uids
         let participant_indices = sign_init
             .party_uids
             .iter()
             .map(|s| {
                 all_party_uids.iter().position(|k| k == s).ok_or_else(|| {
-                    anyhow!(
+                    InvalidArgument(format!(
                         "participant [{}] not found in key [{}]",
-                        s,
-                        sign_init.key_uid
-                    )
+                        s, sign_init.key_uid
+                    ))
                 })
             })
             .collect::<Result<Vec<usize>, _>>()?;
 
         Ok(SignInitSanitized {
             new_sig_uid: sign_init.new_sig_uid,
+            key_uid: sign_init.key_uid,
             participant_uids: sign_init.party_uids,
             participant_indices,
-            message_to_sign: sign_init.message_to_sign.as_slice().try_into()?,
+            message_to_sign: sign_init
+                .message_to_sign
+                .as_slice()
+                .try_into()
+                .map_err(|err| InvalidArgument(format!("message_to_sign: {:?}", err)))?,
         })
     }
 }
@@ -125,6 +164,7 @@ mod tests {
         };
         let sanitized_sign_init = SignInitSanitized {
             new_sig_uid: "test_uid".to_owned(), // new sig uid should be the same
+            key_uid: "test_uid".to_owned(),
             participant_uids: vec!["party_2".to_owned(), "party_1".to_owned()], // party 2 has index 2, party 1 has index 1
             participant_indices: vec![2, 1], // indices should be [2, 1]
             message_to_sign: vec![42; 32].as_slice().try_into().unwrap(), // msg of 32 bytes should be successfully converted to MessageDigest
@@ -132,6 +172,7 @@ mod tests {
 
         let res = Gg20Service::sign_sanitize_args(raw_sign_init, &all_party_uids).unwrap();
         assert_eq!(&res.new_sig_uid, &sanitized_sign_init.new_sig_uid);
+        assert_eq!(&res.key_uid, &sanitized_sign_init.key_uid);
         assert_eq!(&res.participant_uids, &sanitized_sign_init.participant_uids);
         assert_eq!(
             &res.participant_indices,
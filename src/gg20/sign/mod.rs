@@ -21,11 +21,10 @@ use tokio::sync::{mpsc, oneshot};
 use tonic::Status;
 
 // logging
-use tracing::{span, Level, Span};
+use tracing::{span, warn, Level, Span};
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::*, ServiceResult};
 
 pub mod types;
 use types::*;
@@ -41,7 +40,7 @@ impl Gg20Service {
         mut stream_in: tonic::Streaming<proto::MessageIn>,
         mut stream_out_sender: mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
         sign_span: Span,
-    ) -> TofndResult<()> {
+    ) -> ServiceResult<()> {
         // 1. Receive SignInit, open message, sanitize arguments -> init mod
         // 2. Spawn N sign threads to execute the protocol in parallel; one of each of our shares -> execute mod
         // 3. Spawn 1 router thread to route messages from client to the respective sign thread -> routing mod
@@ -58,10 +57,10 @@ impl Gg20Service {
         // find my share count to allocate channel vectors
         let my_share_count = party_info.shares.len();
         if my_share_count == 0 {
-            return Err(anyhow!(
+            return Err(InvalidArgument(format!(
                 "Party {} has 0 shares assigned",
                 party_info.tofnd.index
-            ));
+            )));
         }
 
         // create in and out channels for each share, and spawn as many threads
@@ -106,13 +105,26 @@ impl Gg20Service {
 
         // 4.
         // wait for all sign threads to end, get responses, and return signature
-        Self::handle_results(
+        let signed = Self::handle_results(
             aggregator_receivers,
             &mut stream_out_sender,
             &sign_init.participant_uids,
         )
         .await?;
 
+        // the signature has been sent already; record the usage of the key in the background
+        if signed {
+            let kv_manager = self.kv_manager.clone();
+            tokio::spawn(async move {
+                if let Err(err) = kv_manager.record_sign(&sign_init.key_uid).await {
+                    warn!(
+                        "Unable to record sign with key {}: {}",
+                        sign_init.key_uid, err
+                    );
+                }
+            });
+        }
+
         Ok(())
     }
 }
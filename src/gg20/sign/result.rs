@@ -9,25 +9,27 @@ use tokio::sync::oneshot;
 use tonic::Status;
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::*, ServiceResult};
 
 impl Gg20Service {
     /// handle results from all shares
     /// if all shares return a valid output, send the result to client
-    /// if a share does not return a valid output, return an [anyhow!]
+    /// if a share does not return a valid output, return a [crate::error::ServiceError]
+    /// returns whether the result sent to the client is a signature, rather than a list of faulters
     pub(super) async fn handle_results(
-        aggregator_receivers: Vec<oneshot::Receiver<TofndResult<TofnSignOutput>>>,
+        aggregator_receivers: Vec<oneshot::Receiver<ServiceResult<TofnSignOutput>>>,
         stream_out_sender: &mut mpsc::UnboundedSender<Result<proto::MessageOut, Status>>,
         participant_uids: &[String],
-    ) -> TofndResult<()> {
+    ) -> ServiceResult<bool> {
         // create vec to store all sign outputs
         // cannot use aggregator_receivers.map(|aggr| aggr.await??) because map() does not support async funcs
         let mut sign_outputs = Vec::with_capacity(aggregator_receivers.len());
 
         //  wait all sign threads and get signature
         for aggregator in aggregator_receivers {
-            let sign_output = aggregator.await??;
+            let sign_output = aggregator
+                .await
+                .map_err(|err| Internal(format!("sign thread was dropped: {}", err)))??;
             sign_outputs.push(sign_output);
         }
 
@@ -45,15 +47,18 @@ impl Gg20Service {
                     error_msg,
                     sign_output.iter().enumerate().collect::<Vec<_>>()
                 );
-                return Err(anyhow!(error_msg));
+                return Err(Internal(error_msg));
             }
         }
 
         // send signature to client
-        stream_out_sender.send(Ok(proto::MessageOut::new_sign_result(
-            participant_uids,
-            sign_outputs[0].clone(),
-        )))?;
-        Ok(())
+        let signed = sign_outputs[0].is_ok();
+        stream_out_sender
+            .send(Ok(proto::MessageOut::new_sign_result(
+                participant_uids,
+                sign_outputs[0].clone(),
+            )))
+            .map_err(|_| StreamClosed("could not send sign result".to_string()))?;
+        Ok(signed)
     }
 }
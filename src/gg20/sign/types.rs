@@ -1,8 +1,7 @@
 //! Helper structs and implementations for [crate::gg20::sign].
 
 // error handling
-use crate::TofndResult;
-use anyhow::anyhow;
+use crate::error::{ServiceError::*, ServiceResult};
 
 // tofn types
 use super::super::MessageDigest;
@@ -14,12 +13,12 @@ use tofn::sdk::api::ProtocolOutput;
 /// tofn's ProtocolOutput for Sign
 pub type TofnSignOutput = ProtocolOutput<Vec<u8>, SignPartyId>;
 /// tofnd's ProtocolOutput for Sign
-pub type TofndSignOutput = TofndResult<TofnSignOutput>;
+pub type TofndSignOutput = ServiceResult<TofnSignOutput>;
 
 #[derive(Clone, Debug)]
 pub(super) struct SignInitSanitized {
     pub(super) new_sig_uid: String, // this is only used for logging
-    // pub(super) key_uid: String,
+    pub(super) key_uid: String,
     pub(super) participant_uids: Vec<String>,
     pub(super) participant_indices: Vec<usize>,
     pub(super) message_to_sign: MessageDigest,
@@ -42,7 +41,7 @@ impl Context {
         sign_init: SignInitSanitized,
         party_info: PartyInfo,
         tofnd_subindex: usize,
-    ) -> TofndResult<Self> {
+    ) -> ServiceResult<Self> {
         // retrieve sign_share_couts and secret_key_shares here instead of adding
         // getters to immediatelly dicover potential errors
         let sign_share_counts = Self::get_sign_share_counts(
@@ -82,29 +81,31 @@ impl Context {
         keygen_uids: &[String],
         keygen_share_counts: &[usize],
         sign_uids: &[String],
-    ) -> TofndResult<Vec<usize>> {
+    ) -> ServiceResult<Vec<usize>> {
         if keygen_uids.len() != keygen_share_counts.len() {
-            return Err(anyhow!("misalligned keygen uids and keygen share counts"));
+            return Err(Internal(
+                "misalligned keygen uids and keygen share counts".to_string(),
+            ));
         }
         let mut sign_share_counts = vec![];
         for sign_uid in sign_uids {
             let keygen_index = keygen_uids
                 .iter()
                 .position(|uid| uid == sign_uid)
-                .ok_or_else(|| anyhow!("Sign uid was not found"))?;
+                .ok_or_else(|| InvalidArgument("Sign uid was not found".to_string()))?;
             let sign_share_count = *keygen_share_counts
                 .get(keygen_index)
-                .ok_or_else(|| anyhow!("invalid index"))?;
+                .ok_or_else(|| Internal("invalid index".to_string()))?;
             sign_share_counts.push(sign_share_count);
         }
         Ok(sign_share_counts)
     }
 
-    fn get_share(party_info: &PartyInfo, tofnd_subindex: usize) -> TofndResult<ShareSecretInfo> {
+    fn get_share(party_info: &PartyInfo, tofnd_subindex: usize) -> ServiceResult<ShareSecretInfo> {
         Ok(party_info
             .shares
             .get(tofnd_subindex)
-            .ok_or_else(|| anyhow!("failed to get ShareSecretInfo from PartyInfo"))?
+            .ok_or_else(|| Internal("failed to get ShareSecretInfo from PartyInfo".to_string()))?
             .clone())
     }
 
@@ -128,14 +129,14 @@ impl Context {
     pub(super) fn get_sign_parties(
         length: usize,
         sign_indices: &[usize],
-    ) -> TofndResult<SignParties> {
+    ) -> ServiceResult<SignParties> {
         let mut sign_parties = Subset::with_max_size(length);
         for signer_idx in sign_indices.iter() {
             if sign_parties
                 .add(TypedUsize::from_usize(*signer_idx))
                 .is_err()
             {
-                return Err(anyhow!("failed to call Subset::add"));
+                return Err(InvalidArgument("failed to call Subset::add".to_string()));
             }
         }
         Ok(sign_parties)
@@ -32,7 +32,44 @@ impl<InMsg, OutMsg> ProtocolCommunication<InMsg, OutMsg> {
 }
 
 use serde::{Deserialize, Serialize};
-use tofn::gg20::keygen::{GroupPublicInfo, SecretKeyShare, ShareSecretInfo};
+use tofn::{
+    gg20::keygen::{GroupPublicInfo, SecretKeyShare, ShareSecretInfo},
+    sdk::api::{deserialize, serialize},
+};
+
+use crate::kv_manager::unix_timestamp;
+
+// error handling
+use crate::error::{ServiceError::Internal, ServiceResult};
+
+/// Primes that the Paillier keys of a key were generated with
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum KeygenMode {
+    /// safe primes
+    Safe,
+    /// unsafe primes, only used for tests
+    Unsafe,
+}
+
+impl std::fmt::Display for KeygenMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeygenMode::Safe => write!(f, "safe"),
+            KeygenMode::Unsafe => write!(f, "unsafe"),
+        }
+    }
+}
+
+impl KeygenMode {
+    /// the mode of keys generated with `safe_keygen` of [crate::config::Config]
+    pub(super) fn from_safe_keygen(safe_keygen: bool) -> Self {
+        match safe_keygen {
+            true => KeygenMode::Safe,
+            false => KeygenMode::Unsafe,
+        }
+    }
+}
 
 /// Struct to hold `tonfd` info. This consists of information we need to
 /// store in the KV store that is not relevant to `tofn`
@@ -41,6 +78,73 @@ pub(super) struct TofndInfo {
     pub(super) party_uids: Vec<String>,
     pub(super) share_counts: Vec<usize>,
     pub(super) index: usize,
+    /// seconds since unix epoch at the time the key was generated or recovered.
+    /// Unknown for keys stored by older versions of tofnd, as are the fields below.
+    pub(super) created_at: Option<u64>,
+    pub(super) keygen_mode: Option<KeygenMode>,
+    /// version of tofnd that generated or recovered the key
+    pub(super) tofnd_version: Option<String>,
+}
+
+/// [TofndInfo] as stored by older versions of tofnd, before key metadata was recorded
+#[derive(Deserialize)]
+struct LegacyTofndInfo {
+    party_uids: Vec<String>,
+    share_counts: Vec<usize>,
+    index: usize,
+}
+
+/// [PartyInfo] as stored by older versions of tofnd. Upgraded to a [PartyInfo] without key metadata.
+#[derive(Deserialize)]
+pub(crate) struct LegacyPartyInfo {
+    common: GroupPublicInfo,
+    shares: Vec<ShareSecretInfo>,
+    tofnd: LegacyTofndInfo,
+}
+
+impl From<LegacyPartyInfo> for PartyInfo {
+    fn from(legacy: LegacyPartyInfo) -> Self {
+        PartyInfo {
+            common: legacy.common,
+            shares: legacy.shares,
+            tofnd: TofndInfo {
+                party_uids: legacy.tofnd.party_uids,
+                share_counts: legacy.tofnd.share_counts,
+                index: legacy.tofnd.index,
+                created_at: None,
+                keygen_mode: None,
+                tofnd_version: None,
+            },
+        }
+    }
+}
+
+/// Sign usage of a key, stored apart from its [PartyInfo] so that the shares are not rewritten on every sign
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Gg20KeyUsage {
+    pub(crate) sign_count: u64,
+    /// seconds since unix epoch at the time of the last successful sign
+    pub(crate) last_signed_at: Option<u64>,
+}
+
+/// Public information of a stored key; holds no secret material
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Gg20KeyInfo {
+    pub(crate) key_uid: String,
+    #[serde(serialize_with = "crate::output::serialize_hex")]
+    pub(crate) pub_key: Vec<u8>,
+    pub(crate) threshold: usize,
+    pub(crate) party_uids: Vec<String>,
+    pub(crate) share_counts: Vec<usize>,
+    /// our index in `party_uids`
+    pub(crate) index: usize,
+    /// number of shares stored in this kv store
+    pub(crate) local_shares: usize,
+    pub(crate) created_at: Option<u64>,
+    pub(crate) keygen_mode: Option<KeygenMode>,
+    pub(crate) tofnd_version: Option<String>,
+    #[serde(flatten)]
+    pub(crate) usage: Gg20KeyUsage,
 }
 
 /// `KeyShareKv` record
@@ -51,6 +155,24 @@ pub struct PartyInfo {
     pub(super) tofnd: TofndInfo,
 }
 
+/// A deleted key, kept until it is purged so that it can be undeleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gg20Tombstone {
+    pub(super) party_info: PartyInfo,
+    /// seconds since unix epoch at the time of the deletion
+    pub(super) deleted_at: u64,
+}
+
+impl Gg20Tombstone {
+    /// the tombstone of `party_info`, deleted at `deleted_at`
+    pub(crate) fn new(party_info: PartyInfo, deleted_at: u64) -> Self {
+        Gg20Tombstone {
+            party_info,
+            deleted_at,
+        }
+    }
+}
+
 impl PartyInfo {
     /// Get GroupPublicInfo and ShareSecretInfo from tofn to create PartyInfo
     /// Also needed in recovery
@@ -59,6 +181,7 @@ impl PartyInfo {
         uids: Vec<String>,
         share_counts: Vec<usize>,
         tofnd_index: usize,
+        keygen_mode: KeygenMode,
     ) -> Self {
         // grap the first share to acquire common data
         let common = secret_key_shares[0].group().clone();
@@ -74,6 +197,9 @@ impl PartyInfo {
             party_uids: uids,
             share_counts,
             index: tofnd_index,
+            created_at: Some(unix_timestamp()),
+            keygen_mode: Some(keygen_mode),
+            tofnd_version: Some(env!("CARGO_PKG_VERSION").to_string()),
         };
 
         PartyInfo {
@@ -83,6 +209,63 @@ impl PartyInfo {
         }
     }
 
+    /// Get the public information of the key `key_uid`, along with its `usage`
+    pub(crate) fn key_info(&self, key_uid: &str, usage: Gg20KeyUsage) -> Gg20KeyInfo {
+        Gg20KeyInfo {
+            key_uid: key_uid.to_owned(),
+            pub_key: self.common.encoded_pubkey(),
+            threshold: self.common.threshold(),
+            party_uids: self.tofnd.party_uids.clone(),
+            share_counts: self.tofnd.share_counts.clone(),
+            index: self.tofnd.index,
+            local_shares: self.shares.len(),
+            created_at: self.tofnd.created_at,
+            keygen_mode: self.tofnd.keygen_mode,
+            tofnd_version: self.tofnd.tofnd_version.clone(),
+            usage,
+        }
+    }
+
+    /// Rebuild the [SecretKeyShare]s of our shares.
+    /// tofn has no public constructor of [SecretKeyShare], but it is encoded as its group info followed by
+    /// its share, so each share is decoded from the encoding of its parts. The integration tests check
+    /// that the rebuilt shares hold the parts they were decoded from, in case tofn changes the encoding.
+    pub(super) fn secret_key_shares(&self) -> ServiceResult<Vec<SecretKeyShare>> {
+        self.shares
+            .iter()
+            .map(|share| {
+                let mut bytes = serialize(&(&self.common, share))
+                    .map_err(|_| Internal("failed to serialize key share".to_string()))?;
+                let secret_key_share = deserialize(&bytes);
+                // the encoding holds the secret share
+                bytes.zeroize();
+                secret_key_share.ok_or_else(|| {
+                    Internal(
+                        "tofn changed the encoding of SecretKeyShare; can't rebuild key share"
+                            .to_string(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that [PartyInfo::secret_key_shares] rebuilds the shares that were stored
+    #[cfg(test)]
+    pub(crate) fn assert_secret_key_shares_round_trip(&self) {
+        let secret_key_shares = self.secret_key_shares().unwrap();
+        assert_eq!(secret_key_shares.len(), self.shares.len());
+        for (secret_key_share, share) in secret_key_shares.iter().zip(&self.shares) {
+            assert_eq!(
+                serialize(secret_key_share.group()).unwrap(),
+                serialize(&self.common).unwrap()
+            );
+            assert_eq!(
+                serialize(secret_key_share.share()).unwrap(),
+                serialize(share).unwrap()
+            );
+        }
+    }
+
     /// log PartyInfo state
     pub(super) fn log_info(&self, session_id: &str, sign_span: Span) {
         let init_span = span!(parent: &sign_span, Level::INFO, "init");
@@ -0,0 +1,59 @@
+//! Tracks the sign usage of gg20 keys.
+//!
+//! Every successful sign increments the [Gg20KeyUsage] of its key. The usage is stored apart from the
+//! [super::types::PartyInfo] of the key, so that the shares are not rewritten on every sign, and it is
+//! updated after the signature is sent, so that signing is not slowed down.
+//!
+//! The usage of a deleted key is kept for undeletion, and purged along with the key.
+
+use super::types::{Gg20KeyUsage, PartyInfo};
+use crate::kv_manager::{error::KvResult, unix_timestamp, KvDomain, KvManager, KvType};
+
+impl KvType for Gg20KeyUsage {
+    const TYPE_ID: &'static str = "gg20_key_usage";
+    const VERSION: u32 = 1;
+    const DOMAIN: KvDomain = KvDomain::Gg20Usage;
+}
+
+/// implement key usage functions for KvManager
+impl KvManager {
+    /// Records a successful sign with the key `key_uid`.
+    /// Nothing is recorded if the key was deleted or purged after the sign.
+    pub(super) async fn record_sign(&self, key_uid: &str) -> KvResult<()> {
+        // concurrent signs with the same key must not overwrite each other's count
+        let _guard = self.lock_key_usage().await;
+
+        // a purge forgets the usage under this lock, after removing the key;
+        // recording the sign after that would leave a usage for a key that no longer exists
+        if !self.exists::<PartyInfo>(key_uid).await? {
+            return Ok(());
+        }
+
+        let mut usage = self.stored_key_usage(key_uid).await?;
+        usage.sign_count += 1;
+        usage.last_signed_at = Some(unix_timestamp());
+
+        // replace the stored usage in one write, so that a crash can't lose the count
+        self.replace(key_uid, usage).await
+    }
+
+    /// Get the sign usage of the key `key_uid`. A key that never signed has no usage stored.
+    pub(crate) async fn key_usage(&self, key_uid: &str) -> KvResult<Gg20KeyUsage> {
+        let _guard = self.lock_key_usage().await;
+        self.stored_key_usage(key_uid).await
+    }
+
+    /// Forgets the usage of the key `key_uid`, once the key is purged or its uid is used for a new key
+    pub(super) async fn forget_key_usage(&self, key_uid: &str) -> KvResult<()> {
+        let _guard = self.lock_key_usage().await;
+        self.delete::<Gg20KeyUsage>(key_uid).await
+    }
+
+    /// the stored usage of `key_uid`; the caller holds the usage lock
+    async fn stored_key_usage(&self, key_uid: &str) -> KvResult<Gg20KeyUsage> {
+        Ok(match self.exists::<Gg20KeyUsage>(key_uid).await? {
+            true => self.get::<Gg20KeyUsage>(key_uid).await?,
+            false => Gg20KeyUsage::default(),
+        })
+    }
+}
@@ -0,0 +1,256 @@
+//! Offline inspection of the keys of the kv store, for `tofnd keys`.
+//! Only public information is reported: secret shares and mnemonics are never part of the report.
+//!
+//! Multisig keys are derived from the mnemonic on every use, so only the keys whose origin was recorded are known.
+
+use serde::Serialize;
+use tokio_stream::StreamExt;
+use tracing::info;
+
+use crate::{
+    gg20::types::{Gg20KeyInfo, PartyInfo},
+    kv_manager::KvManager,
+    mnemonic::KeyScheme,
+    TofndResult,
+};
+use anyhow::anyhow;
+
+/// Commands of `tofnd keys`
+#[derive(Clone, Debug)]
+pub enum KeysCmd {
+    /// list all keys
+    List,
+    /// show the keys of a key uid
+    Show(String),
+}
+
+impl KeysCmd {
+    /// the name of the command
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeysCmd::List => "list",
+            KeysCmd::Show(_) => "show",
+        }
+    }
+}
+
+/// A mnemonic that a key was generated or recovered with
+#[derive(Clone, Debug, Serialize)]
+pub struct KeyMnemonic {
+    pub fingerprint: String,
+    /// the key that the mnemonic is stored under; unknown for pruned mnemonics
+    pub mnemonic: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Gg20Key {
+    #[serde(flatten)]
+    info: Gg20KeyInfo,
+    mnemonics: Vec<KeyMnemonic>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MultisigKey {
+    key_uid: String,
+    mnemonics: Vec<KeyMnemonic>,
+}
+
+/// The keys found by a [KeysCmd]
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct KeyInventory {
+    pub gg20: Vec<Gg20Key>,
+    pub multisig: Vec<MultisigKey>,
+}
+
+/// Finds the keys of `cmd` in the kv store of `kv` and logs them
+pub async fn handle_keys_cmd(kv: &KvManager, cmd: &KeysCmd) -> TofndResult<KeyInventory> {
+    let (gg20_uids, multisig_uids) = match cmd {
+        KeysCmd::List => (
+            gg20_key_uids(kv).await?,
+            kv.key_uids(KeyScheme::Multisig).await?,
+        ),
+        // key uids of different schemes may collide
+        KeysCmd::Show(key_uid) => {
+            let gg20_uids = match kv.exists::<PartyInfo>(key_uid).await? {
+                true => vec![key_uid.clone()],
+                false => vec![],
+            };
+            let multisig_uids = match kv
+                .key_origins(KeyScheme::Multisig, key_uid)
+                .await?
+                .is_empty()
+            {
+                true => vec![],
+                false => vec![key_uid.clone()],
+            };
+            if gg20_uids.is_empty() && multisig_uids.is_empty() {
+                return Err(anyhow!("key {} not found", key_uid));
+            }
+            (gg20_uids, multisig_uids)
+        }
+    };
+
+    let mut inventory = KeyInventory::default();
+    for key_uid in gg20_uids {
+        let usage = kv.key_usage(&key_uid).await?;
+        let key = Gg20Key {
+            info: kv
+                .get::<PartyInfo>(&key_uid)
+                .await?
+                .key_info(&key_uid, usage),
+            mnemonics: key_mnemonics(kv, KeyScheme::Gg20, &key_uid).await?,
+        };
+        info!(
+            "gg20 key '{}': (t,n)=({},{}), our index {} of parties {:?} with share counts {:?}, {} local shares, mnemonics {}",
+            key.info.key_uid,
+            key.info.threshold,
+            key.info.share_counts.iter().sum::<usize>(),
+            key.info.index,
+            key.info.party_uids,
+            key.info.share_counts,
+            key.info.local_shares,
+            describe(&key.mnemonics),
+        );
+        info!(
+            "gg20 key '{}': created at {}, {} primes, tofnd version {}, signed {} times, last at {}",
+            key.info.key_uid,
+            describe_unknown(key.info.created_at),
+            describe_unknown(key.info.keygen_mode),
+            describe_unknown(key.info.tofnd_version.as_ref()),
+            key.info.usage.sign_count,
+            key.info
+                .usage
+                .last_signed_at
+                .map_or_else(|| "never".to_owned(), |at| at.to_string()),
+        );
+        inventory.gg20.push(key);
+    }
+    for key_uid in multisig_uids {
+        let key = MultisigKey {
+            mnemonics: key_mnemonics(kv, KeyScheme::Multisig, &key_uid).await?,
+            key_uid,
+        };
+        info!(
+            "multisig key '{}': mnemonics {}",
+            key.key_uid,
+            describe(&key.mnemonics)
+        );
+        inventory.multisig.push(key);
+    }
+
+    if inventory.gg20.is_empty() && inventory.multisig.is_empty() {
+        info!("No keys found");
+    }
+    Ok(inventory)
+}
+
+/// Get the uids of all gg20 keys
+async fn gg20_key_uids(kv: &KvManager) -> TofndResult<Vec<String>> {
+    let mut key_uids = vec![];
+    let mut keys = kv.keys_with_prefix::<PartyInfo>("");
+    while let Some(key) = keys.next().await {
+        key_uids.push(key?);
+    }
+    Ok(key_uids)
+}
+
+/// Get the mnemonics that the key `key_uid` of `scheme` was generated or recovered with
+async fn key_mnemonics(
+    kv: &KvManager,
+    scheme: KeyScheme,
+    key_uid: &str,
+) -> TofndResult<Vec<KeyMnemonic>> {
+    let mut mnemonics = vec![];
+    for fingerprint in kv.key_origins(scheme, key_uid).await? {
+        mnemonics.push(KeyMnemonic {
+            fingerprint: fingerprint.to_string(),
+            mnemonic: kv.mnemonic_of_fingerprint(fingerprint).await?,
+        });
+    }
+    Ok(mnemonics)
+}
+
+/// describes `mnemonics` for logs
+fn describe(mnemonics: &[KeyMnemonic]) -> String {
+    if mnemonics.is_empty() {
+        return "not recorded".to_owned();
+    }
+    let mnemonics: Vec<String> = mnemonics
+        .iter()
+        .map(|mnemonic| match &mnemonic.mnemonic {
+            Some(key) => format!("{} ('{}')", mnemonic.fingerprint, key),
+            None => format!("{} (pruned)", mnemonic.fingerprint),
+        })
+        .collect();
+    mnemonics.join(", ")
+}
+
+/// describes an optional value for logs
+fn describe_unknown<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map_or_else(|| "unknown".to_owned(), |value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encrypted_sled::get_test_password,
+        mnemonic::{Cmd, MnemonicConfig},
+    };
+    use testdir::testdir;
+    use tracing_test::traced_test;
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_keys_cmd() {
+        let root = testdir!();
+        let kv = KvManager::new(root.join("kv"), get_test_password())
+            .unwrap()
+            .handle_mnemonic(
+                &Cmd::Create,
+                &MnemonicConfig {
+                    export_path: Some(root.join("export")),
+                    ..MnemonicConfig::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // an empty store has no keys
+        let inventory = handle_keys_cmd(&kv, &KeysCmd::List).await.unwrap();
+        assert!(inventory.gg20.is_empty() && inventory.multisig.is_empty());
+        assert!(handle_keys_cmd(&kv, &KeysCmd::Show("key".to_owned()))
+            .await
+            .is_err());
+
+        let seed = kv.seed().await.unwrap();
+        for key_uid in ["first", "second"] {
+            kv.record_key_seed(KeyScheme::Multisig, key_uid, "mnemonic", &seed)
+                .await
+                .unwrap();
+        }
+
+        let inventory = handle_keys_cmd(&kv, &KeysCmd::List).await.unwrap();
+        let key_uids: Vec<_> = inventory.multisig.iter().map(|key| &key.key_uid).collect();
+        assert_eq!(key_uids, ["first", "second"]);
+
+        let inventory = handle_keys_cmd(&kv, &KeysCmd::Show("second".to_owned()))
+            .await
+            .unwrap();
+        assert!(inventory.gg20.is_empty());
+        assert_eq!(inventory.multisig.len(), 1);
+        let mnemonics = &inventory.multisig[0].mnemonics;
+        assert_eq!(mnemonics.len(), 1);
+        assert_eq!(mnemonics[0].mnemonic.as_deref(), Some("mnemonic"));
+
+        // the report refers to the mnemonic by its fingerprint only
+        let report = serde_json::to_value(&inventory).unwrap();
+        assert_eq!(
+            report["multisig"][0],
+            serde_json::json!({
+                "key_uid": "second",
+                "mnemonics": [{ "fingerprint": mnemonics[0].fingerprint, "mnemonic": "mnemonic" }],
+            })
+        );
+    }
+}
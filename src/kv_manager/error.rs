@@ -30,6 +30,16 @@ pub enum KvError {
     DeleteErr(InnerKvError),
     #[error("Exits Error: {0}")]
     ExistsErr(InnerKvError),
+    #[error("Reservations Error: {0}")]
+    ReservationsErr(InnerKvError),
+    #[error("Migration Error: {0}")]
+    MigrationErr(InnerKvError),
+    #[error("Keys Error: {0}")]
+    KeysErr(InnerKvError),
+    #[error("Scan Error: {0}")]
+    ScanErr(InnerKvError),
+    #[error("Read-only Error: {0}")]
+    ReadOnlyErr(String),
 }
 pub type KvResult<Success> = Result<Success, KvError>;
 
@@ -40,6 +50,13 @@ pub enum InnerKvError {
     SledErr(#[from] encrypted_sled::Error),
     #[error("Logical Error: {0}")] // Logical errors (eg double deletion)
     LogicalErr(String),
+    #[error("Reserved Name Error: {0}")] // key uids that can't be used
+    ReservedNameErr(String),
+    #[error("Reserved Key Error: {0}")] // keys whose value is still being written
+    ReservedKeyErr(String),
+    #[error("Incompatible Value Error: {0}")]
+    // stored values of another type or an unknown version
+    IncompatibleValueErr(String),
     #[error("Serialization Error: failed to serialize value")]
     SerializationErr,
     #[error("Deserialization Error: failed to deserialize kvstore bytes")]
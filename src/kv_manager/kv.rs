@@ -4,11 +4,17 @@
 use crate::encrypted_sled::{self, Password};
 
 use super::{
-    error::{KvError::*, KvResult},
-    sled_bindings::{handle_delete, handle_exists, handle_get, handle_put, handle_reserve},
+    error::{InnerKvError, KvError, KvError::*, KvResult},
+    sled_bindings::{
+        handle_clear_reservation, handle_delete, handle_exists, handle_get,
+        handle_keys_with_prefix, handle_put, handle_replace, handle_reservations, handle_reserve,
+        handle_scan, handle_sweep_reservations,
+    },
     types::{
+        new_run_id,
         Command::{self, *},
-        KeyReservation, DEFAULT_KV_NAME, DEFAULT_KV_PATH,
+        KeyReservation, ReservationsCmd, ReservedKey, Responder, DEFAULT_KV_NAME, DEFAULT_KV_PATH,
+        KV_TREES, SCAN_PAGE_SIZE,
     },
 };
 use serde::{de::DeserializeOwned, Serialize};
@@ -29,30 +35,57 @@ impl<V: 'static> Kv<V>
 where
     V: Debug + Send + Sync + Serialize + DeserializeOwned,
 {
-    /// Creates a new kv service. Returns [InitErr] on failure.
-    /// the path of the kvstore is `root_path` + "/kvstore/" + `kv_name`
-    pub fn new(root_path: PathBuf, password: Password) -> KvResult<Self> {
-        let kv_path = root_path.join(DEFAULT_KV_PATH).join(DEFAULT_KV_NAME);
-        // use to_string_lossy() instead of to_str() to avoid handling Option<&str>
-        let kv_path = kv_path.to_string_lossy().to_string();
-        Self::with_db_name(kv_path, password)
-    }
-
-    /// Creates a kvstore at `full_db_name` and spawns a new kv_manager. Returns [InitErr] on failure.
+    /// Creates a kvstore at `full_db_name` and spawns a new kv_manager on its default tree.
+    /// Returns [InitErr] on failure.
     /// `full_db_name` is the name of the path of the kvstrore + its name
     /// Example: ~/tofnd/kvstore/database_1
+    /// Reservations left over from a previous run are removed. Returns [ReservationsErr] on failure.
     pub fn with_db_name(full_db_name: String, password: Password) -> KvResult<Self> {
-        let (sender, rx) = mpsc::unbounded_channel();
-
         // get kv store from db name before entering the kv_cmd_handler because
         // it's more convenient to return an error from outside of a tokio::span
         let kv = get_kv_store(&full_db_name, password)?;
+        Self::spawn(kv)
+    }
+
+    /// Spawns a new kv_manager on the tree `tree_name` of `db`. Returns [InitErr] on failure.
+    /// Reservations left over from a previous run are removed. Returns [ReservationsErr] on failure.
+    pub fn with_tree(db: &encrypted_sled::Db, tree_name: &str) -> KvResult<Self> {
+        Self::spawn(db.open_tree(tree_name)?)
+    }
 
-        tokio::spawn(kv_cmd_handler(rx, kv));
+    /// Spawns a read-only kv_manager on the tree `tree_name` of `db`. Returns [InitErr] on failure.
+    /// Reservations are left as they are, and commands that write are refused.
+    pub fn read_only_tree(db: &encrypted_sled::Db, tree_name: &str) -> KvResult<Self> {
+        let (sender, rx) = mpsc::unbounded_channel();
+        tokio::spawn(kv_cmd_handler(
+            rx,
+            db.open_tree(tree_name)?,
+            new_run_id(),
+            true,
+        ));
         Ok(Self { sender })
     }
 
-    /// Reserves a key in the kvstore with [super::types::DEFAULT_RESERV] value.
+    fn spawn(kv: encrypted_sled::Db) -> KvResult<Self> {
+        let (sender, rx) = mpsc::unbounded_channel();
+
+        // no reservation can survive a restart, so all existing reservations are stale
+        let owner = new_run_id();
+        let stale_keys = handle_sweep_reservations(&kv, &owner).map_err(ReservationsErr)?;
+        if !stale_keys.is_empty() {
+            warn!(
+                "kv_manager removed stale reservations from a previous run: {:?}",
+                stale_keys
+            );
+        }
+
+        tokio::spawn(kv_cmd_handler(rx, kv, owner, false));
+        Ok(Self { sender })
+    }
+
+    /// Reserves a key in the kvstore with a [super::types::ReservationInfo] value.
+    /// The key is unreserved when the returned [KeyReservation] is dropped without a successful [Kv::put],
+    /// including when it is never delivered because this future is cancelled.
     /// Returns [ReserveErr] or [SendErr] on failure.
     pub async fn reserve_key(&self, key: String) -> KvResult<KeyReservation> {
         let (resp_tx, resp_rx) = oneshot::channel();
@@ -62,12 +95,7 @@ where
         resp_rx.await?.map_err(ReserveErr)
     }
 
-    /// Unreserves an existing reservation
-    pub async fn unreserve_key(&self, reservation: KeyReservation) {
-        let _ = self.sender.send(UnreserveKey { reservation });
-    }
-
-    /// Puts a new value given a [super::types::KeyReservation]
+    /// Puts a new value given a [super::types::KeyReservation] and commits the reservation
     /// Returns [PutErr] or [SendErr] on failure.
     pub async fn put(&self, reservation: KeyReservation, value: V) -> KvResult<()> {
         let (resp_tx, resp_rx) = oneshot::channel();
@@ -94,6 +122,20 @@ where
         resp_rx.await?.map_err(GetErr)
     }
 
+    /// Puts a value to an unreserved key, replacing its value if it exists
+    /// Returns [PutErr] or [SendErr] on failure.
+    pub async fn replace(&self, key: &str, value: V) -> KvResult<()> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        self.sender
+            .send(Replace {
+                key: key.to_string(),
+                value,
+                resp: resp_tx,
+            })
+            .map_err(|e| SendErr(e.to_string()))?;
+        resp_rx.await?.map_err(PutErr)
+    }
+
     /// Deletes an unreserved key
     /// Returns [DeleteErr] or [SendErr] on failure.
     pub async fn delete(&self, key: &str) -> KvResult<()> {
@@ -119,6 +161,217 @@ where
             .map_err(|e| SendErr(e.to_string()))?;
         resp_rx.await?.map_err(ExistsErr)
     }
+
+    /// Streams all keys that start with `prefix`, including reserved keys.
+    /// Values are not read. Stream items are [KeysErr] or [SendErr] on failure.
+    pub fn keys_with_prefix(&self, prefix: &str) -> KvStream<String> {
+        let sender = self.sender.clone();
+        let prefix = prefix.to_string();
+        KvStream::new(
+            move |after, resp| {
+                sender
+                    .send(KeysWithPrefix {
+                        prefix: prefix.clone(),
+                        after,
+                        resp,
+                    })
+                    .map_err(|e| SendErr(e.to_string()))
+            },
+            KeysErr,
+        )
+    }
+
+    /// Streams all `(key, value)` pairs whose key starts with `prefix`. Reserved keys are skipped.
+    /// Stream items are [ScanErr] or [SendErr] on failure.
+    pub fn scan(&self, prefix: &str) -> KvStream<(String, V)> {
+        let sender = self.sender.clone();
+        let prefix = prefix.to_string();
+        KvStream::new(
+            move |after, resp| {
+                sender
+                    .send(Scan {
+                        prefix: prefix.clone(),
+                        after,
+                        resp,
+                    })
+                    .map_err(|e| SendErr(e.to_string()))
+            },
+            ScanErr,
+        )
+    }
+}
+
+/// An item of a [KvStream]; its key is where the next page of the stream starts
+pub trait KvStreamItem {
+    fn key(&self) -> &str;
+}
+
+impl KvStreamItem for String {
+    fn key(&self) -> &str {
+        self
+    }
+}
+
+impl<V> KvStreamItem for (String, V) {
+    fn key(&self) -> &str {
+        &self.0
+    }
+}
+
+/// sends the command that reads the page after the given key to the kv_manager
+type PageRequest<T> = Box<dyn Fn(Option<String>, Responder<Vec<T>>) -> KvResult<()> + Send + Sync>;
+
+/// Items streamed back from the kv_manager by [Kv::keys_with_prefix] and [Kv::scan].
+/// Items are read in pages of [SCAN_PAGE_SIZE], only as the stream is consumed, so that other
+/// commands to the kv_manager are handled between pages, and no more than a page is held in memory.
+pub struct KvStream<T> {
+    request: PageRequest<T>,
+    map_err: fn(InnerKvError) -> KvError,
+    page: std::vec::IntoIter<T>,
+    // where the next page starts; `None` once the last page is read or a page fails
+    next_page: Option<Option<String>>,
+}
+
+impl<T: KvStreamItem> KvStream<T> {
+    fn new<F>(request: F, map_err: fn(InnerKvError) -> KvError) -> Self
+    where
+        F: Fn(Option<String>, Responder<Vec<T>>) -> KvResult<()> + Send + Sync + 'static,
+    {
+        KvStream {
+            request: Box::new(request),
+            map_err,
+            page: vec![].into_iter(),
+            next_page: Some(None),
+        }
+    }
+
+    /// Returns the next item, or [None] if there are no more items
+    pub async fn next(&mut self) -> Option<KvResult<T>> {
+        loop {
+            if let Some(item) = self.page.next() {
+                return Some(Ok(item));
+            }
+            let after = self.next_page.take()?;
+            let page = match self.read_page(after).await {
+                Ok(page) => page,
+                Err(err) => return Some(Err(err)),
+            };
+            if page.len() == SCAN_PAGE_SIZE {
+                self.next_page = page.last().map(|item| Some(item.key().to_string()));
+            }
+            self.page = page.into_iter();
+        }
+    }
+
+    /// Receives all remaining items. Returns the first error.
+    pub async fn collect(mut self) -> KvResult<Vec<T>> {
+        let mut items = vec![];
+        while let Some(item) = self.next().await {
+            items.push(item?);
+        }
+        Ok(items)
+    }
+
+    /// reads the page that starts after the key `after`, if any
+    async fn read_page(&self, after: Option<String>) -> KvResult<Vec<T>> {
+        let (resp_tx, resp_rx) = oneshot::channel();
+        (self.request)(after, resp_tx)?;
+        resp_rx.await?.map_err(self.map_err)
+    }
+}
+
+/// the full name of the default kvstore under `root_path`
+fn default_db_name(root_path: PathBuf) -> String {
+    let kv_path = root_path.join(DEFAULT_KV_PATH).join(DEFAULT_KV_NAME);
+    // use to_string_lossy() instead of to_str() to avoid handling Option<&str>
+    kv_path.to_string_lossy().to_string()
+}
+
+/// Opens the default kvstore under `root_path`, or creates a new one if it does not exist.
+/// the path of the kvstore is `root_path` + "/kvstore/" + `kv_name`
+/// Returns [InitErr] on failure.
+pub(super) fn open_default_db(
+    root_path: PathBuf,
+    password: Password,
+) -> KvResult<encrypted_sled::Db> {
+    Ok(get_kv_store(&default_db_name(root_path), password)?)
+}
+
+/// Opens the existing default kvstore under `root_path`, like [open_default_db].
+/// Returns [ReadOnlyErr] if there is no kvstore, instead of creating one.
+pub(super) fn open_existing_db(
+    root_path: PathBuf,
+    password: Password,
+) -> KvResult<encrypted_sled::Db> {
+    let db_name = default_db_name(root_path);
+    if !std::path::Path::new(&db_name).exists() {
+        return Err(ReadOnlyErr(format!("no kvstore found at {}", db_name)));
+    }
+    Ok(get_kv_store(&db_name, password)?)
+}
+
+/// Admin operation on key reservations of the default kvstore under `root_path`.
+/// Runs offline, without sweeping stale reservations first, so that they can be inspected.
+/// [ReservationsCmd::List] logs all reservations with their tree, owner and timestamp.
+/// [ReservationsCmd::Clear] removes all reservations.
+/// Returns the reservations that were found, or [InitErr] or [ReservationsErr] on failure.
+pub fn handle_reservations_cmd(
+    root_path: PathBuf,
+    password: Password,
+    cmd: &ReservationsCmd,
+) -> KvResult<Vec<ReservedKey>> {
+    let db = open_default_db(root_path, password)?;
+
+    // the default tree holds reservations of a kvstore that has not been migrated yet
+    let mut found = handle_tree_reservations(&db, "default", cmd)?;
+    for tree_name in KV_TREES {
+        found.extend(handle_tree_reservations(
+            &db.open_tree(tree_name)?,
+            tree_name,
+            cmd,
+        )?);
+    }
+
+    if found.is_empty() {
+        info!("No reserved keys found");
+    }
+
+    Ok(found)
+}
+
+/// Logs the reservations of the tree `kv` and clears them on [ReservationsCmd::Clear].
+/// Returns the reservations that were found.
+fn handle_tree_reservations(
+    kv: &encrypted_sled::Db,
+    tree_name: &str,
+    cmd: &ReservationsCmd,
+) -> KvResult<Vec<ReservedKey>> {
+    let reservations = handle_reservations(kv).map_err(ReservationsErr)?;
+    for (key, reservation) in &reservations {
+        info!(
+            "Reserved key <{}> in tree <{}> [owner: {}, timestamp: {}]",
+            key, tree_name, reservation.owner, reservation.timestamp
+        );
+    }
+
+    if let ReservationsCmd::Clear = cmd {
+        for (key, _) in &reservations {
+            handle_clear_reservation(kv, key).map_err(ReservationsErr)?;
+            info!(
+                "Cleared reservation of key <{}> in tree <{}>",
+                key, tree_name
+            );
+        }
+    }
+
+    Ok(reservations
+        .into_iter()
+        .map(|(key, info)| ReservedKey {
+            key,
+            tree: tree_name.to_owned(),
+            info,
+        })
+        .collect())
 }
 
 /// Returns the db with name `db_name`, or creates a new if such DB does not exist
@@ -149,33 +402,76 @@ pub fn get_kv_store(
     Ok(kv)
 }
 
+/// fails if the kv_manager is read-only
+fn check_writable(read_only: bool) -> Result<(), InnerKvError> {
+    match read_only {
+        true => Err(InnerKvError::LogicalErr(
+            "kvstore is opened read-only".to_string(),
+        )),
+        false => Ok(()),
+    }
+}
+
 // private handler function to process commands as per the "actor" pattern (see above)
+// a `read_only` handler refuses commands that write
 async fn kv_cmd_handler<V: 'static>(
     mut rx: mpsc::UnboundedReceiver<Command<V>>,
     kv: encrypted_sled::Db,
+    owner: String,
+    read_only: bool,
 ) where
     V: Serialize + DeserializeOwned,
 {
+    // reservations are released through their own channel when they are dropped uncommitted.
+    // The handler keeps a sender, so the channel never closes while commands are handled.
+    let (unreserve_tx, mut unreserve_rx) = mpsc::unbounded_channel::<String>();
+
     // if resp.send() fails then log a warning and continue
     // see discussion https://github.com/axelarnetwork/tofnd/pull/15#discussion_r595426775
-    while let Some(cmd) = rx.recv().await {
+    loop {
+        let cmd = tokio::select! {
+            // released keys come first, so a key is unreserved before any command that was sent
+            // after its reservation was dropped
+            biased;
+            Some(key) = unreserve_rx.recv() => {
+                if let Err(err) = handle_clear_reservation(&kv, &key) {
+                    warn!("could not unreserve key <{}>: {}", key, err);
+                }
+                continue;
+            }
+            cmd = rx.recv() => match cmd {
+                Some(cmd) => cmd,
+                // reservations that are still held are swept at the next start
+                None => break,
+            },
+        };
+
         // TODO better error handling and logging: we should log when `handle_*` fails
         // TODO refactor repeated code
         match cmd {
             ReserveKey { key, resp } => {
-                if resp.send(handle_reserve(&kv, key)).is_err() {
+                // the release is attached before the reply, so a reservation that never reaches
+                // its caller, because the caller is gone, is released when it is dropped
+                let unreserve_tx = unreserve_tx.clone();
+                let result = check_writable(read_only)
+                    .and_then(|_| handle_reserve(&kv, key, &owner))
+                    .map(|reservation| {
+                        reservation.with_unreserve(move |key| {
+                            let _ = unreserve_tx.send(key);
+                        })
+                    });
+                if resp.send(result).is_err() {
                     warn!("receiver dropped");
                 }
             }
-            UnreserveKey { reservation } => {
-                let _ = kv.remove(&reservation.key);
-            }
             Put {
                 reservation,
                 value,
                 resp,
             } => {
-                if resp.send(handle_put(&kv, reservation, value)).is_err() {
+                let result =
+                    check_writable(read_only).and_then(|_| handle_put(&kv, reservation, value));
+                if resp.send(result).is_err() {
                     warn!("receiver dropped");
                 }
             }
@@ -189,8 +485,40 @@ async fn kv_cmd_handler<V: 'static>(
                     warn!("receiver dropped");
                 }
             }
+            Replace { key, value, resp } => {
+                let result =
+                    check_writable(read_only).and_then(|_| handle_replace(&kv, key, value));
+                if resp.send(result).is_err() {
+                    warn!("receiver dropped");
+                }
+            }
             Delete { key, resp } => {
-                if resp.send(handle_delete(&kv, key)).is_err() {
+                let result = check_writable(read_only).and_then(|_| handle_delete(&kv, key));
+                if resp.send(result).is_err() {
+                    warn!("receiver dropped");
+                }
+            }
+            KeysWithPrefix {
+                prefix,
+                after,
+                resp,
+            } => {
+                if resp
+                    .send(handle_keys_with_prefix(&kv, &prefix, after.as_deref()))
+                    .is_err()
+                {
+                    warn!("receiver dropped");
+                }
+            }
+            Scan {
+                prefix,
+                after,
+                resp,
+            } => {
+                if resp
+                    .send(handle_scan(&kv, &prefix, after.as_deref()))
+                    .is_err()
+                {
                     warn!("receiver dropped");
                 }
             }
@@ -0,0 +1,52 @@
+//! Migration from the flat layout of older versions of tofnd, where mnemonics,
+//! the mnemonic count and gg20 shares shared the default tree.
+
+use super::{
+    error::InnerKvResult,
+    sled_bindings::reservation_info,
+    types::{GG20_SHARES_TREE, MNEMONIC_META_TREE, MNEMONIC_TREE},
+};
+use crate::{
+    encrypted_sled,
+    mnemonic::{rotated_mnemonic_index, MNEMONIC_COUNT_KEY, MNEMONIC_KEY},
+};
+
+/// Returns the tree that `key` of the flat layout belongs to
+fn tree_of(key: &str) -> &'static str {
+    if key == MNEMONIC_COUNT_KEY {
+        MNEMONIC_META_TREE
+    } else if key == MNEMONIC_KEY || rotated_mnemonic_index(key).is_some() {
+        MNEMONIC_TREE
+    } else {
+        GG20_SHARES_TREE
+    }
+}
+
+/// Moves all entries of the default tree of `db` to their domain trees.
+/// Reservations are left over from a previous run and are dropped.
+/// Entries are copied before they are removed, so an interrupted migration can be resumed.
+/// Returns the number of moved entries.
+pub(super) fn migrate_flat_layout(db: &encrypted_sled::Db) -> InnerKvResult<usize> {
+    let mnemonics = db.open_tree(MNEMONIC_TREE)?;
+    let mnemonic_meta = db.open_tree(MNEMONIC_META_TREE)?;
+    let shares = db.open_tree(GG20_SHARES_TREE)?;
+
+    let mut moved = 0;
+    for entry in db.iter() {
+        let (key, value) = entry?;
+
+        if reservation_info(&value).is_none() {
+            let tree = match tree_of(&String::from_utf8_lossy(&key)) {
+                MNEMONIC_TREE => &mnemonics,
+                MNEMONIC_META_TREE => &mnemonic_meta,
+                _ => &shares,
+            };
+            tree.insert(&key, value)?;
+            moved += 1;
+        }
+
+        db.remove(&key)?;
+    }
+
+    Ok(moved)
+}
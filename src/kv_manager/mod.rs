@@ -8,6 +8,8 @@
 pub mod error;
 /// public API of kv manager
 mod kv;
+/// migration from the flat kvstore layout to separate trees
+mod migration;
 /// sled bindings for basic kv operations
 mod sled_bindings;
 /// definition of kv_manager types and default paths
@@ -15,9 +17,11 @@ mod types;
 /// wrapers for values stored by tofnd services
 mod value;
 
-pub use types::KeyReservation;
-pub use value::KvManager;
+pub use kv::{handle_reservations_cmd, KvStream};
+pub(crate) use types::unix_timestamp;
+pub use types::{KeyReservation, KvDomain, ReservationsCmd, ReservedKey, GG20_SHARES_TREE};
+pub use value::{unknown_version, KvManager, KvType};
 
 // tests for low-level operations
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
@@ -4,15 +4,37 @@ use serde::{de::DeserializeOwned, Serialize};
 use tofn::sdk::api::{deserialize, serialize};
 
 use super::error::{InnerKvError::*, InnerKvResult};
-use super::types::{KeyReservation, DEFAULT_RESERVE};
+use super::types::{
+    KeyReservation, ReservationInfo, DEFAULT_RESERVE, RESERVE_PREFIX, SCAN_PAGE_SIZE,
+};
 
 use crate::encrypted_sled;
 
-/// Reserves a key. New key's value is [DEFAULT_RESERVE].
+/// Returns the [ReservationInfo] if `bytes` is the value of a reserved key, [None] otherwise.
+/// Reservations made by older versions of tofnd carry no info; their owner is empty.
+pub(super) fn reservation_info(bytes: &[u8]) -> Option<ReservationInfo> {
+    if bytes == DEFAULT_RESERVE.as_bytes() {
+        return Some(ReservationInfo {
+            owner: String::new(),
+            timestamp: 0,
+        });
+    }
+    bytes
+        .strip_prefix(RESERVE_PREFIX)
+        .and_then(|info_bytes| deserialize(info_bytes))
+}
+
+/// Returns `true` if `key` exists and holds a reservation
+fn is_reserved(kv: &encrypted_sled::Db, key: &str) -> InnerKvResult<bool> {
+    Ok(matches!(kv.get(key)?, Some(bytes) if reservation_info(&bytes).is_some()))
+}
+
+/// Reserves a key. New key's value is a [ReservationInfo] tagged with `owner`.
 /// Returns [SledErr] of [LogicalErr] on failure.
 pub(super) fn handle_reserve(
     kv: &encrypted_sled::Db,
     key: String,
+    owner: &str,
 ) -> InnerKvResult<KeyReservation> {
     // search key in kv store.
     // If reserve key already exists inside our database, return an error
@@ -23,11 +45,12 @@ pub(super) fn handle_reserve(
         )));
     }
 
-    // try to insert the new key with default value
-    kv.insert(&key, DEFAULT_RESERVE)?;
+    // try to insert the new key with the reservation info
+    let info = serialize(&ReservationInfo::new(owner)).map_err(|_| SerializationErr)?;
+    kv.insert(&key, [RESERVE_PREFIX, info.as_slice()].concat())?;
 
     // return key reservation
-    Ok(KeyReservation { key })
+    Ok(KeyReservation::new(key))
 }
 
 /// Deletes an unreserved key if it exists.
@@ -37,8 +60,8 @@ pub(super) fn handle_delete(kv: &encrypted_sled::Db, key: String) -> InnerKvResu
         return Ok(());
     }
 
-    // check if key holds the default reserve value. If yes, can't delete it.
-    if kv.get(&key)? == Some(sled::IVec::from(DEFAULT_RESERVE)) {
+    // check if key holds a reservation. If yes, can't delete it.
+    if is_reserved(kv, &key)? {
         return Err(LogicalErr(format!(
             "can't delete reserved key <{}> in kv store.",
             key
@@ -50,21 +73,19 @@ pub(super) fn handle_delete(kv: &encrypted_sled::Db, key: String) -> InnerKvResu
     Ok(())
 }
 
-/// Inserts a value to an existing key.
+/// Inserts a value to an existing key and commits the reservation.
+/// If the value can't be inserted, the reservation is dropped uncommitted.
 /// Returns [SledErr] of [LogicalErr] on failure.
 pub(super) fn handle_put<V>(
     kv: &encrypted_sled::Db,
-    reservation: KeyReservation,
+    mut reservation: KeyReservation,
     value: V,
 ) -> InnerKvResult<()>
 where
     V: Serialize,
 {
-    // check if key holds the default reserve value. If not, send an error.
-    // Explanation of code ugliness: that's the standard way to compare a
-    // sled retrieved value with a local value:
-    // https://docs.rs/sled/0.34.6/sled/struct.Tree.html#examples-4
-    if kv.get(&reservation.key)? != Some(sled::IVec::from(DEFAULT_RESERVE)) {
+    // check if key holds a reservation. If not, send an error.
+    if !is_reserved(kv, &reservation.key)? {
         return Err(LogicalErr(format!(
             "did not find reservation for key <{}> in kv store.",
             reservation.key
@@ -76,18 +97,45 @@ where
 
     // insert new value
     kv.insert(&reservation.key, bytes)?;
+    reservation.commit();
 
     Ok(())
 }
 
-/// Get the value of an existing key.
+/// Inserts a value to an unreserved key, replacing its value if it exists.
+/// The value is replaced in a single write, so the key is never left without a value.
 /// Returns [SledErr] of [LogicalErr] on failure.
+pub(super) fn handle_replace<V>(kv: &encrypted_sled::Db, key: String, value: V) -> InnerKvResult<()>
+where
+    V: Serialize,
+{
+    // check if key holds a reservation. If yes, can't replace it.
+    if is_reserved(kv, &key)? {
+        return Err(LogicalErr(format!(
+            "can't replace reserved key <{}> in kv store.",
+            key
+        )));
+    }
+
+    // convert value into bytes
+    let bytes = serialize(&value).map_err(|_| SerializationErr)?;
+
+    kv.insert(&key, bytes)?;
+
+    Ok(())
+}
+
+/// Get the value of an existing key.
+/// Returns [ReservedKeyErr] if the key holds a reservation, [SledErr] of [LogicalErr] on other failures.
 pub(super) fn handle_get<V>(kv: &encrypted_sled::Db, key: String) -> InnerKvResult<V>
 where
     V: DeserializeOwned,
 {
     // try to get value of 'key'
     let value = match kv.get(&key)? {
+        Some(bytes) if reservation_info(&bytes).is_some() => {
+            return Err(ReservedKeyErr(format!("key <{}> is reserved.", key)));
+        }
         Some(bytes) => deserialize(&bytes).ok_or(DeserializationErr)?,
         None => {
             return Err(LogicalErr(format!("key <{}> does not have a value.", key)));
@@ -108,3 +156,93 @@ pub(super) fn handle_exists(kv: &encrypted_sled::Db, key: &str) -> InnerKvResult
         ))
     })
 }
+
+/// Reads the next page of at most [SCAN_PAGE_SIZE] keys that start with `prefix`, including reserved keys.
+/// The page starts after the key `after`, if any. No value is decrypted.
+/// Returns [SledErr] on failure.
+pub(super) fn handle_keys_with_prefix(
+    kv: &encrypted_sled::Db,
+    prefix: &str,
+    after: Option<&str>,
+) -> InnerKvResult<Vec<String>> {
+    kv.keys_with_prefix(prefix.as_bytes(), after.map(str::as_bytes))
+        .take(SCAN_PAGE_SIZE)
+        .map(|key| Ok(String::from_utf8_lossy(&key?).to_string()))
+        .collect()
+}
+
+/// Reads the next page of at most [SCAN_PAGE_SIZE] `(key, value)` pairs whose key starts with `prefix`.
+/// The page starts after the key `after`, if any. Reserved keys are skipped.
+/// Returns [SledErr] or [DeserializationErr] on failure.
+pub(super) fn handle_scan<V>(
+    kv: &encrypted_sled::Db,
+    prefix: &str,
+    after: Option<&str>,
+) -> InnerKvResult<Vec<(String, V)>>
+where
+    V: DeserializeOwned,
+{
+    let mut page = vec![];
+    for entry in kv.scan_prefix(prefix.as_bytes(), after.map(str::as_bytes)) {
+        let (key, bytes) = entry?;
+        if reservation_info(&bytes).is_some() {
+            continue;
+        }
+        let value = deserialize(&bytes).ok_or(DeserializationErr)?;
+        page.push((String::from_utf8_lossy(&key).to_string(), value));
+        if page.len() == SCAN_PAGE_SIZE {
+            break;
+        }
+    }
+    Ok(page)
+}
+
+/// Lists all reserved keys along with their [ReservationInfo].
+/// Returns [SledErr] on failure.
+pub(super) fn handle_reservations(
+    kv: &encrypted_sled::Db,
+) -> InnerKvResult<Vec<(String, ReservationInfo)>> {
+    let mut reservations = vec![];
+    for entry in kv.iter() {
+        let (key, value) = entry?;
+        if let Some(info) = reservation_info(&value) {
+            reservations.push((String::from_utf8_lossy(&key).to_string(), info));
+        }
+    }
+    Ok(reservations)
+}
+
+/// Removes the reservation of `key`.
+/// Returns [LogicalErr] if `key` does not hold a reservation.
+pub(super) fn handle_clear_reservation(kv: &encrypted_sled::Db, key: &str) -> InnerKvResult<()> {
+    if !is_reserved(kv, key)? {
+        return Err(LogicalErr(format!(
+            "key <{}> does not hold a reservation.",
+            key
+        )));
+    }
+
+    kv.remove(key)?;
+
+    Ok(())
+}
+
+/// Removes all reservations that were not made by `owner`.
+/// Reservations can't outlive the process that made them, so these are left over from a crash.
+/// Returns the removed keys.
+pub(super) fn handle_sweep_reservations(
+    kv: &encrypted_sled::Db,
+    owner: &str,
+) -> InnerKvResult<Vec<String>> {
+    let stale_keys: Vec<String> = handle_reservations(kv)?
+        .into_iter()
+        .filter(|(_, info)| info.owner != owner)
+        .map(|(key, _)| key)
+        .collect();
+
+    for key in &stale_keys {
+        kv.remove(key)?;
+    }
+
+    Ok(stale_keys)
+}
@@ -1,20 +1,44 @@
 //! [sled_bindings] tests
 
 use super::{
-    error::InnerKvError::LogicalErr,
-    sled_bindings::{handle_exists, handle_get, handle_put, handle_reserve},
-    types::{KeyReservation, DEFAULT_RESERVE},
+    error::{
+        InnerKvError::{IncompatibleValueErr, LogicalErr, ReservedKeyErr, ReservedNameErr},
+        KvError::{DeleteErr, ReadOnlyErr, ReserveErr},
+    },
+    kv::{get_kv_store, Kv},
+    migration::migrate_flat_layout,
+    sled_bindings::{
+        handle_clear_reservation, handle_delete, handle_exists, handle_get, handle_put,
+        handle_replace, handle_reservations, handle_reserve, handle_sweep_reservations,
+        reservation_info,
+    },
+    types::{
+        KeyReservation, DEFAULT_RESERVE, GG20_SHARES_TREE, MNEMONIC_META_TREE, MNEMONIC_TREE,
+        SCAN_PAGE_SIZE,
+    },
+    value::{decode, encode},
+    KvManager,
+};
+use crate::{
+    encrypted_sled::{self, get_test_password},
+    gg20::types::{Entropy, Gg20KeyUsage, PartyInfo},
+    mnemonic::{
+        KeyOrigin, MnemonicCount, MnemonicFingerprint, MnemonicIndex, MnemonicLanguage,
+        MnemonicRecord,
+    },
 };
-use crate::encrypted_sled;
 
 // testdir creates a test directory at $TMPDIR.
 // Mac: /var/folders/v4/x_j3jj7d6ql4gjdf7b7jvjhm0000gn/T/testdir-of-$(USER)
 // Linux: /tmp
 // Windows: /data/local/tmp
 // https://doc.rust-lang.org/std/env/fn.temp_dir.html#unix
+use futures_util::FutureExt;
 use testdir::testdir;
 use tofn::sdk::api::deserialize;
 
+const TEST_OWNER: &str = "test_owner";
+
 fn clean_up(kv_name: &str, kv: encrypted_sled::Db) {
     assert!(kv.flush().is_ok());
     std::fs::remove_dir_all(kv_name).unwrap();
@@ -34,15 +58,15 @@ fn reserve_success() {
 
     let key: String = "key".to_string();
     assert_eq!(
-        handle_reserve(&kv, key.clone()).unwrap(),
-        KeyReservation { key: key.clone() }
+        handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap(),
+        KeyReservation::new(key.clone())
     );
 
-    // check if default value was stored
+    // check if reservation info was stored
     // get bytes
-    let default_reserv = kv.get(&key).unwrap().unwrap();
-    // convert to value type
-    assert!(default_reserv == DEFAULT_RESERVE);
+    let reservation = kv.get(&key).unwrap().unwrap();
+    // convert to reservation info
+    assert_eq!(reservation_info(&reservation).unwrap().owner, TEST_OWNER);
 
     clean_up(kv_name.to_str().unwrap(), kv);
 }
@@ -53,9 +77,9 @@ fn reserve_failure() {
     let kv = open_with_test_password(&kv_name).unwrap();
 
     let key: String = "key".to_string();
-    handle_reserve(&kv, key.clone()).unwrap();
+    handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
     // try reserving twice
-    let err = handle_reserve(&kv, key).err().unwrap();
+    let err = handle_reserve(&kv, key, TEST_OWNER).err().unwrap();
     assert!(matches!(err, LogicalErr(_)));
     clean_up(kv_name.to_str().unwrap(), kv);
 }
@@ -66,10 +90,10 @@ fn put_success() {
     let kv = open_with_test_password(&kv_name).unwrap();
 
     let key: String = "key".to_string();
-    handle_reserve(&kv, key.clone()).unwrap();
+    handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
 
     let value: String = "value".to_string();
-    assert!(handle_put(&kv, KeyReservation { key }, value).is_ok());
+    assert!(handle_put(&kv, KeyReservation::new(key), value).is_ok());
 
     clean_up(kv_name.to_str().unwrap(), kv);
 }
@@ -83,7 +107,7 @@ fn put_failure_no_reservation() {
 
     let value: String = "value".to_string();
     // try to add put a key without reservation and get an error
-    let err = handle_put(&kv, KeyReservation { key: key.clone() }, value)
+    let err = handle_put(&kv, KeyReservation::new(key.clone()), value)
         .err()
         .unwrap();
     assert!(matches!(err, LogicalErr(_)));
@@ -102,10 +126,10 @@ fn put_failure_put_twice() {
     let value = "value".to_string();
     let value2 = "value2".to_string();
 
-    handle_reserve(&kv, key.clone()).unwrap();
-    handle_put(&kv, KeyReservation { key: key.clone() }, value.clone()).unwrap();
+    handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
+    handle_put(&kv, KeyReservation::new(key.clone()), value.clone()).unwrap();
 
-    let err = handle_put(&kv, KeyReservation { key: key.clone() }, value2)
+    let err = handle_put(&kv, KeyReservation::new(key.clone()), value2)
         .err()
         .unwrap();
     assert!(matches!(err, LogicalErr(_)));
@@ -128,8 +152,8 @@ fn get_success() {
 
     let key: String = "key".to_string();
     let value = "value";
-    handle_reserve(&kv, key.clone()).unwrap();
-    handle_put(&kv, KeyReservation { key: key.clone() }, value).unwrap();
+    handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
+    handle_put(&kv, KeyReservation::new(key.clone()), value).unwrap();
     let res = handle_get::<String>(&kv, key);
     assert!(res.is_ok());
     let res = res.unwrap();
@@ -144,8 +168,40 @@ fn get_failure() {
     let kv = open_with_test_password(&kv_name).unwrap();
 
     let key: String = "key".to_string();
+    let err = handle_get::<String>(&kv, key.clone()).err().unwrap();
+    assert!(matches!(err, LogicalErr(_)));
+
+    // a reserved key has no value yet
+    handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
     let err = handle_get::<String>(&kv, key).err().unwrap();
+    assert!(matches!(err, ReservedKeyErr(_)));
+
+    clean_up(kv_name.to_str().unwrap(), kv);
+}
+
+#[test]
+fn replace_value() {
+    let kv_name = testdir!();
+    let kv = open_with_test_password(&kv_name).unwrap();
+    let key: String = "key".to_string();
+
+    // a missing key is inserted, an existing one is overwritten
+    handle_replace(&kv, key.clone(), "value").unwrap();
+    assert_eq!(handle_get::<String>(&kv, key.clone()).unwrap(), "value");
+    handle_replace(&kv, key.clone(), "value2").unwrap();
+    assert_eq!(handle_get::<String>(&kv, key).unwrap(), "value2");
+
+    // a reservation is not overwritten
+    let reserved_key: String = "reserved_key".to_string();
+    handle_reserve(&kv, reserved_key.clone(), TEST_OWNER).unwrap();
+    let err = handle_replace(&kv, reserved_key.clone(), "value")
+        .err()
+        .unwrap();
     assert!(matches!(err, LogicalErr(_)));
+    assert!(matches!(
+        handle_get::<String>(&kv, reserved_key).err().unwrap(),
+        ReservedKeyErr(_)
+    ));
 
     clean_up(kv_name.to_str().unwrap(), kv);
 }
@@ -163,7 +219,7 @@ fn test_exists() {
     assert!(!exists.unwrap()); // assert that the result is false
 
     // reserve key
-    let reservation = handle_reserve(&kv, key.clone()).unwrap();
+    let reservation = handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
 
     // exists should succeed
     let exists = handle_exists(&kv, &key);
@@ -187,3 +243,507 @@ fn test_exists() {
     assert!(exists.is_ok());
     assert!(!exists.unwrap()); // check that the result is false
 }
+
+#[test]
+fn sweep_stale_reservations() {
+    let kv_name = testdir!();
+    let kv = open_with_test_password(&kv_name).unwrap();
+
+    let reserved_key: String = "reserved_key".to_string();
+    let key: String = "key".to_string();
+    let value: String = "value".to_string();
+
+    // reserve a key and crash before putting a value
+    handle_reserve(&kv, reserved_key.clone(), TEST_OWNER).unwrap();
+    let reservation = handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
+    handle_put(&kv, reservation, value.clone()).unwrap();
+    drop(kv);
+
+    // restart with a new owner
+    let kv = open_with_test_password(&kv_name).unwrap();
+
+    // reserved key can't be deleted or reserved again
+    assert!(matches!(
+        handle_delete(&kv, reserved_key.clone()).err().unwrap(),
+        LogicalErr(_)
+    ));
+    assert!(handle_reserve(&kv, reserved_key.clone(), "new_owner").is_err());
+
+    // sweeping removes the stale reservation only
+    assert_eq!(
+        handle_sweep_reservations(&kv, "new_owner").unwrap(),
+        vec![reserved_key.clone()]
+    );
+    assert!(!handle_exists(&kv, &reserved_key).unwrap());
+    assert_eq!(handle_get::<String>(&kv, key).unwrap(), value);
+
+    // key can be reserved again, and reservations of the current owner are not swept
+    handle_reserve(&kv, reserved_key.clone(), "new_owner").unwrap();
+    assert!(handle_sweep_reservations(&kv, "new_owner")
+        .unwrap()
+        .is_empty());
+    assert!(handle_exists(&kv, &reserved_key).unwrap());
+
+    clean_up(kv_name.to_str().unwrap(), kv);
+}
+
+#[test]
+fn sweep_legacy_reservations() {
+    let kv_name = testdir!();
+    let kv = open_with_test_password(&kv_name).unwrap();
+
+    // reservations of older versions hold an empty value
+    let key: String = "key".to_string();
+    kv.insert(&key, DEFAULT_RESERVE).unwrap();
+
+    assert_eq!(
+        handle_sweep_reservations(&kv, TEST_OWNER).unwrap(),
+        vec![key.clone()]
+    );
+    assert!(!handle_exists(&kv, &key).unwrap());
+
+    clean_up(kv_name.to_str().unwrap(), kv);
+}
+
+#[test]
+fn list_and_clear_reservations() {
+    let kv_name = testdir!();
+    let kv = open_with_test_password(&kv_name).unwrap();
+
+    let key: String = "key".to_string();
+    let reserved_key: String = "reserved_key".to_string();
+
+    let reservation = handle_reserve(&kv, key.clone(), TEST_OWNER).unwrap();
+    handle_put(&kv, reservation, "value").unwrap();
+    handle_reserve(&kv, reserved_key.clone(), TEST_OWNER).unwrap();
+
+    // only the reserved key is listed
+    let reservations = handle_reservations(&kv).unwrap();
+    assert_eq!(reservations.len(), 1);
+    assert_eq!(reservations[0].0, reserved_key);
+    assert_eq!(reservations[0].1.owner, TEST_OWNER);
+
+    // can't clear a key that holds a value
+    assert!(matches!(
+        handle_clear_reservation(&kv, &key).err().unwrap(),
+        LogicalErr(_)
+    ));
+
+    // clear the reservation
+    handle_clear_reservation(&kv, &reserved_key).unwrap();
+    assert!(handle_reservations(&kv).unwrap().is_empty());
+    assert!(handle_exists(&kv, &key).unwrap());
+
+    clean_up(kv_name.to_str().unwrap(), kv);
+}
+
+#[tokio::test]
+async fn dropped_reservation_is_released() {
+    let kv_name = testdir!();
+    let kv = Kv::<String>::with_db_name(kv_name.to_string_lossy().to_string(), get_test_password())
+        .unwrap();
+
+    let key: String = "key".to_string();
+
+    // drop the reservation without putting a value
+    let reservation = kv.reserve_key(key.clone()).await.unwrap();
+    drop(reservation);
+    assert!(!kv.exists(&key).await.unwrap());
+
+    // key can be reserved again
+    assert!(kv.reserve_key(key.clone()).await.is_ok());
+}
+
+#[tokio::test]
+async fn undelivered_reservation_is_released() {
+    let kv_name = testdir!();
+    let kv = Kv::<String>::with_db_name(kv_name.to_string_lossy().to_string(), get_test_password())
+        .unwrap();
+
+    let key: String = "key".to_string();
+
+    // the caller gives up on the reservation before it is delivered
+    assert!(kv.reserve_key(key.clone()).now_or_never().is_none());
+    assert!(!kv.exists(&key).await.unwrap());
+
+    // key can be reserved again
+    assert!(kv.reserve_key(key.clone()).await.is_ok());
+}
+
+#[tokio::test]
+async fn committed_reservation_is_kept() {
+    let kv_name = testdir!();
+    let kv = Kv::<String>::with_db_name(kv_name.to_string_lossy().to_string(), get_test_password())
+        .unwrap();
+
+    let key: String = "key".to_string();
+    let value: String = "value".to_string();
+
+    // put commits the reservation
+    let reservation = kv.reserve_key(key.clone()).await.unwrap();
+    kv.put(reservation, value.clone()).await.unwrap();
+    assert_eq!(kv.get(&key).await.unwrap(), value);
+
+    // key can't be reserved again
+    assert!(kv.reserve_key(key).await.is_err());
+}
+
+#[tokio::test]
+async fn restart_sweeps_stale_reservations() {
+    let kv_name = testdir!();
+    let db = get_kv_store(&kv_name.to_string_lossy(), get_test_password()).unwrap();
+    let kv = Kv::<String>::with_tree(&db, GG20_SHARES_TREE).unwrap();
+
+    let reserved_key: String = "reserved_key".to_string();
+    let key: String = "key".to_string();
+    let value: String = "value".to_string();
+
+    // reserve a key and crash before putting a value, so that the reservation is never released
+    std::mem::forget(kv.reserve_key(reserved_key.clone()).await.unwrap());
+    let reservation = kv.reserve_key(key.clone()).await.unwrap();
+    kv.put(reservation, value.clone()).await.unwrap();
+    drop(kv);
+
+    // restart; opening the kv_manager removes the stale reservation only
+    let kv = Kv::<String>::with_tree(&db, GG20_SHARES_TREE).unwrap();
+    assert!(!kv.exists(&reserved_key).await.unwrap());
+    assert_eq!(kv.get(&key).await.unwrap(), value);
+
+    // key can be reserved again
+    assert!(kv.reserve_key(reserved_key).await.is_ok());
+}
+
+#[tokio::test]
+async fn read_only_keeps_contents() {
+    let kv_name = testdir!();
+    let db = get_kv_store(&kv_name.to_string_lossy(), get_test_password()).unwrap();
+    let kv = Kv::<String>::with_tree(&db, GG20_SHARES_TREE).unwrap();
+
+    let reserved_key: String = "reserved_key".to_string();
+    let key: String = "key".to_string();
+    let value: String = "value".to_string();
+
+    // crash before putting the value of the reserved key
+    std::mem::forget(kv.reserve_key(reserved_key.clone()).await.unwrap());
+    let reservation = kv.reserve_key(key.clone()).await.unwrap();
+    kv.put(reservation, value.clone()).await.unwrap();
+    drop(kv);
+
+    // a read-only kv_manager keeps the stale reservation, and refuses to write
+    let kv = Kv::<String>::read_only_tree(&db, GG20_SHARES_TREE).unwrap();
+    assert!(kv.exists(&reserved_key).await.unwrap());
+    assert_eq!(kv.get(&key).await.unwrap(), value);
+    assert!(matches!(
+        kv.reserve_key("new_key".to_string()).await,
+        Err(ReserveErr(LogicalErr(_)))
+    ));
+    assert!(matches!(
+        kv.delete(&key).await,
+        Err(DeleteErr(LogicalErr(_)))
+    ));
+    assert_eq!(kv.get(&key).await.unwrap(), value);
+}
+
+#[tokio::test]
+async fn read_only_needs_existing_kvstore() {
+    let root = testdir!().join("missing");
+    assert!(matches!(
+        KvManager::open_read_only(root.clone(), get_test_password()),
+        Err(ReadOnlyErr(_))
+    ));
+    assert!(!root.exists());
+}
+
+#[tokio::test]
+async fn keys_with_prefix_and_scan() {
+    let kv_name = testdir!();
+    let kv = Kv::<String>::with_db_name(kv_name.to_string_lossy().to_string(), get_test_password())
+        .unwrap();
+
+    for key in ["a_1", "a_2", "b_1"] {
+        let reservation = kv.reserve_key(key.to_string()).await.unwrap();
+        kv.put(reservation, format!("value_{}", key)).await.unwrap();
+    }
+    let _reservation = kv.reserve_key("a_3".to_string()).await.unwrap();
+
+    // reserved keys are listed
+    let keys = kv.keys_with_prefix("a_").collect().await.unwrap();
+    assert_eq!(keys, vec!["a_1", "a_2", "a_3"]);
+
+    // reserved keys hold no value and are skipped
+    let entries = kv.scan("a_").collect().await.unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            ("a_1".to_string(), "value_a_1".to_string()),
+            ("a_2".to_string(), "value_a_2".to_string()),
+        ]
+    );
+
+    // the stream can be dropped before all entries are received
+    let mut entries = kv.scan("");
+    assert!(entries.next().await.unwrap().is_ok());
+    drop(entries);
+    assert!(kv.exists("b_1").await.unwrap());
+}
+
+#[tokio::test]
+async fn scan_in_pages() {
+    let kv_name = testdir!();
+    let kv = Kv::<String>::with_db_name(kv_name.to_string_lossy().to_string(), get_test_password())
+        .unwrap();
+
+    // more than two pages of keys, with a reserved key at the end of the first page
+    let count = 2 * SCAN_PAGE_SIZE + 1;
+    let keys: Vec<String> = (0..count).map(|i| format!("key_{:04}", i)).collect();
+    let reserved_key = keys[SCAN_PAGE_SIZE - 1].clone();
+    for key in keys.iter().filter(|key| **key != reserved_key) {
+        let reservation = kv.reserve_key(key.clone()).await.unwrap();
+        kv.put(reservation, format!("value_{}", key)).await.unwrap();
+    }
+    let _reservation = kv.reserve_key(reserved_key.clone()).await.unwrap();
+
+    // all keys are listed in order
+    assert_eq!(kv.keys_with_prefix("key_").collect().await.unwrap(), keys);
+
+    // the kv_manager handles other commands while a stream is being consumed
+    let mut entries = kv.scan("key_");
+    let mut scanned = vec![entries.next().await.unwrap().unwrap()];
+    let reservation = kv.reserve_key("other_key".to_string()).await.unwrap();
+    kv.put(reservation, "other_value".to_string())
+        .await
+        .unwrap();
+    while let Some(entry) = entries.next().await {
+        scanned.push(entry.unwrap());
+    }
+
+    // all values are read, and the reserved key is skipped
+    let expected: Vec<(String, String)> = keys
+        .into_iter()
+        .filter(|key| *key != reserved_key)
+        .map(|key| (key.clone(), format!("value_{}", key)))
+        .collect();
+    assert_eq!(scanned, expected);
+}
+
+#[test]
+fn migrate_from_flat_layout() {
+    let kv_name = testdir!("migrate_from_flat_layout");
+    let kv = open_with_test_password(&kv_name).unwrap();
+
+    for key in ["mnemonic", "mnemonic_1", "mnemonic_count", "key_uid"] {
+        kv.insert(key, key).unwrap();
+    }
+    handle_reserve(&kv, "reserved_key_uid".to_string(), TEST_OWNER).unwrap();
+
+    // reservations are dropped
+    assert_eq!(migrate_flat_layout(&kv).unwrap(), 4);
+    assert_eq!(kv.iter().count(), 0);
+
+    let mnemonics = kv.open_tree(MNEMONIC_TREE).unwrap();
+    assert!(mnemonics.contains_key("mnemonic").unwrap());
+    assert!(mnemonics.contains_key("mnemonic_1").unwrap());
+    assert_eq!(mnemonics.iter().count(), 2);
+
+    let mnemonic_meta = kv.open_tree(MNEMONIC_META_TREE).unwrap();
+    assert!(mnemonic_meta.contains_key("mnemonic_count").unwrap());
+    assert_eq!(mnemonic_meta.iter().count(), 1);
+
+    let shares = kv.open_tree(GG20_SHARES_TREE).unwrap();
+    assert_eq!(
+        shares.get("key_uid").unwrap(),
+        Some(sled::IVec::from("key_uid"))
+    );
+    assert_eq!(shares.iter().count(), 1);
+
+    // migration can be repeated
+    assert_eq!(migrate_flat_layout(&kv).unwrap(), 0);
+
+    clean_up(kv_name.to_str().unwrap(), kv);
+}
+
+#[tokio::test]
+async fn reject_reserved_key_uids() {
+    let root = testdir!();
+    let kv_manager = KvManager::new(root, get_test_password()).unwrap();
+
+    for key_uid in [
+        "mnemonic",
+        "mnemonic_2",
+        "mnemonic_count",
+        "password_salt_key",
+        "verification_key",
+    ] {
+        assert!(matches!(
+            kv_manager.reserve::<PartyInfo>(key_uid).await,
+            Err(ReserveErr(ReservedNameErr(_)))
+        ));
+    }
+    assert!(kv_manager
+        .reserve::<PartyInfo>("mnemonic_key")
+        .await
+        .is_ok());
+}
+
+// golden fixtures of the current encodings. If any of these fail, the encoding of a stored type
+// has changed: bump its version and handle the previous one in its upgrade hook.
+
+#[test]
+fn mnemonic_record_golden_fixture() {
+    let golden: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[16, 0, 0, 0, 0, 0, 0, 0],
+        b"mnemonic_entropy",
+        &[2, 0, 0, 0],
+        &[34, 0, 0, 0, 0, 0, 0, 0],
+        &[
+            3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 5, 0, 0, 0, 3, 0, 0, 0, 1, 9, 8, 7, 6, 1, 1, 2, 0, 0,
+            0, 0, 0, 0, 1,
+        ],
+    ];
+    let golden = golden.concat();
+
+    let mut record = MnemonicRecord::new(Entropy(vec![1, 2, 3]), MnemonicLanguage::Japanese);
+    record.passphrase_fingerprint = Some([9, 8, 7, 6]);
+    record.created_at = Some(513);
+    record.imported = true;
+    assert_eq!(encode(&record).unwrap(), golden);
+    let record = decode::<MnemonicRecord>(&golden).unwrap();
+    assert_eq!(record.entropy.0, vec![1, 2, 3]);
+    assert_eq!(record.language, MnemonicLanguage::Japanese);
+    assert_eq!(record.entropy_len, 3);
+    assert_eq!(record.passphrase_fingerprint, Some([9, 8, 7, 6]));
+    assert_eq!(record.created_at, Some(513));
+    assert!(record.imported);
+
+    // version 1 stored the entropy of an English mnemonic
+    let v1: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[16, 0, 0, 0, 0, 0, 0, 0],
+        b"mnemonic_entropy",
+        &[1, 0, 0, 0],
+        &[11, 0, 0, 0, 0, 0, 0, 0],
+        &[3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3],
+    ];
+    let record = decode::<MnemonicRecord>(&v1.concat()).unwrap();
+    assert_eq!(record.entropy.0, vec![1, 2, 3]);
+    assert_eq!(record.language, MnemonicLanguage::English);
+    assert_eq!(record.entropy_len, 3);
+    assert_eq!(record.passphrase_fingerprint, None);
+    // keys of older mnemonics are not tracked
+    assert_eq!(record.created_at, None);
+    assert!(!record.tracks_keys());
+
+    // values written by older versions of tofnd are not enveloped
+    let legacy = [3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3];
+    let record = decode::<MnemonicRecord>(&legacy).unwrap();
+    assert_eq!(record.entropy.0, vec![1, 2, 3]);
+    assert_eq!(record.language, MnemonicLanguage::English);
+    assert_eq!(record.created_at, None);
+}
+
+#[test]
+fn mnemonic_count_golden_fixture() {
+    let golden: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[14, 0, 0, 0, 0, 0, 0, 0],
+        b"mnemonic_count",
+        &[1, 0, 0, 0],
+        &[4, 0, 0, 0, 0, 0, 0, 0],
+        &[5, 0, 0, 0],
+    ];
+    let golden = golden.concat();
+
+    assert_eq!(encode(&MnemonicCount(5)).unwrap(), golden);
+    assert_eq!(decode::<MnemonicCount>(&golden).unwrap(), MnemonicCount(5));
+
+    // values written by older versions of tofnd are not enveloped
+    let legacy = [5, 0, 0, 0];
+    assert_eq!(decode::<MnemonicCount>(&legacy).unwrap(), MnemonicCount(5));
+}
+
+#[test]
+fn key_origin_golden_fixture() {
+    let golden: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[10, 0, 0, 0, 0, 0, 0, 0],
+        b"key_origin",
+        &[1, 0, 0, 0],
+        &[16, 0, 0, 0, 0, 0, 0, 0],
+        &[1, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8],
+    ];
+    let golden = golden.concat();
+
+    let fingerprint = MnemonicFingerprint([1, 2, 3, 4, 5, 6, 7, 8]);
+    let origin = KeyOrigin {
+        mnemonics: vec![fingerprint],
+    };
+    assert_eq!(encode(&origin).unwrap(), golden);
+    assert_eq!(
+        decode::<KeyOrigin>(&golden).unwrap().mnemonics,
+        vec![fingerprint]
+    );
+}
+
+#[test]
+fn mnemonic_index_golden_fixture() {
+    let golden: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[14, 0, 0, 0, 0, 0, 0, 0],
+        b"mnemonic_index",
+        &[1, 0, 0, 0],
+        &[5, 0, 0, 0, 0, 0, 0, 0],
+        &[1, 3, 0, 0, 0],
+    ];
+    let golden = golden.concat();
+
+    assert_eq!(encode(&MnemonicIndex(Some(3))).unwrap(), golden);
+    assert_eq!(
+        decode::<MnemonicIndex>(&golden).unwrap(),
+        MnemonicIndex(Some(3))
+    );
+}
+
+#[test]
+fn gg20_key_usage_golden_fixture() {
+    let golden: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[14, 0, 0, 0, 0, 0, 0, 0],
+        b"gg20_key_usage",
+        &[1, 0, 0, 0],
+        &[17, 0, 0, 0, 0, 0, 0, 0],
+        &[7, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 0, 0, 0, 0, 0, 0],
+    ];
+    let golden = golden.concat();
+
+    let usage = Gg20KeyUsage {
+        sign_count: 7,
+        last_signed_at: Some(513),
+    };
+    assert_eq!(encode(&usage).unwrap(), golden);
+    assert_eq!(decode::<Gg20KeyUsage>(&golden).unwrap(), usage);
+}
+
+#[test]
+fn decode_wrong_type_or_version() {
+    let count = encode(&MnemonicCount(5)).unwrap();
+    assert!(matches!(
+        decode::<MnemonicRecord>(&count),
+        Err(IncompatibleValueErr(_))
+    ));
+
+    // version 2 of mnemonic count is not known yet
+    let newer: [&[u8]; 6] = [
+        b"\0tofnd_value\0",
+        &[14, 0, 0, 0, 0, 0, 0, 0],
+        b"mnemonic_count",
+        &[2, 0, 0, 0],
+        &[4, 0, 0, 0, 0, 0, 0, 0],
+        &[5, 0, 0, 0],
+    ];
+    let newer = newer.concat();
+    assert!(matches!(
+        decode::<MnemonicCount>(&newer),
+        Err(IncompatibleValueErr(_))
+    ));
+}
@@ -1,22 +1,123 @@
 //! useful types and default paths for the kv_manager
 
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
 // default KV store names
 pub const DEFAULT_KV_NAME: &str = "kv";
 
+/// tree of mnemonic entropies
+pub(super) const MNEMONIC_TREE: &str = "mnemonic";
+
+/// tree of mnemonic metadata, such as the mnemonic count
+pub(super) const MNEMONIC_META_TREE: &str = "mnemonic_meta";
+
+/// tree of gg20 [crate::gg20::types::PartyInfo] records, keyed by key_uid
+pub const GG20_SHARES_TREE: &str = "gg20_shares";
+
+/// tree of deleted gg20 keys, keyed by key_uid, until they are purged
+pub const GG20_TOMBSTONES_TREE: &str = "gg20_tombstones";
+
+/// tree of the sign usage of gg20 keys, keyed by key_uid
+pub(super) const GG20_USAGE_TREE: &str = "gg20_usage";
+
+/// tree of the mnemonics that keys were generated with
+pub(super) const KEY_ORIGINS_TREE: &str = "key_origins";
+
+/// Data domains of tofnd services. Each domain is stored in its own tree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KvDomain {
+    /// gg20 [crate::gg20::types::PartyInfo] records, in [GG20_SHARES_TREE]
+    Gg20Shares,
+    /// deleted gg20 keys, in [GG20_TOMBSTONES_TREE]
+    Gg20Tombstones,
+    /// sign usage of gg20 keys, in [GG20_USAGE_TREE]
+    Gg20Usage,
+    /// mnemonic entropies, in [MNEMONIC_TREE]
+    Mnemonics,
+    /// mnemonic metadata, in [MNEMONIC_META_TREE]
+    MnemonicMeta,
+    /// the mnemonics that keys were generated with, in [KEY_ORIGINS_TREE]
+    KeyOrigins,
+}
+
+/// all trees used by tofnd services
+pub(super) const KV_TREES: [&str; 6] = [
+    MNEMONIC_TREE,
+    MNEMONIC_META_TREE,
+    GG20_SHARES_TREE,
+    GG20_TOMBSTONES_TREE,
+    GG20_USAGE_TREE,
+    KEY_ORIGINS_TREE,
+];
+
 /// default path of kv store
 /// the full name of the kv store is "DEFAULT_KV_PATH/kv_name"
 pub(super) const DEFAULT_KV_PATH: &str = "kvstore";
 
-/// default value for reserved key
+/// max number of items read from the kvstore per command by [super::kv::KvStream]
+pub(super) const SCAN_PAGE_SIZE: usize = 64;
+
+/// value used for reserved keys by older versions of tofnd; no owner info is available
 pub(super) const DEFAULT_RESERVE: &str = "";
 
-/// Returned from a successful `ReserveKey` command
-#[derive(Debug)] // disallow derive Clone, Copy
+/// prefix of the value stored for reserved keys. It is followed by a serialized [ReservationInfo].
+/// Every other stored value is a serialized byte vector, which starts with its length as a little-endian u64.
+/// Read as such a length, the first 8 bytes of the prefix are more than 2^62, so no stored value starts with them.
+pub(super) const RESERVE_PREFIX: &[u8] = b"\0tofnd_reserved\0";
+
+/// Releases a reservation of the given key
+type Unreserve = Box<dyn FnOnce(String) + Send + Sync>;
+
+/// Returned from a successful `ReserveKey` command.
+/// Works as a guard: the key is unreserved when the reservation is dropped,
+/// unless it has been committed by a successful `Put` command.
+// disallow derive Clone, Copy
 pub struct KeyReservation {
     pub(super) key: String,
+    unreserve: Option<Unreserve>,
+}
+
+impl KeyReservation {
+    /// create a reservation that is not released on drop
+    pub(super) fn new(key: String) -> Self {
+        KeyReservation {
+            key,
+            unreserve: None,
+        }
+    }
+
+    /// release the reservation with `unreserve` when it is dropped
+    pub(super) fn with_unreserve<F>(mut self, unreserve: F) -> Self
+    where
+        F: FnOnce(String) + Send + Sync + 'static,
+    {
+        self.unreserve = Some(Box::new(unreserve));
+        self
+    }
+
+    /// the key holds a value now; don't release it on drop
+    pub(super) fn commit(&mut self) {
+        self.unreserve = None;
+    }
+}
+
+impl Drop for KeyReservation {
+    fn drop(&mut self) {
+        if let Some(unreserve) = self.unreserve.take() {
+            unreserve(std::mem::take(&mut self.key));
+        }
+    }
+}
+
+impl Debug for KeyReservation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyReservation")
+            .field("key", &self.key)
+            .finish()
+    }
 }
+
 /// kv store needs PartialEq to complare values
 impl PartialEq for KeyReservation {
     fn eq(&self, other: &Self) -> bool {
@@ -24,8 +125,71 @@ impl PartialEq for KeyReservation {
     }
 }
 
+/// Info stored with every reserved key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReservationInfo {
+    /// id of the kv_manager run that made the reservation
+    pub owner: String,
+    /// seconds since unix epoch at the time of the reservation
+    pub timestamp: u64,
+}
+
+impl ReservationInfo {
+    pub(super) fn new(owner: &str) -> Self {
+        ReservationInfo {
+            owner: owner.to_string(),
+            timestamp: unix_timestamp(),
+        }
+    }
+}
+
+/// A reserved key found by a [ReservationsCmd]
+#[derive(Debug, Clone, Serialize)]
+pub struct ReservedKey {
+    pub key: String,
+    /// the tree of the kvstore that holds the key
+    pub tree: String,
+    #[serde(flatten)]
+    pub info: ReservationInfo,
+}
+
+/// Admin operations on reserved keys
+#[derive(Clone, Debug)]
+pub enum ReservationsCmd {
+    List,
+    Clear,
+}
+
+impl ReservationsCmd {
+    /// the name of the command
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReservationsCmd::List => "list",
+            ReservationsCmd::Clear => "clear",
+        }
+    }
+}
+
+/// seconds since unix epoch; falls back to 0 if the system clock is set before the epoch
+pub(crate) fn unix_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// create a new id for the current kv_manager run.
+/// Reservations made by any other id are left over from a previous run.
+pub(super) fn new_run_id() -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("{}-{}", std::process::id(), nanos)
+}
+
 // Provided by the requester and used by the manager task to send the command response back to the requester.
-type Responder<T> = tokio::sync::oneshot::Sender<super::error::InnerKvResult<T>>;
+pub(super) type Responder<T> = tokio::sync::oneshot::Sender<super::error::InnerKvResult<T>>;
 
 #[derive(Debug)]
 pub(super) enum Command<V> {
@@ -33,9 +197,6 @@ pub(super) enum Command<V> {
         key: String,
         resp: Responder<KeyReservation>,
     },
-    UnreserveKey {
-        reservation: KeyReservation,
-    },
     Put {
         reservation: KeyReservation,
         value: V,
@@ -49,8 +210,23 @@ pub(super) enum Command<V> {
         key: String, // TODO should be &str except lifetimes...
         resp: Responder<bool>,
     },
+    Replace {
+        key: String,
+        value: V,
+        resp: Responder<()>,
+    },
     Delete {
         key: String,
         resp: Responder<()>,
     },
+    KeysWithPrefix {
+        prefix: String,
+        after: Option<String>,
+        resp: Responder<Vec<String>>,
+    },
+    Scan {
+        prefix: String,
+        after: Option<String>,
+        resp: Responder<Vec<(String, V)>>,
+    },
 }
@@ -1,70 +1,328 @@
-use std::{convert::TryFrom, path::PathBuf};
+use std::{path::PathBuf, sync::Arc};
 use tofn::sdk::api::{deserialize, serialize};
+use tokio::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::info;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::{
-    encrypted_sled::Password,
-    gg20::types::{Entropy, PartyInfo},
-    mnemonic::FileIo,
+    encrypted_sled::{self, Password},
+    gg20::types::{Gg20Tombstone, LegacyPartyInfo, PartyInfo, Password as MnemonicPassphrase},
+    mnemonic::{rotated_mnemonic_index, FileIo, MNEMONIC_COUNT_KEY, MNEMONIC_KEY},
 };
 
 use super::{
-    error::{InnerKvError, KvResult},
-    kv::Kv,
+    error::{InnerKvError, InnerKvResult, KvError::*, KvResult},
+    kv::{open_default_db, open_existing_db, Kv, KvStream},
+    migration::migrate_flat_layout,
+    types::{
+        KeyReservation, KvDomain, GG20_SHARES_TREE, GG20_TOMBSTONES_TREE, GG20_USAGE_TREE,
+        KEY_ORIGINS_TREE, MNEMONIC_META_TREE, MNEMONIC_TREE,
+    },
 };
 
 /// Kv manager for grpc services
+/// Every data domain is stored in its own tree of the kvstore.
+/// Values are accessed by their [KvType].
 #[derive(Clone)]
 pub struct KvManager {
-    kv: Kv<KvValue>,
+    shares: Kv<KvValue>,
+    tombstones: Kv<KvValue>,
+    usage: Kv<KvValue>,
+    mnemonics: Kv<KvValue>,
+    mnemonic_meta: Kv<KvValue>,
+    key_origins: Kv<KvValue>,
     io: FileIo,
+    /// BIP39 passphrase of the mnemonics, once it has been verified
+    mnemonic_passphrase: Option<MnemonicPassphrase>,
+    /// held for writing while the mnemonic of a running daemon is rotated
+    mnemonic_lock: Arc<RwLock<()>>,
+    /// held while the usage of a key is updated
+    usage_lock: Arc<Mutex<()>>,
+    /// held while a key is deleted, undeleted or purged
+    deletion_lock: Arc<Mutex<()>>,
+    /// held while the origin of a key or the index of a mnemonic is updated
+    origin_lock: Arc<Mutex<()>>,
 }
 
 impl KvManager {
     pub fn new(root: PathBuf, password: Password) -> KvResult<Self> {
+        let db = open_default_db(root.clone(), password)?;
+
+        let moved = migrate_flat_layout(&db).map_err(MigrationErr)?;
+        if moved > 0 {
+            info!("Moved {} kvstore entries to separate trees", moved);
+        }
+
+        Self::with_trees(&db, root, Kv::with_tree)
+    }
+
+    /// Opens the existing kvstore under `root` for inspection, without changing what is stored:
+    /// the kvstore is neither created nor migrated, reservations left over from a previous run are kept,
+    /// and values can't be written. Returns [ReadOnlyErr] if there is no kvstore, or if it has to be migrated.
+    pub fn open_read_only(root: PathBuf, password: Password) -> KvResult<Self> {
+        let db = open_existing_db(root.clone(), password)?;
+
+        // entries of the flat layout are only moved to their trees by [KvManager::new]
+        if db.iter().next().is_some() {
+            return Err(ReadOnlyErr(
+                "kvstore of an older version of tofnd; start tofnd once to migrate it".to_string(),
+            ));
+        }
+
+        Self::with_trees(&db, root, Kv::read_only_tree)
+    }
+
+    /// spawns a kv_manager on every tree of `db` with `spawn`
+    fn with_trees(
+        db: &encrypted_sled::Db,
+        root: PathBuf,
+        spawn: fn(&encrypted_sled::Db, &str) -> KvResult<Kv<KvValue>>,
+    ) -> KvResult<Self> {
         Ok(KvManager {
-            kv: Kv::<KvValue>::new(root.clone(), password)?,
+            shares: spawn(db, GG20_SHARES_TREE)?,
+            tombstones: spawn(db, GG20_TOMBSTONES_TREE)?,
+            usage: spawn(db, GG20_USAGE_TREE)?,
+            mnemonics: spawn(db, MNEMONIC_TREE)?,
+            mnemonic_meta: spawn(db, MNEMONIC_META_TREE)?,
+            key_origins: spawn(db, KEY_ORIGINS_TREE)?,
             io: FileIo::new(root),
+            mnemonic_passphrase: None,
+            mnemonic_lock: Arc::new(RwLock::new(())),
+            usage_lock: Arc::new(Mutex::new(())),
+            deletion_lock: Arc::new(Mutex::new(())),
+            origin_lock: Arc::new(Mutex::new(())),
         })
     }
-    pub fn kv(&self) -> &Kv<KvValue> {
-        &self.kv
-    }
     pub fn io(&self) -> &FileIo {
         &self.io
     }
+
+    pub(crate) fn mnemonic_passphrase(&self) -> Option<&MnemonicPassphrase> {
+        self.mnemonic_passphrase.as_ref()
+    }
+
+    pub(crate) fn set_mnemonic_passphrase(&mut self, passphrase: MnemonicPassphrase) {
+        self.mnemonic_passphrase = Some(passphrase);
+    }
+
+    /// Waits until no mnemonic is being rotated. Mnemonics are not rotated while the guard is held,
+    /// so seeds looked up under it are stored under the keys they were found with.
+    pub(crate) async fn read_mnemonics(&self) -> RwLockReadGuard<'_, ()> {
+        self.mnemonic_lock.read().await
+    }
+
+    /// Waits until no seed is being looked up, and blocks new lookups while the guard is held
+    pub(crate) async fn write_mnemonics(&self) -> RwLockWriteGuard<'_, ()> {
+        self.mnemonic_lock.write().await
+    }
+
+    /// Waits until no key usage is being updated, and blocks other updates while the guard is held
+    pub(crate) async fn lock_key_usage(&self) -> MutexGuard<'_, ()> {
+        self.usage_lock.lock().await
+    }
+
+    /// Waits until no key is being deleted, undeleted or purged, and blocks them while the guard is held.
+    /// Deletions are rare, so a single lock for all keys is enough.
+    pub(crate) async fn lock_deletions(&self) -> MutexGuard<'_, ()> {
+        self.deletion_lock.lock().await
+    }
+
+    /// Waits until no key origin or mnemonic index is being updated, and blocks other updates while the guard is held
+    pub(crate) async fn lock_key_origins(&self) -> MutexGuard<'_, ()> {
+        self.origin_lock.lock().await
+    }
+
+    /// the kv of the domain of `T`
+    fn kv<T: KvType>(&self) -> &Kv<KvValue> {
+        match T::DOMAIN {
+            KvDomain::Gg20Shares => &self.shares,
+            KvDomain::Gg20Tombstones => &self.tombstones,
+            KvDomain::Gg20Usage => &self.usage,
+            KvDomain::Mnemonics => &self.mnemonics,
+            KvDomain::MnemonicMeta => &self.mnemonic_meta,
+            KvDomain::KeyOrigins => &self.key_origins,
+        }
+    }
+
+    /// Reserves `key` for a new value of type `T`.
+    /// Returns [ReserveErr] if `key` is a reserved name of gg20 shares.
+    pub async fn reserve<T: KvType>(&self, key: &str) -> KvResult<KeyReservation> {
+        if T::DOMAIN == KvDomain::Gg20Shares && is_reserved_name(key) {
+            return Err(ReserveErr(InnerKvError::ReservedNameErr(format!(
+                "key uid <{}> is a reserved name",
+                key
+            ))));
+        }
+        self.kv::<T>().reserve_key(key.to_string()).await
+    }
+
+    /// Puts a value of type `T` given a reservation from [KvManager::reserve]
+    pub async fn put<T: KvType>(&self, reservation: KeyReservation, value: T) -> KvResult<()> {
+        let bytes = encode(&value).map_err(PutErr)?;
+        self.kv::<T>().put(reservation, bytes).await
+    }
+
+    /// Puts a value of type `T` under `key` without a reservation, replacing the value if any.
+    /// Callers must serialize their own concurrent updates of `key`.
+    pub async fn replace<T: KvType>(&self, key: &str, value: T) -> KvResult<()> {
+        let bytes = encode(&value).map_err(PutErr)?;
+        self.kv::<T>().replace(key, bytes).await
+    }
+
+    /// Gets the value of type `T` under `key`, upgrading it if it has an older schema version
+    pub async fn get<T: KvType>(&self, key: &str) -> KvResult<T> {
+        decode(&self.kv::<T>().get(key).await?).map_err(GetErr)
+    }
+
+    /// Checks if a value of type `T` exists under `key`
+    pub async fn exists<T: KvType>(&self, key: &str) -> KvResult<bool> {
+        self.kv::<T>().exists(key).await
+    }
+
+    /// Deletes the value of type `T` under `key`
+    pub async fn delete<T: KvType>(&self, key: &str) -> KvResult<()> {
+        self.kv::<T>().delete(key).await
+    }
+
+    /// Streams all keys of type `T` that start with `prefix`
+    pub fn keys_with_prefix<T: KvType>(&self, prefix: &str) -> KvStream<String> {
+        self.kv::<T>().keys_with_prefix(prefix)
+    }
+}
+
+/// Names used for mnemonics and encryption metadata in the flat layout of older versions of tofnd.
+/// Using them as key uids would make the layout migration ambiguous.
+fn is_reserved_name(key_uid: &str) -> bool {
+    key_uid == MNEMONIC_KEY
+        || key_uid == MNEMONIC_COUNT_KEY
+        || rotated_mnemonic_index(key_uid).is_some()
+        || encrypted_sled::Db::is_encryption_metadata_key(key_uid.as_bytes())
 }
 
 /// Value type stored in the kv-store
 type KvValue = Vec<u8>;
 
-/// Create PartyInfo from KvValue
-impl TryFrom<KvValue> for PartyInfo {
-    type Error = InnerKvError;
-    fn try_from(v: KvValue) -> Result<Self, Self::Error> {
-        deserialize(&v).ok_or(InnerKvError::DeserializationErr)
+/// A type stored in the kv-store by [KvManager].
+/// Values are wrapped in an envelope that records [KvType::TYPE_ID] and [KvType::VERSION],
+/// so that a change of encoding is detected instead of making existing values unreadable.
+pub trait KvType: Serialize + DeserializeOwned {
+    /// identifies the type of stored values; must never change
+    const TYPE_ID: &'static str;
+    /// schema version of the current encoding; bump it when the encoding changes
+    const VERSION: u32;
+    /// domain that values of the type are stored in
+    const DOMAIN: KvDomain;
+    /// schema version that values written by older versions of tofnd, which are not enveloped, are encoded with.
+    /// `None` for types that were introduced along with envelopes.
+    const UNENVELOPED_VERSION: Option<u32> = None;
+
+    /// Decodes a `payload` of an older schema `version` into the current one.
+    /// Types without older versions can't upgrade anything.
+    fn upgrade(version: u32, _payload: &[u8]) -> InnerKvResult<Self> {
+        Err(unknown_version::<Self>(version))
     }
 }
 
-/// Create KvValue from PartyInfo
-impl TryFrom<PartyInfo> for KvValue {
-    type Error = InnerKvError;
-    fn try_from(v: PartyInfo) -> Result<Self, Self::Error> {
-        serialize(&v).map_err(|_| InnerKvError::SerializationErr)
+/// prefix of enveloped values. It is followed by a serialized [Envelope].
+/// Values written by older versions of tofnd are serialized structs that don't start with these bytes.
+const ENVELOPE_PREFIX: &[u8] = b"\0tofnd_value\0";
+
+/// A serialized value tagged with its type and schema version
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    type_id: String,
+    version: u32,
+    payload: Vec<u8>,
+}
+
+/// Serializes `value` in an [Envelope] of the current schema version of `T`
+pub(super) fn encode<T: KvType>(value: &T) -> InnerKvResult<KvValue> {
+    let envelope = Envelope {
+        type_id: T::TYPE_ID.to_string(),
+        version: T::VERSION,
+        payload: serialize(value).map_err(|_| InnerKvError::SerializationErr)?,
+    };
+    let envelope = serialize(&envelope).map_err(|_| InnerKvError::SerializationErr)?;
+    Ok([ENVELOPE_PREFIX, envelope.as_slice()].concat())
+}
+
+/// Deserializes a value of type `T`, upgrading it with [KvType::upgrade] if needed
+pub(super) fn decode<T: KvType>(bytes: &[u8]) -> InnerKvResult<T> {
+    let envelope: Envelope = match bytes.strip_prefix(ENVELOPE_PREFIX) {
+        Some(envelope) => deserialize(envelope).ok_or(InnerKvError::DeserializationErr)?,
+        None => {
+            return match T::UNENVELOPED_VERSION {
+                Some(version) => decode_version(version, bytes),
+                None => Err(unknown_version::<T>(0)),
+            }
+        }
+    };
+
+    if envelope.type_id != T::TYPE_ID {
+        return Err(InnerKvError::IncompatibleValueErr(format!(
+            "expected value of type <{}>, found <{}>",
+            T::TYPE_ID,
+            envelope.type_id
+        )));
     }
+
+    decode_version(envelope.version, &envelope.payload)
 }
 
-/// Create Entropy from KvValue
-impl TryFrom<KvValue> for Entropy {
-    type Error = InnerKvError;
-    fn try_from(v: KvValue) -> Result<Self, Self::Error> {
-        deserialize(&v).ok_or(InnerKvError::DeserializationErr)
+/// Deserializes a `payload` of schema `version` of `T`
+fn decode_version<T: KvType>(version: u32, payload: &[u8]) -> InnerKvResult<T> {
+    match version {
+        version if version == T::VERSION => {
+            deserialize(payload).ok_or(InnerKvError::DeserializationErr)
+        }
+        version if version < T::VERSION => T::upgrade(version, payload),
+        version => Err(InnerKvError::IncompatibleValueErr(format!(
+            "value of type <{}> has version {}, but only versions up to {} are supported",
+            T::TYPE_ID,
+            version,
+            T::VERSION
+        ))),
     }
 }
 
-/// Create KvValue from Entropy
-impl TryFrom<Entropy> for KvValue {
-    type Error = InnerKvError;
-    fn try_from(v: Entropy) -> Result<Self, Self::Error> {
-        serialize(&v).map_err(|_| InnerKvError::SerializationErr)
+/// Deserializes a `payload` of an older schema of `T`, encoded as `L`, and converts it into `T`
+fn upgrade_from<L, T>(payload: &[u8]) -> InnerKvResult<T>
+where
+    L: DeserializeOwned,
+    T: From<L>,
+{
+    deserialize::<L>(payload)
+        .map(T::from)
+        .ok_or(InnerKvError::DeserializationErr)
+}
+
+/// Returns an error for an unknown schema `version` of `T`
+pub fn unknown_version<T: KvType>(version: u32) -> InnerKvError {
+    InnerKvError::IncompatibleValueErr(format!(
+        "can't upgrade value of type <{}> from version {}",
+        T::TYPE_ID,
+        version
+    ))
+}
+
+impl KvType for PartyInfo {
+    const TYPE_ID: &'static str = "gg20_party_info";
+    const VERSION: u32 = 2;
+    const DOMAIN: KvDomain = KvDomain::Gg20Shares;
+    const UNENVELOPED_VERSION: Option<u32> = Some(1);
+
+    fn upgrade(version: u32, payload: &[u8]) -> InnerKvResult<Self> {
+        match version {
+            // version 1 predates key metadata
+            1 => upgrade_from::<LegacyPartyInfo, _>(payload),
+            _ => Err(unknown_version::<Self>(version)),
+        }
     }
 }
+
+impl KvType for Gg20Tombstone {
+    const TYPE_ID: &'static str = "gg20_tombstone";
+    const VERSION: u32 = 1;
+    const DOMAIN: KvDomain = KvDomain::Gg20Tombstones;
+}
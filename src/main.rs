@@ -2,11 +2,15 @@ use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tokio_stream::wrappers::TcpListenerStream;
 
+mod admin;
 mod encrypted_sled;
+mod error;
 mod gg20;
+mod keys;
 mod kv_manager;
 mod mnemonic;
 mod multisig;
+mod output;
 
 // gather logs; need to set RUST_LOG=info
 use tracing::{info, span, Level};
@@ -20,16 +24,32 @@ pub mod proto {
 }
 
 mod config;
-use config::parse_args;
+use config::{parse_args, Config, OutputFormat};
+
+use crate::{kv_manager::KvManager, mnemonic::CmdOutput, output::report};
+use anyhow::anyhow;
+
+/// logs are written to stdout, or to stderr if `to_stderr` is set
+fn set_up_logs(to_stderr: bool) {
+    let stream = if to_stderr {
+        atty::Stream::Stderr
+    } else {
+        atty::Stream::Stdout
+    };
+    let writer = move || -> Box<dyn std::io::Write> {
+        if to_stderr {
+            Box::new(std::io::stderr())
+        } else {
+            Box::new(std::io::stdout())
+        }
+    };
 
-use crate::kv_manager::KvManager;
-
-fn set_up_logs() {
     // enable only tofnd and tofn debug logs - disable serde, tonic, tokio, etc.
     tracing_subscriber::fmt()
         .with_env_filter("tofnd=debug,tofn=debug")
         .json()
-        .with_ansi(atty::is(atty::Stream::Stdout))
+        .with_writer(writer)
+        .with_ansi(atty::is(stream))
         .with_target(false)
         .with_current_span(false)
         .flatten_event(true) // make logs complient with datadog
@@ -47,12 +67,18 @@ fn warn_for_unsafe_execution() {
     warn!("WARNING: THIS tofnd BINARY IS NOT SAFE: SAFE PRIMES ARE NOT USED BECAUSE '--unsafe' FLAG IS ENABLED.  USE '--unsafe' FLAG ONLY FOR TESTING.");
 }
 
+fn warn_for_unsafe_keys() {
+    use tracing::warn;
+    warn!("WARNING: '--allow-unsafe-keys' FLAG IS ENABLED: KEYS GENERATED WITH UNSAFE PRIMES ARE USED FOR SIGNING.  DO NOT USE THEM TO SECURE REAL VALUE.");
+}
+
 /// worker_threads defaults to the number of cpus on the system
 /// https://docs.rs/tokio/1.2.0/tokio/attr.main.html#multi-threaded-runtime
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> TofndResult<()> {
-    set_up_logs(); // can't print any logs until they're set up
     let cfg = parse_args()?;
+    // keep stdout clean for a mnemonic exported to it, and for json results
+    set_up_logs(cfg.mnemonic_config.to_stdout || cfg.output == OutputFormat::Json); // can't print any logs until they're set up
     let socket_address = addr(&cfg.ip, cfg.port)?;
 
     // immediately read an encryption password from stdin
@@ -63,6 +89,8 @@ async fn main() -> TofndResult<()> {
     warn_for_malicious_build();
     if !cfg.safe_keygen {
         warn_for_unsafe_execution();
+    } else if cfg.allow_unsafe_keys {
+        warn_for_unsafe_keys();
     }
 
     // set up span for logs
@@ -70,21 +98,64 @@ async fn main() -> TofndResult<()> {
     let _enter = main_span.enter();
     let cmd = cfg.mnemonic_cmd.clone();
 
+    // admin operations on the kv store run before any stale reservation is swept
+    if let Some(reservations_cmd) = &cfg.reservations_cmd {
+        let result =
+            kv_manager::handle_reservations_cmd(cfg.tofnd_path.clone(), password, reservations_cmd)
+                .map(|reservations| serde_json::json!({ "reservations": reservations }))
+                .map_err(anyhow::Error::from);
+        let command = format!("db reservations {}", reservations_cmd.name());
+        return report(cfg.output, &command, result);
+    }
+
+    // key inspection only reads the kv store and exits
+    if let Some(keys_cmd) = &cfg.keys_cmd {
+        let result = run_keys_cmd(&cfg, password, keys_cmd).await;
+        return report(cfg.output, &format!("keys {}", keys_cmd.name()), result);
+    }
+
+    // maintenance commands report their result and exit
+    if cmd.exit_after_cmd() {
+        let result = run_mnemonic_cmd(&cfg, password).await;
+        return report(cfg.output, &format!("mnemonic {}", cmd.name()), result);
+    }
+
+    // check the admin config before the slow kv store decryption
+    let admin_token = match &cfg.admin_token_file {
+        Some(path) => {
+            cfg.mnemonic_config.check_online_export()?;
+            Some(admin::service::read_token(path)?)
+        }
+        None => None,
+    };
+    let mnemonic_config = cfg.mnemonic_config.clone();
+
     // this step takes a long time due to password-based decryption
     let kv_manager = KvManager::new(cfg.tofnd_path.clone(), password)?
-        .handle_mnemonic(&cfg.mnemonic_cmd)
+        .handle_mnemonic(&cfg.mnemonic_cmd, &cfg.mnemonic_config)
         .await?;
 
-    let gg20_service = gg20::service::new_service(cfg, kv_manager.clone());
-    let multisig_service = multisig::service::new_service(kv_manager);
-
-    if cmd.exit_after_cmd() {
-        info!("Tofnd exited after using command <{:?}>. Run `./tofnd -m existing` to execute gRPC daemon.", cmd);
-        return Ok(());
+    // deleted keys whose undelete window has passed while tofnd was down
+    let purged = gg20::delete::purge_deleted_keys(&kv_manager, cfg.undelete_window).await?;
+    if purged > 0 {
+        info!("Purged {} deleted keys", purged);
     }
+    gg20::delete::spawn_purge_task(kv_manager.clone(), cfg.undelete_window);
+
+    let gg20_keys_service =
+        gg20::service::new_keys_service(cfg.clone(), kv_manager.clone(), admin_token.clone());
+    let gg20_service = gg20::service::new_service(cfg, kv_manager.clone());
+    let multisig_service = multisig::service::new_service(kv_manager.clone());
+    let admin_service =
+        admin_token.map(|token| admin::service::new_service(kv_manager, mnemonic_config, token));
 
     let gg20_service = proto::gg20_server::Gg20Server::new(gg20_service);
+    let gg20_keys_service = proto::gg20_keys_server::Gg20KeysServer::new(gg20_keys_service);
     let multisig_service = proto::multisig_server::MultisigServer::new(multisig_service);
+    let admin_service = admin_service.map(proto::admin_server::AdminServer::new);
+    if admin_service.is_some() {
+        info!("Serving admin service");
+    }
 
     let incoming = TcpListener::bind(socket_address).await?;
     info!(
@@ -94,13 +165,38 @@ async fn main() -> TofndResult<()> {
 
     tonic::transport::Server::builder()
         .add_service(gg20_service)
+        .add_service(gg20_keys_service)
         .add_service(multisig_service)
+        .add_optional_service(admin_service)
         .serve_with_incoming_shutdown(TcpListenerStream::new(incoming), shutdown_signal())
         .await?;
 
     Ok(())
 }
 
+/// runs the mnemonic command of `cfg`, which exits tofnd, on the kv store encrypted with `password`
+async fn run_mnemonic_cmd(
+    cfg: &Config,
+    password: encrypted_sled::Password,
+) -> TofndResult<CmdOutput> {
+    // this step takes a long time due to password-based decryption
+    let (_, output) = KvManager::new(cfg.tofnd_path.clone(), password)?
+        .run_mnemonic_cmd(&cfg.mnemonic_cmd, &cfg.mnemonic_config)
+        .await?;
+    output.ok_or_else(|| anyhow!("command <{}> has no result", cfg.mnemonic_cmd.name()))
+}
+
+/// runs `keys_cmd` on the kv store of `cfg` encrypted with `password`
+async fn run_keys_cmd(
+    cfg: &Config,
+    password: encrypted_sled::Password,
+    keys_cmd: &keys::KeysCmd,
+) -> TofndResult<keys::KeyInventory> {
+    // this step takes a long time due to password-based decryption
+    let kv_manager = KvManager::open_read_only(cfg.tofnd_path.clone(), password)?;
+    keys::handle_keys_cmd(&kv_manager, keys_cmd).await
+}
+
 fn addr(ip: &str, port: u16) -> TofndResult<SocketAddr> {
     let socket_addr = format!("{}:{}", ip, port);
     socket_addr
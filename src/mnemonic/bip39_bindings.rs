@@ -1,7 +1,8 @@
 //! This module provides wrappers for mnemonic creation, validation and seed
 //! extraction using the tiny-bip39 https://crates.io/crates/tiny-bip39 library.
 //!
-//! Default (and only) language is English. More languages to be added in the future.
+//! All wordlists of tiny-bip39 are supported; see [MnemonicLanguage]. The default language is English.
+//! The language of a phrase is part of the seed, so it must be stored along with the entropy.
 //!
 //! Zeroization:
 //!   All functions that accept and/or return structs that implement zeroization:
@@ -9,41 +10,136 @@
 
 use super::results::bip39::{Bip39Error::*, Bip39Result};
 use crate::gg20::types::{Entropy, Password};
-use bip39::{Language, Mnemonic, Seed};
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-// TODO: we can enrich the API so that users can decide which language they want to use
-const DEFAUT_LANG: Language = Language::English;
+/// Language of the wordlist of a mnemonic.
+/// Languages are stored by their position, so new ones must only be appended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MnemonicLanguage {
+    English,
+    ChineseSimplified,
+    ChineseTraditional,
+    French,
+    Italian,
+    Japanese,
+    Korean,
+    Spanish,
+}
+
+impl Default for MnemonicLanguage {
+    fn default() -> Self {
+        Self::English
+    }
+}
+
+impl MnemonicLanguage {
+    /// All languages, in the order they are tried when a phrase is imported
+    pub const ALL: [MnemonicLanguage; 8] = [
+        Self::English,
+        Self::ChineseSimplified,
+        Self::ChineseTraditional,
+        Self::French,
+        Self::Italian,
+        Self::Japanese,
+        Self::Korean,
+        Self::Spanish,
+    ];
+
+    /// BCP 47 language tags of all languages, as accepted by [MnemonicLanguage::from_code].
+    /// Chinese is tagged with its script, `zh-hans` or `zh-hant`; other languages with their
+    /// ISO 639-1 code. The tags are stored in mnemonic share files, so they must not change.
+    pub const CODES: [&'static str; 8] = ["en", "zh-hans", "zh-hant", "fr", "it", "ja", "ko", "es"];
+
+    /// Returns the language of a BCP 47 language tag `code`, see [MnemonicLanguage::CODES]
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::CODES
+            .iter()
+            .position(|c| *c == code)
+            .map(|index| Self::ALL[index])
+    }
+
+    /// Returns the BCP 47 language tag of the language, see [MnemonicLanguage::CODES]
+    pub fn code(self) -> &'static str {
+        // languages are listed in the order they are declared
+        Self::CODES[self as usize]
+    }
 
-/// create a new 24 word mnemonic
-pub(super) fn bip39_new_w24() -> Entropy {
-    let mnemonic = Mnemonic::new(bip39::MnemonicType::Words24, DEFAUT_LANG);
-    Entropy(mnemonic.entropy().to_owned())
+    fn bip39(self) -> Language {
+        match self {
+            Self::English => Language::English,
+            Self::ChineseSimplified => Language::ChineseSimplified,
+            Self::ChineseTraditional => Language::ChineseTraditional,
+            Self::French => Language::French,
+            Self::Italian => Language::Italian,
+            Self::Japanese => Language::Japanese,
+            Self::Korean => Language::Korean,
+            Self::Spanish => Language::Spanish,
+        }
+    }
 }
 
-/// create a [Mnemonic] from [Entropy]; takes ownership of entropy and zeroizes it before exit
-pub(super) fn bip39_from_entropy(entropy: Entropy) -> Bip39Result<Mnemonic> {
+/// word counts of BIP39 mnemonics
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// word count of new mnemonics
+pub const DEFAULT_WORD_COUNT: usize = 24;
+
+/// create a new mnemonic of `word_count` words
+pub(super) fn bip39_new(word_count: usize) -> Bip39Result<Entropy> {
+    let mnemonic_type =
+        MnemonicType::for_word_count(word_count).map_err(|_| WordCount(word_count))?;
+    let mnemonic = Mnemonic::new(mnemonic_type, Language::English);
+    Ok(Entropy(mnemonic.entropy().to_owned()))
+}
+
+/// create a [Mnemonic] in `lang` from [Entropy]; takes ownership of entropy and zeroizes it before exit
+pub(super) fn bip39_from_entropy(
+    entropy: Entropy,
+    lang: MnemonicLanguage,
+) -> Bip39Result<Mnemonic> {
     // try to get mnemonic from entropy
-    Mnemonic::from_entropy(&entropy.0, DEFAUT_LANG).map_err(|_| FromEntropy)
+    Mnemonic::from_entropy(&entropy.0, lang.bip39()).map_err(|_| FromEntropy)
 }
 
 /// create an [Entropy] from [Mnemonic]; takes ownership of phrase and zeroizes it before exit
-pub(super) fn bip39_from_phrase(phrase: Password) -> Bip39Result<Entropy> {
-    // matching feels better than map_err() here
-    match Mnemonic::from_phrase(&phrase.0, DEFAUT_LANG) {
-        Ok(mnemonic) => Ok(Entropy(mnemonic.entropy().to_owned())),
-        Err(_) => Err(FromPhrase),
-    }
+/// The language of the phrase is detected by trying the wordlists in the order of [MnemonicLanguage::ALL].
+pub(super) fn bip39_from_phrase(phrase: Password) -> Bip39Result<(Entropy, MnemonicLanguage)> {
+    MnemonicLanguage::ALL
+        .iter()
+        .find_map(|lang| {
+            Mnemonic::from_phrase(&phrase.0, lang.bip39())
+                .ok()
+                .map(|mnemonic| (Entropy(mnemonic.entropy().to_owned()), *lang))
+        })
+        .ok_or(FromPhrase)
 }
 
-/// extract [Seed] from [Mnemonic]; takes ownership of entropy and password and zeroizes them before exit
-pub(super) fn bip39_seed(entropy: Entropy, password: Password) -> Bip39Result<Seed> {
+/// extract [Seed] from [Mnemonic] in `lang`; takes ownership of entropy and password and zeroizes them before exit
+pub(super) fn bip39_seed(
+    entropy: Entropy,
+    lang: MnemonicLanguage,
+    password: Password,
+) -> Bip39Result<Seed> {
     // matching feels better than map_err() here
-    match bip39_from_entropy(entropy) {
+    match bip39_from_entropy(entropy, lang) {
         Ok(mnemonic) => Ok(Seed::new(&mnemonic, &password.0)),
         Err(_) => Err(FromEntropy),
     }
 }
 
+/// Fingerprint of a [Seed], used to verify the passphrase of a mnemonic without storing the seed.
+/// It is short on purpose: it detects a mistyped passphrase, but does not single out the right one among guesses.
+pub(crate) type PassphraseFingerprint = [u8; 4];
+
+/// the first bytes of the SHA-256 hash of `seed`
+pub(super) fn seed_fingerprint(seed: &Seed) -> PassphraseFingerprint {
+    let mut fingerprint = PassphraseFingerprint::default();
+    fingerprint.copy_from_slice(&Sha256::digest(seed.as_bytes())[..fingerprint.len()]);
+    fingerprint
+}
+
 #[cfg(test)]
 pub mod tests {
 
@@ -51,9 +147,14 @@ pub mod tests {
     use tracing::info;
     use tracing_test::traced_test;
 
-    /// create a mnemonic from entropy; takes ownership of entropy and zeroizes it after
-    pub fn bip39_to_phrase(entropy: Entropy) -> Bip39Result<Password> {
-        match Mnemonic::from_entropy(&entropy.0, DEFAUT_LANG) {
+    /// create a new 24 word mnemonic
+    pub fn bip39_new_w24() -> Entropy {
+        bip39_new(24).unwrap()
+    }
+
+    /// create a mnemonic in `lang` from entropy; takes ownership of entropy and zeroizes it after
+    pub fn bip39_to_phrase(entropy: Entropy, lang: MnemonicLanguage) -> Bip39Result<Password> {
+        match Mnemonic::from_entropy(&entropy.0, lang.bip39()) {
             Ok(mnemonic) => Ok(Password(mnemonic.phrase().to_owned())),
             Err(_) => Err(FromEntropy),
         }
@@ -63,7 +164,7 @@ pub mod tests {
     #[test]
     fn test_create() {
         let entropy = bip39_new_w24();
-        let mnemonic = Mnemonic::from_entropy(&entropy.0, DEFAUT_LANG).unwrap();
+        let mnemonic = Mnemonic::from_entropy(&entropy.0, Language::English).unwrap();
         let passphrase = mnemonic.phrase();
         info!(
             "created passphrase [{}] from entropy [{:?}]",
@@ -77,8 +178,8 @@ pub mod tests {
         let ok_entropy = Entropy(vec![42; 16]);
         let err_entropy = Entropy(vec![42; 15]);
 
-        assert!(bip39_from_entropy(ok_entropy).is_ok());
-        assert!(bip39_from_entropy(err_entropy).is_err());
+        assert!(bip39_from_entropy(ok_entropy, MnemonicLanguage::English).is_ok());
+        assert!(bip39_from_entropy(err_entropy, MnemonicLanguage::English).is_err());
     }
 
     #[traced_test]
@@ -92,8 +193,151 @@ pub mod tests {
         let expected_output = "0bde96f14c35a66235478e0c16c152fcaf6301e4d9a81d3febc50879fe7e5438e6a8dd3e39bdf3ab7b12d6b44218710e17d7a2844ee9633fab0e03d9a6c8569b";
         let actual_output = format!(
             "{:x}",
-            bip39_seed(Entropy(entropy), Password("password".to_owned())).unwrap()
+            bip39_seed(
+                Entropy(entropy),
+                MnemonicLanguage::English,
+                Password("password".to_owned())
+            )
+            .unwrap()
+        );
+        assert_eq!(expected_output, &actual_output);
+    }
+
+    #[test]
+    fn test_languages() {
+        // phrases of the all-zero 128-bit entropy: word 0 eleven times, followed by word 3
+        let vectors = [
+            (MnemonicLanguage::English, "abandon", "about"),
+            (MnemonicLanguage::ChineseSimplified, "的", "在"),
+            (MnemonicLanguage::French, "abaisser", "abeille"),
+            (MnemonicLanguage::Italian, "abaco", "abete"),
+            // wordlists are NFKD-normalized: あいこくしん, あおぞら
+            (
+                MnemonicLanguage::Japanese,
+                "あいこくしん",
+                "あおそ\u{3099}ら",
+            ),
+            // 가격, 가능
+            (
+                MnemonicLanguage::Korean,
+                "\u{1100}\u{1161}\u{1100}\u{1167}\u{11a8}",
+                "\u{1100}\u{1161}\u{1102}\u{1173}\u{11bc}",
+            ),
+            // ábaco, abierto
+            (MnemonicLanguage::Spanish, "a\u{301}baco", "abierto"),
+        ];
+        for (lang, first, last) in vectors {
+            let expected = format!("{} {}", vec![first; 11].join(" "), last);
+            let phrase = bip39_to_phrase(Entropy(vec![0; 16]), lang).unwrap();
+            assert_eq!(phrase.0, expected);
+
+            let (entropy, detected) = bip39_from_phrase(phrase).unwrap();
+            assert_eq!(entropy.0, vec![0; 16]);
+            assert_eq!(detected, lang);
+        }
+
+        // the first words of the simplified and traditional Chinese wordlists are the same
+        let phrase = bip39_to_phrase(Entropy(vec![0; 16]), MnemonicLanguage::ChineseTraditional);
+        let (_, detected) = bip39_from_phrase(phrase.unwrap()).unwrap();
+        assert_eq!(detected, MnemonicLanguage::ChineseSimplified);
+
+        // phrases with words that are only in the traditional Chinese wordlist are detected as such
+        let phrase = bip39_to_phrase(
+            Entropy(vec![0x7f; 16]),
+            MnemonicLanguage::ChineseTraditional,
+        );
+        let phrase = phrase.unwrap();
+        assert_eq!(phrase.0, "槍 疫 黴 嘗 倆 鬧 餓 賢 槍 疫 黴 卿");
+        let (entropy, detected) = bip39_from_phrase(phrase).unwrap();
+        assert_eq!(entropy.0, vec![0x7f; 16]);
+        assert_eq!(detected, MnemonicLanguage::ChineseTraditional);
+
+        // every language round-trips
+        for lang in MnemonicLanguage::ALL {
+            let entropy = bip39_new_w24();
+            let phrase = bip39_to_phrase(entropy.clone(), lang).unwrap();
+            let (imported, detected) = bip39_from_phrase(phrase).unwrap();
+            assert_eq!(imported.0, entropy.0);
+            if lang != MnemonicLanguage::ChineseTraditional {
+                assert_eq!(detected, lang);
+            }
+        }
+
+        assert!(bip39_from_phrase(Password("not a mnemonic".to_owned())).is_err());
+    }
+
+    #[test]
+    fn test_language_codes() {
+        for (lang, code) in MnemonicLanguage::ALL.iter().zip(MnemonicLanguage::CODES) {
+            assert_eq!(MnemonicLanguage::from_code(code), Some(*lang));
+            assert_eq!(lang.code(), code);
+        }
+        assert_eq!(MnemonicLanguage::from_code("xx"), None);
+    }
+
+    #[test]
+    fn test_english_seed() {
+        // https://github.com/trezor/python-mnemonic/blob/master/vectors.json without passphrase
+        let expected_output = "5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4";
+        let actual_output = format!(
+            "{:x}",
+            bip39_seed(
+                Entropy(vec![0; 16]),
+                MnemonicLanguage::English,
+                Password("".to_owned())
+            )
+            .unwrap()
         );
         assert_eq!(expected_output, &actual_output);
     }
+
+    #[test]
+    fn test_word_counts() {
+        // https://github.com/trezor/python-mnemonic/blob/master/vectors.json has no 15 and 21 word vectors;
+        // their seeds are derived as specified by BIP39: PBKDF2-HMAC-SHA512 of the phrase with 2048 rounds
+        let vectors = [
+            (
+                16,
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            ),
+            (
+                20,
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon address",
+                "fa08713f46bf5cb48728ceb70e3aae1bc53c5cb7b4e29c5610261d1cbb7be3bed4d805256fec515754d2be35974fc5da678168e9d9bb0cb70948026923b0def3",
+            ),
+            (
+                24,
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+                "035895f2f481b1b0f01fcf8c289c794660b289981a78f8106447707fdd9666ca06da5a9a565181599b79f53b844d8a71dd9f439c52a3d7b3e8a79c906ac845fa",
+            ),
+            (
+                28,
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon admit",
+                "e7dadc189d2e8d07ac278d9ec98a1d2d327e4a6b7df494c00cbf2cbf2d3543dac7000fc72d4ada8d9997dc8db388ff22c6d79f604a7455f2df5534a28eee04c6",
+            ),
+            (
+                32,
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+                "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+            ),
+        ];
+        for (entropy_len, phrase, seed) in vectors {
+            let (entropy, lang) = bip39_from_phrase(Password(phrase.to_owned())).unwrap();
+            assert_eq!(entropy.0, vec![0; entropy_len]);
+            assert_eq!(lang, MnemonicLanguage::English);
+
+            let actual_seed = bip39_seed(entropy, lang, Password("TREZOR".to_owned())).unwrap();
+            assert_eq!(format!("{:x}", actual_seed), seed);
+        }
+
+        for word_count in WORD_COUNTS {
+            let entropy = bip39_new(word_count).unwrap();
+            assert_eq!(entropy.0.len(), word_count / 3 * 4);
+            let phrase = bip39_to_phrase(entropy.clone(), MnemonicLanguage::English).unwrap();
+            assert_eq!(phrase.0.split_whitespace().count(), word_count);
+            assert_eq!(bip39_from_phrase(phrase).unwrap().0 .0, entropy.0);
+        }
+        assert!(bip39_new(13).is_err());
+    }
 }
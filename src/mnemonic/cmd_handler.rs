@@ -1,68 +1,390 @@
 // TODO: consider moving cmd_handler in KvManager
 
 use super::{
-    bip39_bindings::{bip39_from_phrase, bip39_new_w24, bip39_seed},
+    bip39_bindings::{
+        bip39_from_phrase, bip39_new, bip39_seed, seed_fingerprint, MnemonicLanguage,
+        DEFAULT_WORD_COUNT,
+    },
+    file_io::{ExportTarget, FileIo},
+    origins::{mnemonic_fingerprint, MnemonicFingerprint, MnemonicIndex},
+    record::MnemonicRecord,
+    results::bip39::Bip39Error::EntropyLength,
     results::mnemonic::{
         InnerMnemonicError::*, InnerMnemonicResult, MnemonicError::*, MnemonicResult, SeedResult,
     },
+    slip39_bindings::ShareScheme,
 };
 use crate::{
     gg20::types::{Entropy, Password}, // TODO: move from gg20::types
     kv_manager::{
         error::{InnerKvError, KvError},
-        KeyReservation, KvManager,
+        KeyReservation, KvDomain, KvManager, KvType,
     },
 };
-use tofn::{
-    gg20::keygen::SecretRecoveryKey,
-    sdk::api::{deserialize, serialize},
-};
+use bip39::Seed;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use tofn::gg20::keygen::SecretRecoveryKey;
 
 use rpassword::read_password;
-use std::convert::TryInto;
+use std::{
+    convert::TryInto,
+    path::{Path, PathBuf},
+};
 use tracing::{error, info};
 
 // default key to store mnemonic
-const MNEMONIC_KEY: &str = "mnemonic";
+pub(crate) const MNEMONIC_KEY: &str = "mnemonic";
 
 // key to store mnemonic count
-const MNEMONIC_COUNT_KEY: &str = "mnemonic_count";
+pub(crate) const MNEMONIC_COUNT_KEY: &str = "mnemonic_count";
 
 // A user may decide to protect their mnemonic with a passphrase.
-// We pass an empty password since the mnemonic has sufficient entropy and will be backed up.
+// Mnemonics without a passphrase use the empty one.
 // https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki#from-mnemonic-to-seed
-const MNEMONIC_PASSWORD: &str = "";
+const NO_PASSPHRASE: &str = "";
+
+/// Returns x if `key` is of the form 'mnemonic_x', where x is a number.
+/// The mnemonic count key is not a mnemonic key.
+pub(crate) fn rotated_mnemonic_index(key: &str) -> Option<u32> {
+    key.strip_prefix(MNEMONIC_KEY)?
+        .strip_prefix('_')?
+        .parse()
+        .ok()
+}
+
+/// The key of the rotated out mnemonic `index`, or of the current mnemonic if no index is given
+pub(super) fn mnemonic_key(index: Option<u32>) -> String {
+    match index {
+        Some(index) => format!("{}_{}", MNEMONIC_KEY, index),
+        None => MNEMONIC_KEY.to_owned(),
+    }
+}
+
+/// Number of mnemonics in the kv-store, including rotated out ones
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct MnemonicCount(pub(crate) u32);
+
+impl KvType for MnemonicCount {
+    const TYPE_ID: &'static str = "mnemonic_count";
+    const VERSION: u32 = 1;
+    const DOMAIN: KvDomain = KvDomain::MnemonicMeta;
+    const UNENVELOPED_VERSION: Option<u32> = Some(1);
+}
+
+/// The outcome of a rotation of the mnemonic
+#[derive(Clone, Debug)]
+pub(crate) struct RotatedMnemonic {
+    /// the key that the rotated out mnemonic is stored under
+    pub(crate) rotated_out: String,
+    /// the fingerprint of the new mnemonic
+    pub(crate) fingerprint: MnemonicFingerprint,
+}
+
+/// A stored mnemonic, as listed by [Cmd::List]; holds no secret material
+#[derive(Clone, Debug, Serialize)]
+pub struct MnemonicSummary {
+    /// the key that the mnemonic is stored under
+    pub mnemonic: String,
+    pub fingerprint: String,
+    pub words: u32,
+    /// the code of the language of the mnemonic, see [MnemonicLanguage::code]
+    pub language: &'static str,
+    /// whether the mnemonic is protected by a passphrase
+    pub protected: bool,
+    /// seconds since unix epoch at the time the mnemonic was created or imported; unknown for older mnemonics
+    pub created_at: Option<u64>,
+    pub imported: bool,
+    /// whether the keys of the mnemonic are recorded; see [MnemonicRecord::tracks_keys]
+    pub keys_tracked: bool,
+    /// number of the recorded keys of the mnemonic
+    pub key_count: usize,
+    /// the recorded keys of the mnemonic; only listed along with an index
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+}
+
+/// The result of a mnemonic command that exits tofnd; holds no secret material
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum CmdOutput {
+    Created {
+        mnemonic: String,
+        fingerprint: String,
+    },
+    Imported {
+        mnemonic: String,
+        fingerprint: String,
+    },
+    Exported {
+        mnemonic: String,
+    },
+    Rotated {
+        rotated_out: String,
+        fingerprint: String,
+    },
+    Listed {
+        mnemonics: Vec<MnemonicSummary>,
+    },
+    Pruned {
+        mnemonic: String,
+        fingerprint: String,
+    },
+    Verified {
+        mnemonic: String,
+    },
+}
+
+/// Options of mnemonic commands
+#[derive(Clone, Debug, Default)]
+pub struct MnemonicConfig {
+    /// Language of created mnemonics and exported phrases.
+    /// If not set, new mnemonics are English and phrases are exported in the language they were stored in.
+    pub language: Option<MnemonicLanguage>,
+    /// Number of words of created mnemonics. If not set, new mnemonics have 24 words.
+    pub word_count: Option<usize>,
+    /// Protect created and imported mnemonics with a BIP39 passphrase.
+    pub use_passphrase: bool,
+    /// File to read the passphrase from. If not set, the user is prompted for it.
+    pub passphrase_file: Option<PathBuf>,
+    /// Export mnemonics as SLIP-39 shares of this scheme instead of a single phrase.
+    pub shares: Option<ShareScheme>,
+    /// Import the mnemonic from these SLIP-39 share files instead of prompting for a phrase.
+    pub share_files: Vec<PathBuf>,
+    /// Seal exported phrases with an export passphrase. Share files are not sealed.
+    pub seal_export: bool,
+    /// Write exported phrases to this file instead of the export file in the tofnd root.
+    pub export_path: Option<PathBuf>,
+    /// Write exported phrases to stdout instead of a file.
+    pub to_stdout: bool,
+    /// Import the mnemonic from this file instead of prompting for a phrase.
+    /// The file holds either a phrase or a sealed export.
+    pub import_path: Option<PathBuf>,
+    /// File to read the export passphrase from. If not set, the user is prompted for it.
+    pub export_passphrase_file: Option<PathBuf>,
+    /// Index of the rotated out mnemonic `mnemonic_<index>` to export, list or prune.
+    /// If not set, the current mnemonic is exported and all mnemonics are listed.
+    pub index: Option<u32>,
+}
+
+impl MnemonicConfig {
+    /// creates a new mnemonic of the configured word count in `language`
+    fn new_record(&self, language: MnemonicLanguage) -> InnerMnemonicResult<MnemonicRecord> {
+        let entropy = bip39_new(self.word_count.unwrap_or(DEFAULT_WORD_COUNT))?;
+        Ok(MnemonicRecord::new(entropy, language))
+    }
+
+    /// writes the mnemonic of `entropy` in `language` to the configured export target,
+    /// to share files if a share scheme is configured, or to the sealed export file if sealing is configured
+    fn export(
+        &self,
+        io: &FileIo,
+        entropy: Entropy,
+        language: MnemonicLanguage,
+    ) -> InnerMnemonicResult<()> {
+        match (self.shares, self.seal_export) {
+            (Some(scheme), _) => io.entropy_to_share_files(entropy, language, scheme)?,
+            (None, true) => {
+                let passphrase = self.read_export_passphrase(true)?;
+                io.entropy_to_sealed_file(entropy, language, &passphrase, self.export_target())?
+            }
+            (None, false) => io.entropy_to_file(entropy, language, self.export_target())?,
+        }
+        Ok(())
+    }
+
+    /// Checks that phrases are exported sealed with an export passphrase file, so that a running daemon
+    /// exports a rotated mnemonic without prompting and without writing the phrase in plaintext.
+    pub(crate) fn check_online_export(&self) -> InnerMnemonicResult<()> {
+        match self.seal_export
+            && self.export_passphrase_file.is_some()
+            && self.shares.is_none()
+            && !self.to_stdout
+        {
+            true => Ok(()),
+            false => Err(InsecureExport),
+        }
+    }
+
+    /// the file that the sealed export of a rotation by a running daemon is written to.
+    /// Every rotation has its own file, named after the key that the current mnemonic is rotated out to,
+    /// e.g. `export.sealed.mnemonic_1`, so that an earlier export doesn't block the next rotation.
+    pub(crate) fn rotation_export_path(&self, io: &FileIo, rotated_out: &str) -> PathBuf {
+        let mut path = self
+            .export_path
+            .clone()
+            .unwrap_or_else(|| io.sealed_export_path().clone())
+            .into_os_string();
+        path.push(format!(".{}", rotated_out));
+        path.into()
+    }
+
+    /// where exported phrases are written
+    fn export_target(&self) -> ExportTarget {
+        match (self.to_stdout, &self.export_path) {
+            (true, _) => ExportTarget::Stdout,
+            (false, Some(path)) => ExportTarget::Path(path),
+            (false, None) => ExportTarget::Default,
+        }
+    }
+
+    /// reads the mnemonic passphrase from the configured file, or prompts the user for it.
+    /// If `confirm` is set, the user has to type a prompted passphrase twice.
+    fn read_passphrase(&self, confirm: bool) -> InnerMnemonicResult<Password> {
+        read_secret(
+            self.passphrase_file.as_deref(),
+            "mnemonic passphrase",
+            confirm,
+        )
+    }
+
+    /// reads the export passphrase from the configured file, or prompts the user for it.
+    /// If `confirm` is set, the user has to type a prompted passphrase twice.
+    fn read_export_passphrase(&self, confirm: bool) -> InnerMnemonicResult<Password> {
+        read_secret(
+            self.export_passphrase_file.as_deref(),
+            "export passphrase",
+            confirm,
+        )
+    }
+}
+
+/// Reads the secret called `name` from `file`, or prompts the user for it if no file is given.
+/// If `confirm` is set, the user has to type a prompted secret twice.
+fn read_secret(file: Option<&Path>, name: &str, confirm: bool) -> InnerMnemonicResult<Password> {
+    let mut secret = match file {
+        Some(path) => Password(
+            std::fs::read_to_string(path)
+                .map_err(|e| PasswordErr(format!("cannot read {} {:?}: {}", name, path, e)))?,
+        ),
+        None => {
+            eprintln!("Please type your {}:", name);
+            let secret = Password(read_password().map_err(|e| PasswordErr(e.to_string()))?);
+            if confirm {
+                eprintln!("Please type your {} again:", name);
+                let confirmation =
+                    Password(read_password().map_err(|e| PasswordErr(e.to_string()))?);
+                if secret.0 != confirmation.0 {
+                    return Err(PasswordErr(format!("{}s do not match", name)));
+                }
+            }
+            secret
+        }
+    };
+
+    // drop the line ending of the file
+    let len = secret.0.trim_end_matches(&['\r', '\n'][..]).len();
+    secret.0.truncate(len);
+
+    // an empty mnemonic passphrase would produce the same seed as no passphrase
+    if secret.0.is_empty() {
+        return Err(PasswordErr(format!("empty {}", name)));
+    }
+    Ok(secret)
+}
+
+/// Gets the seed of `record` under `passphrase`; takes ownership of record to delegate zeroization.
+/// If the record is protected by a passphrase, the passphrase is verified against its fingerprint.
+fn record_seed(record: MnemonicRecord, passphrase: Option<&Password>) -> SeedResult<Seed> {
+    let MnemonicRecord {
+        entropy,
+        language,
+        entropy_len,
+        passphrase_fingerprint,
+        ..
+    } = record;
+
+    if entropy.0.len() != entropy_len as usize {
+        return Err(Bip39Error(EntropyLength {
+            expected: entropy_len as usize,
+            found: entropy.0.len(),
+        }));
+    }
+
+    let passphrase = match passphrase_fingerprint {
+        Some(_) => passphrase.ok_or(MissingPassphrase)?.clone(),
+        None => Password(NO_PASSPHRASE.to_owned()),
+    };
+
+    let seed = bip39_seed(entropy, language, passphrase)?;
+    match passphrase_fingerprint {
+        Some(fingerprint) if seed_fingerprint(&seed) != fingerprint => Err(WrongPassphrase),
+        _ => Ok(seed),
+    }
+}
+
+/// Gets the non-secret fingerprint of `record` under `passphrase`; takes ownership of record to delegate zeroization.
+fn record_fingerprint(
+    record: MnemonicRecord,
+    passphrase: Option<&Password>,
+) -> InnerMnemonicResult<MnemonicFingerprint> {
+    let seed: SecretRecoveryKey = record_seed(record, passphrase)?.as_bytes().try_into()?;
+    mnemonic_fingerprint(&seed)
+}
+
+/// Protects `record` with `passphrase` by recording the fingerprint of its seed
+fn protect_record(record: &mut MnemonicRecord, passphrase: &Password) -> InnerMnemonicResult<()> {
+    let seed = bip39_seed(record.entropy.clone(), record.language, passphrase.clone())?;
+    record.passphrase_fingerprint = Some(seed_fingerprint(&seed));
+    Ok(())
+}
 
 #[derive(Clone, Debug)]
 pub enum Cmd {
     Existing,
+    Auto,
     Create,
     Import,
     Export,
     Rotate,
+    List,
+    Prune,
+    Verify,
 }
 
 impl Cmd {
     pub fn from_string(cmd_str: &str) -> MnemonicResult<Self> {
         let cmd = match cmd_str {
             "existing" => Self::Existing,
+            "auto" => Self::Auto,
             "create" => Self::Create,
             "import" => Self::Import,
             "export" => Self::Export,
             "rotate" => Self::Rotate,
+            "list" => Self::List,
+            "prune" => Self::Prune,
+            "verify" => Self::Verify,
             _ => return Err(WrongCommand(cmd_str.to_string())),
         };
         Ok(cmd)
     }
-    /// On [Cmd::Existing], continue tofnd.
-    /// On [Cmd::Create], [Cmd::Import] or [Cmd::Export], exit tofnd.
+    /// the name of the command, as parsed by [Cmd::from_string]
+    pub fn name(&self) -> &'static str {
+        match &self {
+            Cmd::Existing => "existing",
+            Cmd::Auto => "auto",
+            Cmd::Create => "create",
+            Cmd::Import => "import",
+            Cmd::Export => "export",
+            Cmd::Rotate => "rotate",
+            Cmd::List => "list",
+            Cmd::Prune => "prune",
+            Cmd::Verify => "verify",
+        }
+    }
+    /// On [Cmd::Existing] and [Cmd::Auto], continue tofnd.
+    /// On any other command, exit tofnd.
     pub fn exit_after_cmd(&self) -> bool {
         match &self {
             Cmd::Existing => false,
+            Cmd::Auto => false,
             Cmd::Create => true,
             Cmd::Import => true,
             Cmd::Export => true,
             Cmd::Rotate => true,
+            Cmd::List => true,
+            Cmd::Prune => true,
+            Cmd::Verify => true,
         }
     }
 }
@@ -75,24 +397,73 @@ impl KvManager {
     }
 
     /// Get mnemonic seed under key
+    /// If the mnemonic is protected by a passphrase, the passphrase verified at startup is used.
     pub async fn get_seed(&self, key: &str) -> SeedResult<SecretRecoveryKey> {
-        let mnemonic = self
-            .kv()
-            .get(key)
-            .await?
-            .try_into()
-            .map_err(KvError::GetErr)?;
-
-        Ok(
-            bip39_seed(mnemonic, Password(MNEMONIC_PASSWORD.to_owned()))?
-                .as_bytes()
-                .try_into()?,
-        )
+        let record = self.get::<MnemonicRecord>(key).await?;
+        Ok(record_seed(record, self.mnemonic_passphrase())?
+            .as_bytes()
+            .try_into()?)
+    }
+
+    /// Get the keys of all mnemonics that are protected by a passphrase
+    async fn protected_keys(&self) -> InnerMnemonicResult<Vec<String>> {
+        let mut protected = vec![];
+        let mut keys = self.keys_with_prefix::<MnemonicRecord>(MNEMONIC_KEY);
+        while let Some(key) = keys.next().await {
+            let key = key?;
+            let record = self.get::<MnemonicRecord>(&key).await?;
+            if record.passphrase_fingerprint.is_some() {
+                protected.push(key);
+            }
+        }
+        Ok(protected)
+    }
+
+    /// Verifies `passphrase` against the mnemonics under `keys`
+    async fn verify_passphrase(
+        &self,
+        keys: &[String],
+        passphrase: &Password,
+    ) -> InnerMnemonicResult<()> {
+        for key in keys {
+            let record = self.get::<MnemonicRecord>(key).await?;
+            record_seed(record, Some(passphrase)).map_err(|err| {
+                error!("Cannot verify passphrase of mnemonic '{}': {}", key, err);
+                err
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Reads the passphrase of a new mnemonic.
+    /// All mnemonics in the kv-store share one passphrase, so if some are already protected,
+    /// the passphrase is verified against them. Otherwise, a prompted passphrase has to be confirmed.
+    async fn read_new_passphrase(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<Password> {
+        let protected = self.protected_keys().await?;
+        if protected.is_empty() {
+            return cfg.read_passphrase(true);
+        }
+        let passphrase = cfg.read_passphrase(false)?;
+        self.verify_passphrase(&protected, &passphrase).await?;
+        Ok(passphrase)
     }
 
+    /// Get the keys of all mnemonics in the kv store, from the latest to the oldest
     pub async fn seed_key_iter(&self) -> InnerMnemonicResult<Vec<String>> {
-        let count = self.seed_count().await?;
-        if count == 0 {
+        let mut has_latest = false;
+        let mut indices = vec![];
+
+        let mut keys = self.keys_with_prefix::<MnemonicRecord>(MNEMONIC_KEY);
+        while let Some(key) = keys.next().await {
+            let key = key?;
+            if key == MNEMONIC_KEY {
+                has_latest = true;
+            } else if let Some(index) = rotated_mnemonic_index(&key) {
+                indices.push(index);
+            }
+        }
+
+        if !has_latest && indices.is_empty() {
             return Err(KvErr(KvError::GetErr(InnerKvError::LogicalErr(
                 "no mnemonic found".to_owned(),
             ))));
@@ -100,56 +471,146 @@ impl KvManager {
 
         // To optimize performance, iterate from the latest mnemonic to the oldest
         // Latest mnemonic is stored under 'mnemonic'
-        // Second latest mnemonic is stored under 'mnemonic_x' where x is the seed count
-        // Older mnemonics are stored in decreasing order of index
-        let mut keys = vec![String::from(MNEMONIC_KEY)];
+        // Rotated out mnemonics are stored under 'mnemonic_x'; a greater x is more recent
+        indices.sort_unstable_by(|a, b| b.cmp(a));
 
-        for i in (1..count).rev() {
-            keys.push(format!("{}_{}", MNEMONIC_KEY, i))
+        let mut keys = vec![];
+        if has_latest {
+            keys.push(String::from(MNEMONIC_KEY));
         }
+        keys.extend(
+            indices
+                .into_iter()
+                .map(|index| format!("{}_{}", MNEMONIC_KEY, index)),
+        );
 
         Ok(keys)
     }
 
     /// async function that handles all mnemonic commands
-    pub async fn handle_mnemonic(self, cmd: &Cmd) -> MnemonicResult<Self> {
-        match cmd {
-            Cmd::Existing => self.handle_existing().await.map_err(ExistingErr)?,
-            Cmd::Create => self.handle_create().await.map_err(CreateErr)?,
-            Cmd::Import => self.handle_import().await.map_err(ImportErr)?,
-            Cmd::Export => self.handle_export().await.map_err(ExportErr)?,
-            Cmd::Rotate => self.handle_rotate().await.map_err(RotateErr)?,
+    pub async fn handle_mnemonic(self, cmd: &Cmd, cfg: &MnemonicConfig) -> MnemonicResult<Self> {
+        Ok(self.run_mnemonic_cmd(cmd, cfg).await?.0)
+    }
+
+    /// handles a mnemonic command like [KvManager::handle_mnemonic].
+    /// Also returns the result of commands that exit tofnd, see [Cmd::exit_after_cmd].
+    pub async fn run_mnemonic_cmd(
+        mut self,
+        cmd: &Cmd,
+        cfg: &MnemonicConfig,
+    ) -> MnemonicResult<(Self, Option<CmdOutput>)> {
+        let output = match cmd {
+            Cmd::Existing => {
+                if let Some(passphrase) = self.handle_existing(cfg).await.map_err(ExistingErr)? {
+                    self.set_mnemonic_passphrase(passphrase);
+                }
+                None
+            }
+            Cmd::Auto => {
+                if let Some(passphrase) = self.handle_auto(cfg).await.map_err(AutoErr)? {
+                    self.set_mnemonic_passphrase(passphrase);
+                }
+                None
+            }
+            Cmd::Create => Some(self.handle_create(cfg).await.map_err(CreateErr)?),
+            Cmd::Import => Some(self.handle_import(cfg).await.map_err(ImportErr)?),
+            Cmd::Export => Some(self.handle_export(cfg).await.map_err(ExportErr)?),
+            Cmd::Rotate => {
+                let rotated = self.handle_rotate(cfg).await.map_err(RotateErr)?;
+                Some(CmdOutput::Rotated {
+                    rotated_out: rotated.rotated_out,
+                    fingerprint: rotated.fingerprint.to_string(),
+                })
+            }
+            Cmd::List => Some(self.handle_list(cfg).await.map_err(ListErr)?),
+            Cmd::Prune => Some(self.handle_prune(cfg).await.map_err(PruneErr)?),
+            Cmd::Verify => Some(CmdOutput::Verified {
+                mnemonic: self.handle_verify(cfg).await.map_err(VerifyErr)?,
+            }),
         };
-        Ok(self)
+        Ok((self, output))
     }
 
     /// use the existing mnemonic to spin up a tofnd deamon.
     /// if an export file exists in the default path, returns an error.
     /// if an no mnemonic record exists in the kv-store, returns an error.
-    async fn handle_existing(&self) -> InnerMnemonicResult<()> {
+    /// if mnemonics are protected by a passphrase, reads the passphrase and verifies it
+    /// against them before it is returned; returns an error if it is wrong.
+    async fn handle_existing(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<Option<Password>> {
         // if there is an exported mnemonic, raise an error and don't start the daemon.
         // we do this to prevent users from accidentally leave their mnemonic on disk in plain text
         self.io().check_if_not_exported()?;
 
         // try to get mnemonic from kv-store
-        match self.kv().exists(MNEMONIC_KEY).await? {
-            true => Ok(()),
-            false => Err(KvErr(KvError::ExistsErr(InnerKvError::LogicalErr(
+        if !self.exists::<MnemonicRecord>(MNEMONIC_KEY).await? {
+            return Err(KvErr(KvError::ExistsErr(InnerKvError::LogicalErr(
                 "Mnemonic not found".to_string(),
-            )))),
+            ))));
+        }
+
+        self.unlock(cfg).await
+    }
+
+    /// sets up a mnemonic if none exists, and then uses it to spin up a tofnd daemon like [Cmd::Existing].
+    /// the mnemonic is imported from the import path of `cfg`, or from the import file in the tofnd root.
+    /// if there is no import file, a new mnemonic is created and exported to the import file,
+    /// so that the export does not block the daemon and the mnemonic is imported again if the kv-store is lost.
+    async fn handle_auto(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<Option<Password>> {
+        if self.exists::<MnemonicRecord>(MNEMONIC_KEY).await? {
+            info!("Found existing mnemonic; skipping mnemonic set up");
+        } else {
+            let import_path = cfg
+                .import_path
+                .clone()
+                .unwrap_or_else(|| self.io().import_path().clone());
+
+            if import_path.exists() {
+                self.handle_import(&MnemonicConfig {
+                    import_path: Some(import_path),
+                    ..cfg.clone()
+                })
+                .await?;
+            } else {
+                info!("No import file found at {:?}", import_path);
+                self.handle_create(&MnemonicConfig {
+                    export_path: Some(import_path),
+                    ..cfg.clone()
+                })
+                .await?;
+            }
         }
+
+        self.handle_existing(cfg).await
+    }
+
+    /// if mnemonics are protected by a passphrase, reads the passphrase and verifies it
+    /// against them before it is returned; returns an error if it is wrong.
+    async fn unlock(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<Option<Password>> {
+        let protected = self.protected_keys().await?;
+        if protected.is_empty() {
+            return Ok(None);
+        }
+
+        info!(
+            "{} mnemonics are protected by a passphrase. Verifying passphrase",
+            protected.len()
+        );
+        let passphrase = cfg.read_passphrase(false)?;
+        self.verify_passphrase(&protected, &passphrase).await?;
+        Ok(Some(passphrase))
     }
 
     /// Get the mnemonic count in the kv store.
     pub async fn seed_count(&self) -> InnerMnemonicResult<u32> {
-        match self.kv().get(MNEMONIC_COUNT_KEY).await {
-            Ok(encoded_count) => Ok(deserialize(&encoded_count)
-                .ok_or(KvErr(KvError::GetErr(InnerKvError::DeserializationErr)))?),
+        match self.get::<MnemonicCount>(MNEMONIC_COUNT_KEY).await {
+            Ok(MnemonicCount(count)) => Ok(count),
             // if MNEMONIC_COUNT_KEY does not exist then mnemonic count is either 0 or 1
-            Err(KvError::GetErr(_)) => Ok(match self.kv().exists(MNEMONIC_KEY).await? {
-                true => 1,
-                false => 0,
-            }),
+            Err(KvError::GetErr(_)) => {
+                Ok(match self.exists::<MnemonicRecord>(MNEMONIC_KEY).await? {
+                    true => 1,
+                    false => 0,
+                })
+            }
             Err(_) => {
                 error!("");
                 Err(PasswordErr(String::from("")))
@@ -169,21 +630,17 @@ impl KvManager {
         Ok((key, count))
     }
 
-    /// inserts entropy to the kv-store
-    /// takes ownership of entropy to delegate zeroization.
-    async fn put_entropy(
+    /// inserts a mnemonic record to the kv-store
+    /// takes ownership of record to delegate zeroization.
+    async fn put_record(
         &self,
         reservation: KeyReservation,
-        entropy: Entropy,
+        record: MnemonicRecord,
     ) -> InnerMnemonicResult<()> {
-        match self
-            .kv()
-            .put(reservation, entropy.try_into().map_err(KvError::PutErr)?)
-            .await
-        {
+        match self.put(reservation, record).await {
             // if put is ok, write the phrase to a file
             Ok(()) => {
-                info!("Mnemonic successfully added in kv store. Use the `-m export` command to retrieve it.");
+                info!("Mnemonic successfully added in kv store. Use `tofnd mnemonic export` to retrieve it.");
                 Ok(())
             }
             // else return failure
@@ -194,9 +651,10 @@ impl KvManager {
         }
     }
 
-    /// inserts entropy to the kv-store
-    /// takes ownership of entropy to delegate zeroization.
-    async fn handle_insert(&self, entropy: Entropy) -> InnerMnemonicResult<()> {
+    /// inserts a mnemonic record to the kv-store
+    /// takes ownership of record to delegate zeroization.
+    /// Returns the index of the inserted mnemonic; see [MnemonicIndex].
+    async fn handle_insert(&self, record: MnemonicRecord) -> InnerMnemonicResult<Option<u32>> {
         let (key, count) = self.get_next_key().await?;
 
         info!(
@@ -204,49 +662,65 @@ impl KvManager {
             key, count
         );
 
-        let reservation = self.kv().reserve_key(key).await.map_err(|err| {
+        let reservation = self.reserve::<MnemonicRecord>(&key).await.map_err(|err| {
             error!("Cannot reserve mnemonic key: {:?}", err);
             KvErr(err)
         })?;
 
         // Insert before updating the count to minimize state corruption if it fails in the middle
-        self.put_entropy(reservation, entropy).await?;
+        self.put_record(reservation, record).await?;
 
         // If delete isn't successful, the previous mnemonic count will still allow tofnd to work
-        self.kv().delete(MNEMONIC_COUNT_KEY).await.map_err(|err| {
-            error!("could not delete mnemonic count: {:?}", err);
-            KvErr(err)
-        })?;
+        self.delete::<MnemonicCount>(MNEMONIC_COUNT_KEY)
+            .await
+            .map_err(|err| {
+                error!("could not delete mnemonic count: {:?}", err);
+                KvErr(err)
+            })?;
 
         let count_reservation = self
-            .kv()
-            .reserve_key(MNEMONIC_COUNT_KEY.to_owned())
+            .reserve::<MnemonicCount>(MNEMONIC_COUNT_KEY)
             .await
             .map_err(|err| {
                 error!("Cannot reserve mnemonic count key: {:?}", err);
                 KvErr(err)
             })?;
 
-        let encoded_count = serialize(&(count + 1))
-            .map_err(|_| KvErr(KvError::PutErr(InnerKvError::SerializationErr)))?;
-
         // If the new count isn't written, tofnd will still work with the latest mnemonic
-        self.kv()
-            .put(count_reservation, encoded_count)
+        self.put(count_reservation, MnemonicCount(count + 1))
             .await
             .map_err(|err| {
                 error!("Could not update the mnemonic count in kv store: {:?}", err);
                 KvErr(err)
-            })
+            })?;
+
+        Ok(rotated_mnemonic_index(&key))
+    }
+
+    /// Records the index of the mnemonic of `record` under `passphrase`,
+    /// so that the seeds of its keys are looked up without scanning all mnemonics.
+    /// takes ownership of record to delegate zeroization.
+    /// Returns the fingerprint of the mnemonic.
+    async fn index_record(
+        &self,
+        record: MnemonicRecord,
+        passphrase: Option<&Password>,
+        index: Option<u32>,
+    ) -> InnerMnemonicResult<MnemonicFingerprint> {
+        let fingerprint = record_fingerprint(record, passphrase)?;
+        self.record_mnemonic_index(fingerprint, MnemonicIndex(index))
+            .await?;
+        Ok(fingerprint)
     }
 
     /// Creates a new entropy, inserts the entropy in the kv-store and exports it to a file
+    /// The mnemonic is in the language of `cfg`, or English if none is set.
     /// If a mnemonic already exists in the kv store or an exported file already exists in
     /// the default path, an error is produced
-    async fn handle_create(&self) -> InnerMnemonicResult<()> {
+    async fn handle_create(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<CmdOutput> {
         info!("Creating mnemonic");
 
-        if self.kv().exists(MNEMONIC_KEY).await? {
+        if self.exists::<MnemonicRecord>(MNEMONIC_KEY).await? {
             error!("Mnemonic was already created");
             return Err(KvErr(KvError::ReserveErr(InnerKvError::LogicalErr(
                 "mnemonic was already present".to_owned(),
@@ -254,88 +728,378 @@ impl KvManager {
         }
 
         // create a new entropy
-        let new_entropy = bip39_new_w24();
+        let mut new_record = cfg.new_record(cfg.language.unwrap_or_default())?;
+        let passphrase = match cfg.use_passphrase {
+            true => Some(self.read_new_passphrase(cfg).await?),
+            false => None,
+        };
+        if let Some(passphrase) = &passphrase {
+            protect_record(&mut new_record, passphrase)?;
+        }
+
+        // export first, so that a mnemonic is never stored without its backup
+        cfg.export(self.io(), new_record.entropy.clone(), new_record.language)?;
 
-        self.handle_insert(new_entropy.clone()).await?;
+        let index = self.handle_insert(new_record.clone()).await?;
+        let fingerprint = self
+            .index_record(new_record, passphrase.as_ref(), index)
+            .await?;
 
-        Ok(self.io().entropy_to_file(new_entropy)?)
+        Ok(CmdOutput::Created {
+            mnemonic: mnemonic_key(index),
+            fingerprint: fingerprint.to_string(),
+        })
     }
 
     /// Inserts a new mnemonic to the kv-store.
     /// If a mnemonic already exists in the kv store, a new entry is created
     /// storing it as a rotated out mnemonic.
-    async fn handle_import(&self) -> InnerMnemonicResult<()> {
+    /// The language of the mnemonic is detected from the phrase.
+    /// If `cfg` has an import path or share files, the mnemonic is read from them instead of prompted for.
+    /// If `cfg` uses a passphrase, the mnemonic is protected by it.
+    async fn handle_import(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<CmdOutput> {
         info!("Importing mnemonic");
-        let imported_phrase = Password(read_password().map_err(|e| PasswordErr(e.to_string()))?);
-        let imported_entropy = bip39_from_phrase(imported_phrase)?;
-        self.handle_insert(imported_entropy).await
+        let (entropy, language) = self.read_backup(cfg)?;
+        self.import_entropy(entropy, language, cfg).await
+    }
+
+    /// Reads a backup of a mnemonic from the import path or the share files of `cfg`,
+    /// or prompts the user for a phrase if none are given.
+    /// Returns the entropy of the mnemonic along with its language.
+    fn read_backup(
+        &self,
+        cfg: &MnemonicConfig,
+    ) -> InnerMnemonicResult<(Entropy, MnemonicLanguage)> {
+        let (entropy, language) = if let Some(path) = &cfg.import_path {
+            if self.io().is_sealed_file(path)? {
+                info!("Unsealing mnemonic from {:?}", path);
+                let passphrase = cfg.read_export_passphrase(false)?;
+                self.io().entropy_from_sealed_file(path, &passphrase)?
+            } else {
+                info!("Reading mnemonic from {:?}", path);
+                self.io().entropy_from_file(path)?
+            }
+        } else if !cfg.share_files.is_empty() {
+            info!("Combining {} mnemonic shares", cfg.share_files.len());
+            self.io().entropy_from_share_files(&cfg.share_files)?
+        } else {
+            let phrase = Password(read_password().map_err(|e| PasswordErr(e.to_string()))?);
+            bip39_from_phrase(phrase)?
+        };
+        info!("Detected mnemonic language {:?}", language);
+        Ok((entropy, language))
     }
 
-    /// Exports the current mnemonic to a file
-    async fn handle_export(&self) -> InnerMnemonicResult<()> {
-        info!("Exporting mnemonic");
+    /// inserts the mnemonic of `phrase` to the kv-store
+    /// takes ownership of phrase to delegate zeroization.
+    #[cfg(test)]
+    async fn import_phrase(
+        &self,
+        phrase: Password,
+        cfg: &MnemonicConfig,
+    ) -> InnerMnemonicResult<CmdOutput> {
+        let (entropy, language) = bip39_from_phrase(phrase)?;
+        info!("Detected mnemonic language {:?}", language);
+        self.import_entropy(entropy, language, cfg).await
+    }
+
+    /// inserts the mnemonic of `entropy` in `language` to the kv-store
+    /// takes ownership of entropy to delegate zeroization.
+    async fn import_entropy(
+        &self,
+        entropy: Entropy,
+        language: MnemonicLanguage,
+        cfg: &MnemonicConfig,
+    ) -> InnerMnemonicResult<CmdOutput> {
+        let mut record = MnemonicRecord {
+            imported: true,
+            ..MnemonicRecord::new(entropy, language)
+        };
+        let passphrase = match cfg.use_passphrase {
+            true => Some(self.read_new_passphrase(cfg).await?),
+            false => None,
+        };
+        if let Some(passphrase) = &passphrase {
+            protect_record(&mut record, passphrase)?;
+        }
+
+        let index = self.handle_insert(record.clone()).await?;
+        let fingerprint = self
+            .index_record(record, passphrase.as_ref(), index)
+            .await?;
+        Ok(CmdOutput::Imported {
+            mnemonic: mnemonic_key(index),
+            fingerprint: fingerprint.to_string(),
+        })
+    }
+
+    /// Exports the current mnemonic to a file, or to share files if `cfg` sets a share scheme
+    /// If `cfg` has an index, the rotated out mnemonic of that index is exported instead.
+    /// The phrase is in the language of `cfg`, or in the language the mnemonic was stored in if none is set.
+    async fn handle_export(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<CmdOutput> {
+        let key = mnemonic_key(cfg.index);
+        info!("Exporting mnemonic '{}'", key);
 
         // try to get mnemonic from kv-store
-        let entropy = self
-            .kv()
-            .get(MNEMONIC_KEY)
-            .await
-            .map_err(|err| {
-                error!("Did not find mnemonic in kv store {:?}", err);
-                err
-            })?
-            .try_into()
-            .map_err(KvError::GetErr)?;
+        let MnemonicRecord {
+            entropy,
+            language,
+            passphrase_fingerprint,
+            ..
+        } = self.get::<MnemonicRecord>(&key).await.map_err(|err| {
+            error!("Did not find mnemonic in kv store {:?}", err);
+            err
+        })?;
 
         // write to file
         info!("Mnemonic found in kv store");
-        Ok(self.io().entropy_to_file(entropy)?)
+        if passphrase_fingerprint.is_some() {
+            info!("Mnemonic is protected by a passphrase, which is not exported. Keep a separate backup of the passphrase.");
+        }
+        cfg.export(self.io(), entropy, cfg.language.unwrap_or(language))?;
+        Ok(CmdOutput::Exported { mnemonic: key })
+    }
+
+    /// Lists the current and rotated out mnemonics, from the latest to the oldest,
+    /// along with the number of keys that depend on them.
+    /// If `cfg` has an index, only the mnemonic of that index is listed, along with the keys that depend on it.
+    /// The fingerprint of a protected mnemonic depends on its passphrase, so the passphrase is read if needed.
+    async fn handle_list(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<CmdOutput> {
+        let passphrase = self.unlock(cfg).await?;
+        let keys = match cfg.index {
+            Some(index) => vec![mnemonic_key(Some(index))],
+            None => self.seed_key_iter().await?,
+        };
+
+        let mut mnemonics = vec![];
+        for key in keys {
+            let record = self.get::<MnemonicRecord>(&key).await?;
+            let mut summary = MnemonicSummary {
+                mnemonic: key.clone(),
+                fingerprint: String::new(),
+                words: record.word_count(),
+                language: record.language.code(),
+                protected: record.passphrase_fingerprint.is_some(),
+                created_at: record.created_at,
+                imported: record.imported,
+                keys_tracked: record.tracks_keys(),
+                key_count: 0,
+                keys: vec![],
+            };
+            let created = match (record.created_at, record.imported) {
+                (Some(time), false) => format!("created at unix time {}", time),
+                (Some(time), true) => format!("imported at unix time {}", time),
+                (None, _) => "stored by an older version of tofnd".to_owned(),
+            };
+            let protection = match record.passphrase_fingerprint {
+                Some(_) => "with passphrase",
+                None => "without passphrase",
+            };
+            let tracked = match record.tracks_keys() {
+                true => "keys are tracked",
+                false => "keys are not tracked",
+            };
+            let description = format!(
+                "{} words, {:?}, {}, {}, {}",
+                record.word_count(),
+                record.language,
+                protection,
+                created,
+                tracked
+            );
+
+            let fingerprint = record_fingerprint(record, passphrase.as_ref())?;
+            let dependents = self.keys_of_mnemonic(fingerprint).await?;
+            info!(
+                "Mnemonic '{}': fingerprint {}, {}, used by {} keys",
+                key,
+                fingerprint,
+                description,
+                dependents.len()
+            );
+            summary.fingerprint = fingerprint.to_string();
+            summary.key_count = dependents.len();
+            if cfg.index.is_some() {
+                for dependent in &dependents {
+                    info!("Mnemonic '{}' is used by key '{}'", key, dependent);
+                }
+                summary.keys = dependents;
+            }
+            mnemonics.push(summary);
+        }
+        Ok(CmdOutput::Listed { mnemonics })
+    }
+
+    /// Deletes the rotated out mnemonic of the index of `cfg`.
+    /// A mnemonic is only pruned if its keys are tracked and no key depends on it.
+    /// The current mnemonic can't be pruned.
+    async fn handle_prune(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<CmdOutput> {
+        let key = mnemonic_key(Some(cfg.index.ok_or(MissingIndex)?));
+        info!("Pruning mnemonic '{}'", key);
+
+        let passphrase = self.unlock(cfg).await?;
+        let record = self.get::<MnemonicRecord>(&key).await?;
+        if !record.tracks_keys() {
+            return Err(UntrackedMnemonic(key));
+        }
+
+        let fingerprint = record_fingerprint(record, passphrase.as_ref())?;
+        let keys = self.keys_of_mnemonic(fingerprint).await?;
+        if !keys.is_empty() {
+            return Err(MnemonicInUse {
+                mnemonic: key,
+                keys,
+            });
+        }
+
+        self.delete::<MnemonicRecord>(&key).await?;
+        self.forget_mnemonic_index(fingerprint).await?;
+        info!("Pruned mnemonic '{}' with fingerprint {}", key, fingerprint);
+        Ok(CmdOutput::Pruned {
+            mnemonic: key,
+            fingerprint: fingerprint.to_string(),
+        })
+    }
+
+    /// Checks that a backup of a mnemonic matches a mnemonic in the kv-store, without exporting anything.
+    /// The backup is read like in [Cmd::Import] and compared against every stored mnemonic in constant time.
+    /// Returns the key of the matching mnemonic.
+    async fn handle_verify(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<String> {
+        info!("Verifying mnemonic backup");
+        let (entropy, language) = self.read_backup(cfg)?;
+
+        // compare against all mnemonics, so that the time taken does not reveal which one matches
+        let mut matching = None;
+        for key in self.seed_key_iter().await? {
+            let record = self.get::<MnemonicRecord>(&key).await?;
+            let is_match: bool = entropy.0.as_slice().ct_eq(&record.entropy.0).into();
+            if is_match && matching.is_none() {
+                matching = Some((key, record.language));
+            }
+        }
+
+        let (key, stored) = matching.ok_or(NoMatchingMnemonic)?;
+        if stored != language {
+            return Err(BackupLanguage {
+                mnemonic: key,
+                backup: language,
+                stored,
+            });
+        }
+
+        match rotated_mnemonic_index(&key) {
+            Some(index) => info!(
+                "Backup matches rotated out mnemonic '{}' of index {}",
+                key, index
+            ),
+            None => info!("Backup matches current mnemonic '{}'", key),
+        }
+        Ok(key)
     }
 
     /// Rotates out existing mnemonic for new one in the kv-store and exports it to a file
+    /// The new mnemonic is in the language of `cfg`, or in the language of the current one if none is set.
+    /// The new mnemonic is protected by a passphrase if the current one is, or if `cfg` uses a passphrase.
     /// If an exported file already exists in the default path, an error is produced
-    async fn handle_rotate(&self) -> InnerMnemonicResult<()> {
+    async fn handle_rotate(&self, cfg: &MnemonicConfig) -> InnerMnemonicResult<RotatedMnemonic> {
         info!("Rotating mnemonic");
+        let current_record: MnemonicRecord = self.get(MNEMONIC_KEY).await?;
+
+        let passphrase = match cfg.use_passphrase || current_record.passphrase_fingerprint.is_some()
+        {
+            true => Some(self.read_new_passphrase(cfg).await?),
+            false => None,
+        };
+        self.rotate_record(current_record, cfg, passphrase).await
+    }
+
+    /// Rotates out the current mnemonic of a running daemon for a new one.
+    /// The new phrase is only exported sealed, see [MnemonicConfig::check_online_export],
+    /// and it is protected by the passphrase verified at startup if the current mnemonic is.
+    /// Seeds are not looked up during the rotation; lookups that started before it finish with the old seed.
+    pub(crate) async fn rotate_online(
+        &self,
+        cfg: &MnemonicConfig,
+    ) -> InnerMnemonicResult<RotatedMnemonic> {
+        cfg.check_online_export()?;
+
+        let _guard = self.write_mnemonics().await;
+        info!("Rotating mnemonic of running daemon");
+        let current_record: MnemonicRecord = self.get(MNEMONIC_KEY).await?;
+
+        let passphrase = match current_record.passphrase_fingerprint {
+            Some(_) => Some(self.mnemonic_passphrase().ok_or(MissingPassphrase)?.clone()),
+            None => None,
+        };
+
+        // the write lock is held, so the current mnemonic is rotated out to the next key
+        let (rotated_out, _) = self.get_next_key().await?;
+        let cfg = MnemonicConfig {
+            export_path: Some(cfg.rotation_export_path(self.io(), &rotated_out)),
+            ..cfg.clone()
+        };
+        self.rotate_record(current_record, &cfg, passphrase).await
+    }
+
+    /// Rotates out `current_record` for a new mnemonic, which is exported as configured by `cfg`.
+    /// The new mnemonic is in the language of `cfg`, or in the language of the current one if none is set,
+    /// and it is protected by `passphrase`, if any.
+    /// takes ownership of current_record to delegate zeroization.
+    async fn rotate_record(
+        &self,
+        current_record: MnemonicRecord,
+        cfg: &MnemonicConfig,
+        passphrase: Option<Password>,
+    ) -> InnerMnemonicResult<RotatedMnemonic> {
         // create a new entropy
-        let new_entropy = bip39_new_w24();
+        let mut new_record = cfg.new_record(cfg.language.unwrap_or(current_record.language))?;
+        if let Some(passphrase) = &passphrase {
+            protect_record(&mut new_record, passphrase)?;
+        }
 
         // export right away in case of intermediate failures
-        self.io().entropy_to_file(new_entropy.clone())?;
-
-        let current_entropy: Entropy = self
-            .kv()
-            .get(MNEMONIC_KEY)
-            .await?
-            .try_into()
-            .map_err(KvError::GetErr)?;
+        cfg.export(self.io(), new_record.entropy.clone(), new_record.language)?;
 
-        self.handle_insert(current_entropy.clone()).await?;
+        // the current mnemonic moves to a new index
+        let index = self.handle_insert(current_record.clone()).await?;
+        self.index_record(current_record, passphrase.as_ref(), index)
+            .await?;
 
         info!("reserving mnemonic");
 
-        self.kv().delete(MNEMONIC_KEY).await.map_err(|err| {
-            error!("could not delete mnemonic being rotated out: {:?}", err);
-            KvErr(err)
-        })?;
+        self.delete::<MnemonicRecord>(MNEMONIC_KEY)
+            .await
+            .map_err(|err| {
+                error!("could not delete mnemonic being rotated out: {:?}", err);
+                KvErr(err)
+            })?;
 
         let reservation = self
-            .kv()
-            .reserve_key(MNEMONIC_KEY.to_owned())
+            .reserve::<MnemonicRecord>(MNEMONIC_KEY)
             .await
             .map_err(|err| {
                 error!("Cannot reserve mnemonic key: {:?}", err);
                 KvErr(err)
             })?;
 
-        self.put_entropy(reservation, new_entropy).await?;
+        self.put_record(reservation, new_record.clone()).await?;
+        let fingerprint = self
+            .index_record(new_record, passphrase.as_ref(), None)
+            .await?;
 
-        Ok(())
+        Ok(RotatedMnemonic {
+            rotated_out: mnemonic_key(index),
+            fingerprint,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{io::Read, path::PathBuf};
+    use std::{
+        io::Read,
+        path::{Path, PathBuf},
+    };
     use testdir::testdir;
 
     use crate::{
@@ -348,6 +1112,11 @@ mod tests {
     };
 
     use super::*;
+    use crate::mnemonic::bip39_bindings::{
+        tests::{bip39_new_w24, bip39_to_phrase},
+        WORD_COUNTS,
+    };
+    use crate::mnemonic::KeyScheme;
     use tracing_test::traced_test;
 
     // create a service
@@ -356,6 +1125,21 @@ mod tests {
         KvManager::new(testdir, get_test_password()).unwrap()
     }
 
+    fn new_record() -> MnemonicRecord {
+        MnemonicRecord::new(bip39_new_w24(), MnemonicLanguage::English)
+    }
+
+    fn read_export(kv: &KvManager) -> String {
+        let path = kv.io().export_path();
+        let mut phrase = String::new();
+        std::fs::File::open(path)
+            .unwrap()
+            .read_to_string(&mut phrase)
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+        phrase
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn test_create() {
@@ -363,16 +1147,85 @@ mod tests {
         // create a service
         let kv = get_kv_manager(testdir);
         // first attempt should succeed
-        assert!(kv.handle_create().await.is_ok());
+        assert!(kv.handle_create(&MnemonicConfig::default()).await.is_ok());
         // second attempt should fail
         assert!(matches!(
-            kv.handle_create().await,
+            kv.handle_create(&MnemonicConfig::default()).await,
             Err(InnerMnemonicError::KvErr(KvError::ReserveErr(
                 InnerKvError::LogicalErr(_)
             )))
         ));
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn test_auto() {
+        let testdir = testdir!();
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = MnemonicConfig::default();
+
+        // without an import file, a mnemonic is created and exported to the import file
+        assert!(kv.handle_auto(&cfg).await.unwrap().is_none());
+        assert!(kv.io().import_path().exists());
+        assert!(kv.io().check_if_not_exported().is_ok());
+        let seed = format!("{:?}", kv.seed().await.unwrap());
+
+        // an existing mnemonic is used as is
+        assert!(kv.handle_auto(&cfg).await.is_ok());
+        assert_eq!(kv.seed_key_iter().await.unwrap(), vec![MNEMONIC_KEY]);
+        assert_eq!(format!("{:?}", kv.seed().await.unwrap()), seed);
+
+        // the import file recovers the mnemonic in a new kv store
+        let other_kv = get_kv_manager(testdir.join("other_kv"));
+        std::fs::copy(kv.io().import_path(), other_kv.io().import_path()).unwrap();
+        assert!(other_kv.handle_auto(&cfg).await.is_ok());
+        assert_eq!(format!("{:?}", other_kv.seed().await.unwrap()), seed);
+
+        // so does a configured import path
+        let third_kv = get_kv_manager(testdir.join("third_kv"));
+        let import_cfg = MnemonicConfig {
+            import_path: Some(kv.io().import_path().clone()),
+            ..MnemonicConfig::default()
+        };
+        assert!(third_kv.handle_auto(&import_cfg).await.is_ok());
+        assert_eq!(format!("{:?}", third_kv.seed().await.unwrap()), seed);
+        assert!(!third_kv.io().import_path().exists());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_auto_errors() {
+        let testdir = testdir!();
+
+        // a malformed import file is not replaced by a new mnemonic
+        let kv = get_kv_manager(testdir.join("kv"));
+        std::fs::write(kv.io().import_path(), "not a mnemonic").unwrap();
+        assert!(kv.handle_auto(&MnemonicConfig::default()).await.is_err());
+        assert!(!kv.exists::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap());
+
+        // a plaintext export in the tofnd root still blocks the daemon
+        let kv = get_kv_manager(testdir.join("other_kv"));
+        let cfg = MnemonicConfig {
+            export_path: Some(kv.io().export_path().clone()),
+            ..MnemonicConfig::default()
+        };
+        kv.handle_create(&cfg).await.unwrap();
+        assert!(matches!(
+            kv.handle_auto(&MnemonicConfig::default()).await,
+            Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
+        ));
+    }
+
+    #[test]
+    fn test_cmd_names() {
+        for name in [
+            "existing", "auto", "create", "import", "export", "rotate", "list", "prune", "verify",
+        ] {
+            assert_eq!(Cmd::from_string(name).unwrap().name(), name);
+        }
+        assert!(Cmd::from_string("unknown").is_err());
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn test_insert() {
@@ -380,9 +1233,9 @@ mod tests {
         // create a service
         let kv = get_kv_manager(testdir.clone());
         // insert should succeed
-        assert!(kv.handle_insert(bip39_new_w24()).await.is_ok());
+        assert!(kv.handle_insert(new_record()).await.is_ok());
         // insert should succeed again
-        assert!(kv.handle_insert(bip39_new_w24()).await.is_ok());
+        assert!(kv.handle_insert(new_record()).await.is_ok());
     }
 
     #[traced_test]
@@ -393,7 +1246,7 @@ mod tests {
         let kv = get_kv_manager(testdir.clone());
         // handle existing should fail
         assert!(matches!(
-            kv.handle_existing().await,
+            kv.handle_existing(&MnemonicConfig::default()).await,
             Err(InnerMnemonicError::KvErr(KvError::ExistsErr(
                 InnerKvError::LogicalErr(_)
             )))
@@ -401,21 +1254,39 @@ mod tests {
         // mnemonic should not be exported
         assert!(kv.io().check_if_not_exported().is_ok());
         // create a new mnemonic
-        assert!(kv.handle_create().await.is_ok());
+        assert!(kv.handle_create(&MnemonicConfig::default()).await.is_ok());
         // mnemonic should now be exported
         assert!(kv.io().check_if_not_exported().is_err());
         // export should fail because create also exports
         assert!(matches!(
-            kv.handle_export().await,
+            kv.handle_export(&MnemonicConfig::default()).await,
             Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
         ));
         // handle existing should fail because export file exists
         assert!(matches!(
-            kv.handle_existing().await,
+            kv.handle_existing(&MnemonicConfig::default()).await,
             Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
         ));
     }
 
+    #[traced_test]
+    #[tokio::test]
+    async fn test_create_needs_export() {
+        let kv = get_kv_manager(testdir!());
+
+        // an existing export target is not overwritten, and no mnemonic is stored without a backup
+        std::fs::write(kv.io().export_path(), "previous backup").unwrap();
+        assert!(matches!(
+            kv.handle_create(&MnemonicConfig::default()).await,
+            Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
+        ));
+        assert!(!kv.exists::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap());
+        assert_eq!(
+            std::fs::read_to_string(kv.io().export_path()).unwrap(),
+            "previous backup"
+        );
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn test_existing() {
@@ -423,19 +1294,28 @@ mod tests {
         // create a service
         let kv = get_kv_manager(testdir.clone());
         // create a new mnemonic
-        assert!(kv.handle_create().await.is_ok());
+        assert!(kv.handle_create(&MnemonicConfig::default()).await.is_ok());
         // handle_existing should fail because export file exists
         assert!(matches!(
-            kv.handle_existing().await,
+            kv.handle_existing(&MnemonicConfig::default()).await,
             Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
         ));
         // export should fail because export file exists
         assert!(matches!(
-            kv.handle_export().await,
+            kv.handle_export(&MnemonicConfig::default()).await,
             Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
         ));
     }
 
+    #[test]
+    fn test_rotated_mnemonic_index() {
+        assert_eq!(rotated_mnemonic_index("mnemonic_1"), Some(1));
+        assert_eq!(rotated_mnemonic_index("mnemonic_42"), Some(42));
+        assert_eq!(rotated_mnemonic_index(MNEMONIC_KEY), None);
+        assert_eq!(rotated_mnemonic_index(MNEMONIC_COUNT_KEY), None);
+        assert_eq!(rotated_mnemonic_index("mnemonic_1a"), None);
+    }
+
     #[traced_test]
     #[tokio::test]
     async fn test_rotate() {
@@ -451,9 +1331,9 @@ mod tests {
 
         for i in 0..rotations {
             if i == 0 {
-                assert!(kv.handle_create().await.is_ok());
+                assert!(kv.handle_create(&MnemonicConfig::default()).await.is_ok());
             } else {
-                assert!(kv.handle_rotate().await.is_ok());
+                assert!(kv.handle_rotate(&MnemonicConfig::default()).await.is_ok());
             }
 
             assert!(kv.io().check_if_not_exported().is_err());
@@ -465,15 +1345,13 @@ mod tests {
 
             assert!(std::fs::remove_file(path).is_ok());
 
+            let (entropy, language) = bip39_from_phrase(Password(phrase)).unwrap();
             seeds.push(
-                bip39_seed(
-                    bip39_from_phrase(Password(phrase)).unwrap(),
-                    Password(MNEMONIC_PASSWORD.to_owned()),
-                )
-                .unwrap()
-                .as_bytes()
-                .try_into()
-                .unwrap(),
+                bip39_seed(entropy, language, Password(NO_PASSPHRASE.to_owned()))
+                    .unwrap()
+                    .as_bytes()
+                    .try_into()
+                    .unwrap(),
             );
         }
 
@@ -499,5 +1377,642 @@ mod tests {
                 key
             );
         }
+
+        // every mnemonic is indexed under its current key
+        for key in kv.seed_key_iter().await.unwrap() {
+            let fingerprint = mnemonic_fingerprint(&kv.get_seed(&key).await.unwrap()).unwrap();
+            assert_eq!(
+                kv.mnemonic_of_fingerprint(fingerprint).await.unwrap(),
+                Some(key)
+            );
+        }
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_import_languages() {
+        for language in MnemonicLanguage::ALL {
+            let kv = get_kv_manager(testdir!().join(format!("{:?}", language)));
+
+            let entropy = bip39_new_w24();
+            let phrase = bip39_to_phrase(entropy.clone(), language).unwrap();
+            kv.import_phrase(phrase.clone(), &MnemonicConfig::default())
+                .await
+                .unwrap();
+
+            let record = kv.get::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap();
+            assert_eq!(record.entropy.0, entropy.0);
+            // Chinese phrases that only use characters common to both wordlists are valid in both
+            if language != MnemonicLanguage::ChineseTraditional {
+                assert_eq!(record.language, language);
+            }
+
+            // export returns the imported phrase
+            kv.handle_export(&MnemonicConfig::default()).await.unwrap();
+            assert_eq!(read_export(&kv), phrase.0);
+
+            // the seed is derived from the phrase in its language
+            let expected_seed: SecretRecoveryKey =
+                bip39_seed(entropy, record.language, Password(NO_PASSPHRASE.to_owned()))
+                    .unwrap()
+                    .as_bytes()
+                    .try_into()
+                    .unwrap();
+            assert_eq!(
+                format!("{:?}", kv.seed().await.unwrap()),
+                format!("{:?}", expected_seed)
+            );
+        }
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_create_and_export_language() {
+        let kv = get_kv_manager(testdir!());
+        let japanese = MnemonicConfig {
+            language: Some(MnemonicLanguage::Japanese),
+            ..MnemonicConfig::default()
+        };
+        let french = MnemonicConfig {
+            language: Some(MnemonicLanguage::French),
+            ..MnemonicConfig::default()
+        };
+
+        kv.handle_create(&japanese).await.unwrap();
+        let phrase = read_export(&kv);
+        assert_eq!(
+            bip39_from_phrase(Password(phrase)).unwrap().1,
+            MnemonicLanguage::Japanese
+        );
+
+        // export in the stored language by default
+        kv.handle_export(&MnemonicConfig::default()).await.unwrap();
+        let phrase = read_export(&kv);
+        assert_eq!(
+            bip39_from_phrase(Password(phrase)).unwrap().1,
+            MnemonicLanguage::Japanese
+        );
+
+        // export in the configured language
+        kv.handle_export(&french).await.unwrap();
+        let phrase = read_export(&kv);
+        assert_eq!(
+            bip39_from_phrase(Password(phrase)).unwrap().1,
+            MnemonicLanguage::French
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_word_counts() {
+        for word_count in WORD_COUNTS {
+            let kv = get_kv_manager(testdir!().join(format!("{}", word_count)));
+            let cfg = MnemonicConfig {
+                word_count: Some(word_count),
+                ..MnemonicConfig::default()
+            };
+            kv.handle_create(&cfg).await.unwrap();
+
+            let phrase = read_export(&kv);
+            assert_eq!(phrase.split_whitespace().count(), word_count);
+
+            let record = kv.get::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap();
+            assert_eq!(record.word_count() as usize, word_count);
+            assert_eq!(record.entropy_len as usize, record.entropy.0.len());
+
+            // the seed is derived from the exported phrase
+            let (entropy, language) = bip39_from_phrase(Password(phrase)).unwrap();
+            let expected_seed: SecretRecoveryKey =
+                bip39_seed(entropy, language, Password(NO_PASSPHRASE.to_owned()))
+                    .unwrap()
+                    .as_bytes()
+                    .try_into()
+                    .unwrap();
+            assert_eq!(
+                format!("{:?}", kv.seed().await.unwrap()),
+                format!("{:?}", expected_seed)
+            );
+        }
+
+        let cfg = MnemonicConfig {
+            word_count: Some(13),
+            ..MnemonicConfig::default()
+        };
+        assert!(get_kv_manager(testdir!())
+            .handle_create(&cfg)
+            .await
+            .is_err());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_import_12_words() {
+        let kv = get_kv_manager(testdir!());
+        let phrase = Password("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_owned());
+        kv.import_phrase(phrase.clone(), &MnemonicConfig::default())
+            .await
+            .unwrap();
+
+        let record = kv.get::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap();
+        assert_eq!(record.entropy_len, 16);
+        assert_eq!(record.word_count(), 12);
+
+        let (entropy, language) = bip39_from_phrase(phrase).unwrap();
+        let expected_seed: SecretRecoveryKey =
+            bip39_seed(entropy, language, Password(NO_PASSPHRASE.to_owned()))
+                .unwrap()
+                .as_bytes()
+                .try_into()
+                .unwrap();
+        assert_eq!(
+            format!("{:?}", kv.seed().await.unwrap()),
+            format!("{:?}", expected_seed)
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_truncated_entropy() {
+        let kv = get_kv_manager(testdir!());
+        let mut record = new_record();
+        record.entropy.0.truncate(31);
+        kv.handle_insert(record).await.unwrap();
+
+        assert!(matches!(
+            kv.seed().await,
+            Err(InnerMnemonicError::Bip39Error(EntropyLength {
+                expected: 32,
+                found: 31
+            }))
+        ));
+    }
+
+    // config that protects mnemonics with the passphrase written to a file in `dir`
+    fn passphrase_config(dir: &Path, passphrase: &str) -> MnemonicConfig {
+        let path = dir.join(format!("passphrase_{}", passphrase));
+        std::fs::write(&path, format!("{}\n", passphrase)).unwrap();
+        MnemonicConfig {
+            use_passphrase: true,
+            passphrase_file: Some(path),
+            ..MnemonicConfig::default()
+        }
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_passphrase() {
+        let testdir = testdir!();
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = passphrase_config(&testdir, "correct horse");
+        kv.handle_create(&cfg).await.unwrap();
+        let phrase = read_export(&kv);
+
+        let record = kv.get::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap();
+        assert!(record.passphrase_fingerprint.is_some());
+
+        // the seed is not available until the passphrase is verified
+        assert!(matches!(kv.seed().await, Err(MissingPassphrase)));
+
+        // a wrong passphrase is rejected at startup
+        let wrong_cfg = passphrase_config(&testdir, "wrong horse");
+        assert!(matches!(
+            kv.clone().handle_mnemonic(&Cmd::Existing, &wrong_cfg).await,
+            Err(ExistingErr(WrongPassphrase))
+        ));
+
+        // a wrong passphrase does not recover the seed
+        let mut wrong_kv = kv.clone();
+        wrong_kv.set_mnemonic_passphrase(Password("wrong horse".to_owned()));
+        assert!(matches!(wrong_kv.seed().await, Err(WrongPassphrase)));
+
+        // the right passphrase recovers the seed of the phrase under the passphrase
+        let kv = kv.handle_mnemonic(&Cmd::Existing, &cfg).await.unwrap();
+        let (entropy, language) = bip39_from_phrase(Password(phrase)).unwrap();
+        let expected_seed: SecretRecoveryKey = bip39_seed(
+            entropy.clone(),
+            language,
+            Password("correct horse".to_owned()),
+        )
+        .unwrap()
+        .as_bytes()
+        .try_into()
+        .unwrap();
+        let seed = kv.seed().await.unwrap();
+        assert_eq!(format!("{:?}", seed), format!("{:?}", expected_seed));
+
+        // the phrase alone does not recover the seed
+        let unprotected_seed: SecretRecoveryKey =
+            bip39_seed(entropy, language, Password(NO_PASSPHRASE.to_owned()))
+                .unwrap()
+                .as_bytes()
+                .try_into()
+                .unwrap();
+        assert_ne!(format!("{:?}", seed), format!("{:?}", unprotected_seed));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_passphrase_rotate_and_import() {
+        let testdir = testdir!();
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = passphrase_config(&testdir, "correct horse");
+        kv.handle_create(&cfg).await.unwrap();
+        std::fs::remove_file(kv.io().export_path()).unwrap();
+
+        // rotating keeps the passphrase and requires the right one
+        let wrong_cfg = passphrase_config(&testdir, "wrong horse");
+        assert!(matches!(
+            kv.handle_rotate(&wrong_cfg).await,
+            Err(WrongPassphrase)
+        ));
+        let rotate_cfg = MnemonicConfig {
+            use_passphrase: false,
+            ..cfg.clone()
+        };
+        kv.handle_rotate(&rotate_cfg).await.unwrap();
+        std::fs::remove_file(kv.io().export_path()).unwrap();
+
+        // importing with a passphrase requires the passphrase of the other mnemonics
+        let phrase = bip39_to_phrase(bip39_new_w24(), MnemonicLanguage::English).unwrap();
+        assert!(matches!(
+            kv.import_phrase(phrase.clone(), &wrong_cfg).await,
+            Err(WrongPassphrase)
+        ));
+        kv.import_phrase(phrase.clone(), &cfg).await.unwrap();
+        // mnemonics imported without a passphrase use none
+        kv.import_phrase(phrase, &MnemonicConfig::default())
+            .await
+            .unwrap();
+
+        let protected = kv.protected_keys().await.unwrap();
+        assert_eq!(protected.len(), 3);
+        assert_eq!(kv.seed_key_iter().await.unwrap().len(), 4);
+
+        let kv = kv.handle_mnemonic(&Cmd::Existing, &cfg).await.unwrap();
+        for key in kv.seed_key_iter().await.unwrap() {
+            assert!(kv.get_seed(&key).await.is_ok());
+        }
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_shares() {
+        let testdir = testdir!();
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = MnemonicConfig {
+            language: Some(MnemonicLanguage::French),
+            ..MnemonicConfig::default()
+        };
+        kv.handle_create(&cfg).await.unwrap();
+        std::fs::remove_file(kv.io().export_path()).unwrap();
+
+        let share_cfg = MnemonicConfig {
+            shares: Some(ShareScheme {
+                threshold: 2,
+                count: 3,
+            }),
+            ..MnemonicConfig::default()
+        };
+        kv.handle_export(&share_cfg).await.unwrap();
+        assert!(!kv.io().export_path().exists());
+        // share files block the daemon until they are removed
+        assert!(kv.handle_existing(&cfg).await.is_err());
+
+        // any 2 shares recover the mnemonic in another kv store
+        let other_kv = get_kv_manager(testdir.join("other_kv"));
+        let import_cfg = MnemonicConfig {
+            share_files: vec![kv.io().share_path(2), kv.io().share_path(3)],
+            ..MnemonicConfig::default()
+        };
+        other_kv.handle_import(&import_cfg).await.unwrap();
+
+        let record = other_kv.get::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap();
+        assert_eq!(record.language, MnemonicLanguage::French);
+        assert_eq!(
+            format!("{:?}", other_kv.seed().await.unwrap()),
+            format!("{:?}", kv.seed().await.unwrap())
+        );
+
+        // importing into a kv store with a mnemonic adds it to the rotation history
+        kv.handle_import(&import_cfg).await.unwrap();
+        let keys = kv.seed_key_iter().await.unwrap();
+        assert_eq!(
+            keys,
+            vec![MNEMONIC_KEY.to_owned(), format!("{}_1", MNEMONIC_KEY)]
+        );
+        assert_eq!(
+            format!("{:?}", kv.get_seed(&keys[1]).await.unwrap()),
+            format!("{:?}", kv.seed().await.unwrap())
+        );
+
+        // a single share is not enough
+        let import_cfg = MnemonicConfig {
+            share_files: vec![kv.io().share_path(1)],
+            ..MnemonicConfig::default()
+        };
+        assert!(get_kv_manager(testdir.join("third_kv"))
+            .handle_import(&import_cfg)
+            .await
+            .is_err());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_rotate_online() {
+        let testdir = testdir!();
+        let export_passphrase = testdir.join("export_passphrase");
+        std::fs::write(&export_passphrase, "export passphrase\n").unwrap();
+        let online_cfg = MnemonicConfig {
+            seal_export: true,
+            export_passphrase_file: Some(export_passphrase),
+            ..MnemonicConfig::default()
+        };
+
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = passphrase_config(&testdir, "correct horse");
+        kv.handle_create(&cfg).await.unwrap();
+        read_export(&kv);
+
+        // a running daemon never exports the phrase in plaintext
+        assert!(matches!(
+            kv.rotate_online(&MnemonicConfig::default()).await,
+            Err(InsecureExport)
+        ));
+
+        // the passphrase is needed to protect the new mnemonic
+        assert!(matches!(
+            kv.rotate_online(&online_cfg).await,
+            Err(MissingPassphrase)
+        ));
+        let export_path = online_cfg.rotation_export_path(kv.io(), "mnemonic_1");
+        assert_eq!(
+            export_path,
+            testdir.join("kv").join("export.sealed.mnemonic_1")
+        );
+        assert!(!export_path.exists());
+
+        // the new mnemonic is protected by the passphrase verified at startup
+        let kv = kv.handle_mnemonic(&Cmd::Existing, &cfg).await.unwrap();
+        let old_seed = kv.seed().await.unwrap();
+        let rotated = kv.rotate_online(&online_cfg).await.unwrap();
+        assert_eq!(rotated.rotated_out, "mnemonic_1");
+        assert!(export_path.exists());
+        assert!(!kv.io().sealed_export_path().exists());
+
+        let record = kv.get::<MnemonicRecord>(MNEMONIC_KEY).await.unwrap();
+        assert!(record.passphrase_fingerprint.is_some());
+        let seed = kv.seed().await.unwrap();
+        assert_eq!(mnemonic_fingerprint(&seed).unwrap(), rotated.fingerprint);
+        assert_eq!(
+            format!("{:?}", kv.get_seed("mnemonic_1").await.unwrap()),
+            format!("{:?}", old_seed)
+        );
+
+        // the next rotation is exported to its own file
+        let rotated = kv.rotate_online(&online_cfg).await.unwrap();
+        assert_eq!(rotated.rotated_out, "mnemonic_2");
+        assert!(export_path.exists());
+        assert!(online_cfg
+            .rotation_export_path(kv.io(), "mnemonic_2")
+            .exists());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_sealed_export() {
+        let testdir = testdir!();
+        let export_passphrase = testdir.join("export_passphrase");
+        std::fs::write(&export_passphrase, "export passphrase\n").unwrap();
+
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = MnemonicConfig {
+            seal_export: true,
+            export_passphrase_file: Some(export_passphrase.clone()),
+            ..MnemonicConfig::default()
+        };
+        kv.handle_create(&cfg).await.unwrap();
+        assert!(!kv.io().export_path().exists());
+        assert!(kv.io().sealed_export_path().exists());
+
+        // a sealed export does not block the daemon
+        assert!(kv
+            .handle_existing(&MnemonicConfig::default())
+            .await
+            .unwrap()
+            .is_none());
+
+        // the sealed file recovers the mnemonic in another kv store
+        let other_kv = get_kv_manager(testdir.join("other_kv"));
+        let import_cfg = MnemonicConfig {
+            import_path: Some(kv.io().sealed_export_path().clone()),
+            export_passphrase_file: Some(export_passphrase),
+            ..MnemonicConfig::default()
+        };
+        other_kv.handle_import(&import_cfg).await.unwrap();
+        assert_eq!(
+            format!("{:?}", other_kv.seed().await.unwrap()),
+            format!("{:?}", kv.seed().await.unwrap())
+        );
+
+        // not with a wrong export passphrase
+        let wrong_passphrase = testdir.join("wrong_passphrase");
+        std::fs::write(&wrong_passphrase, "wrong passphrase").unwrap();
+        let import_cfg = MnemonicConfig {
+            export_passphrase_file: Some(wrong_passphrase),
+            ..import_cfg
+        };
+        assert!(get_kv_manager(testdir.join("third_kv"))
+            .handle_import(&import_cfg)
+            .await
+            .is_err());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_export_import_paths() {
+        let testdir = testdir!();
+        let path = testdir.join("backup");
+
+        let kv = get_kv_manager(testdir.join("kv"));
+        let cfg = MnemonicConfig {
+            export_path: Some(path.clone()),
+            ..MnemonicConfig::default()
+        };
+        kv.handle_create(&cfg).await.unwrap();
+        assert!(path.exists());
+
+        // a file outside the tofnd root does not block the daemon
+        assert!(!kv.io().export_path().exists());
+        assert!(kv
+            .handle_existing(&MnemonicConfig::default())
+            .await
+            .unwrap()
+            .is_none());
+
+        // the export file is not overwritten
+        assert!(matches!(
+            kv.handle_export(&cfg).await,
+            Err(InnerMnemonicError::FileIoErr(FileIoError::Exists(_)))
+        ));
+
+        // the file recovers the mnemonic in another kv store
+        let other_kv = get_kv_manager(testdir.join("other_kv"));
+        let import_cfg = MnemonicConfig {
+            import_path: Some(path),
+            ..MnemonicConfig::default()
+        };
+        other_kv.handle_import(&import_cfg).await.unwrap();
+        assert_eq!(
+            format!("{:?}", other_kv.seed().await.unwrap()),
+            format!("{:?}", kv.seed().await.unwrap())
+        );
+
+        // a missing file fails the import
+        let import_cfg = MnemonicConfig {
+            import_path: Some(testdir.join("missing")),
+            ..MnemonicConfig::default()
+        };
+        assert!(get_kv_manager(testdir.join("third_kv"))
+            .handle_import(&import_cfg)
+            .await
+            .is_err());
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_list_prune() {
+        let kv = get_kv_manager(testdir!());
+        let cfg = MnemonicConfig::default();
+
+        // 'mnemonic_1' and 'mnemonic_2' are rotated out
+        kv.handle_create(&cfg).await.unwrap();
+        let first_phrase = read_export(&kv);
+        for _ in 0..2 {
+            kv.handle_rotate(&cfg).await.unwrap();
+            read_export(&kv);
+        }
+
+        // a key depends on 'mnemonic_1'
+        let first_seed = kv.get_seed("mnemonic_1").await.unwrap();
+        let fingerprint = mnemonic_fingerprint(&first_seed).unwrap();
+        kv.record_key_origin(KeyScheme::Gg20, "key", fingerprint)
+            .await
+            .unwrap();
+        assert_eq!(
+            kv.keys_of_mnemonic(fingerprint).await.unwrap(),
+            vec!["gg20/key"]
+        );
+
+        assert!(kv.handle_list(&cfg).await.is_ok());
+        let index_cfg = |index| MnemonicConfig {
+            index: Some(index),
+            ..MnemonicConfig::default()
+        };
+        assert!(kv.handle_list(&index_cfg(1)).await.is_ok());
+        assert!(kv.handle_list(&index_cfg(5)).await.is_err());
+
+        // a historic mnemonic can be exported
+        kv.handle_export(&index_cfg(1)).await.unwrap();
+        assert_eq!(read_export(&kv), first_phrase);
+
+        assert!(matches!(
+            kv.handle_prune(&cfg).await,
+            Err(InnerMnemonicError::MissingIndex)
+        ));
+        assert!(matches!(
+            kv.handle_prune(&index_cfg(1)).await,
+            Err(InnerMnemonicError::MnemonicInUse { keys, .. }) if keys == vec!["gg20/key"]
+        ));
+
+        // no key depends on 'mnemonic_2'
+        let pruned_seed = kv.get_seed("mnemonic_2").await.unwrap();
+        let pruned_fingerprint = mnemonic_fingerprint(&pruned_seed).unwrap();
+        kv.handle_prune(&index_cfg(2)).await.unwrap();
+        assert_eq!(
+            kv.mnemonic_of_fingerprint(pruned_fingerprint)
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            kv.seed_key_iter().await.unwrap(),
+            vec![MNEMONIC_KEY.to_owned(), "mnemonic_1".to_owned()]
+        );
+        assert!(kv.handle_prune(&index_cfg(2)).await.is_err());
+
+        // keys of imported mnemonics are not tracked; the imported mnemonic is 'mnemonic_3'
+        kv.import_phrase(Password(first_phrase.clone()), &cfg)
+            .await
+            .unwrap();
+        assert_eq!(
+            kv.mnemonic_of_fingerprint(fingerprint).await.unwrap(),
+            Some("mnemonic_3".to_owned())
+        );
+        assert!(matches!(
+            kv.handle_prune(&index_cfg(3)).await,
+            Err(InnerMnemonicError::UntrackedMnemonic(_))
+        ));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_verify() {
+        let testdir = testdir!();
+        let path = testdir.join("backup");
+        let kv = get_kv_manager(testdir.join("kv"));
+
+        kv.handle_create(&MnemonicConfig {
+            export_path: Some(path.clone()),
+            ..MnemonicConfig::default()
+        })
+        .await
+        .unwrap();
+
+        let verify_cfg = |path: &Path| MnemonicConfig {
+            import_path: Some(path.to_path_buf()),
+            ..MnemonicConfig::default()
+        };
+        assert_eq!(
+            kv.handle_verify(&verify_cfg(&path)).await.unwrap(),
+            MNEMONIC_KEY
+        );
+
+        // the backup matches the rotated out mnemonic; nothing is written to the export path
+        kv.handle_rotate(&MnemonicConfig::default()).await.unwrap();
+        read_export(&kv);
+        assert_eq!(
+            kv.handle_verify(&verify_cfg(&path)).await.unwrap(),
+            "mnemonic_1"
+        );
+        assert!(!kv.io().export_path().exists());
+
+        // the same entropy in another language gives another seed
+        let record = kv.get::<MnemonicRecord>("mnemonic_1").await.unwrap();
+        let other_path = testdir.join("other_language");
+        kv.io()
+            .entropy_to_file(
+                record.entropy,
+                MnemonicLanguage::Spanish,
+                ExportTarget::Path(&other_path),
+            )
+            .unwrap();
+        assert!(matches!(
+            kv.handle_verify(&verify_cfg(&other_path)).await,
+            Err(InnerMnemonicError::BackupLanguage { mnemonic, .. }) if mnemonic == "mnemonic_1"
+        ));
+
+        // an unknown mnemonic does not match
+        let unknown_path = testdir.join("unknown");
+        kv.io()
+            .entropy_to_file(
+                bip39_new_w24(),
+                MnemonicLanguage::English,
+                ExportTarget::Path(&unknown_path),
+            )
+            .unwrap();
+        assert!(matches!(
+            kv.handle_verify(&verify_cfg(&unknown_path)).await,
+            Err(InnerMnemonicError::NoMatchingMnemonic)
+        ));
     }
 }
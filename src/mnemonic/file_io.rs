@@ -1,28 +1,70 @@
 //! This module handles file IO.
 
-use std::{io::Write, path::PathBuf};
+use std::{
+    fs::OpenOptions,
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
 
 use tracing::info;
 
-use super::{bip39_bindings::bip39_from_entropy, results::file_io::FileIoError::Exists};
-use crate::gg20::types::Entropy;
+use super::{
+    bip39_bindings::{bip39_from_entropy, bip39_from_phrase, MnemonicLanguage},
+    results::file_io::FileIoError::{Exists, MalformedShare, ShareLanguages},
+    sealed::{is_sealed, seal, unseal, SEALED_MAGIC},
+    slip39_bindings::{slip39_combine, slip39_split, ShareScheme},
+};
+use crate::gg20::types::{Entropy, Password};
 
 /// name of export file
 const EXPORT_FILE: &str = "export";
 
+/// name of the file that [super::Cmd::Auto] imports a mnemonic from
+const IMPORT_FILE: &str = "import";
+
+/// name of sealed export file
+const SEALED_EXPORT_FILE: &str = "export.sealed";
+
+/// prefix of the names of exported share files; share i is written to `export_share_i`
+const EXPORT_SHARE_PREFIX: &str = "export_share_";
+
+/// prefix of the line of a share file that holds the language of the mnemonic
+const SHARE_LANGUAGE_PREFIX: &str = "bip39-language: ";
+
 use super::results::file_io::FileIoResult;
 
+/// Where an exported phrase is written
+#[derive(Clone, Copy, Debug)]
+pub(super) enum ExportTarget<'a> {
+    /// the default export file in the tofnd root
+    Default,
+    /// a file at the given path
+    Path(&'a Path),
+    /// the standard output; nothing is written to disk
+    Stdout,
+}
+
 /// FileIO wraps all IO functionality
 #[derive(Clone)]
 pub struct FileIo {
+    root: PathBuf,
     export_path: PathBuf,
+    sealed_export_path: PathBuf,
+    import_path: PathBuf,
 }
 
 impl FileIo {
     /// FileIO constructor
-    pub fn new(mut export_path: PathBuf) -> FileIo {
-        export_path.push(EXPORT_FILE);
-        FileIo { export_path }
+    pub fn new(root: PathBuf) -> FileIo {
+        let export_path = root.join(EXPORT_FILE);
+        let sealed_export_path = root.join(SEALED_EXPORT_FILE);
+        let import_path = root.join(IMPORT_FILE);
+        FileIo {
+            root,
+            export_path,
+            sealed_export_path,
+            import_path,
+        }
     }
 
     /// Get the path of export file
@@ -30,37 +72,234 @@ impl FileIo {
         &self.export_path
     }
 
-    /// Check if an exported file exists in the expected path
+    /// Get the path of sealed export file
+    pub fn sealed_export_path(&self) -> &PathBuf {
+        &self.sealed_export_path
+    }
+
+    /// Get the path of the default import file
+    pub fn import_path(&self) -> &PathBuf {
+        &self.import_path
+    }
+
+    /// Get the path of the file of share `index`; shares are indexed from 1
+    pub fn share_path(&self, index: usize) -> PathBuf {
+        self.root.join(format!("{}{}", EXPORT_SHARE_PREFIX, index))
+    }
+
+    /// Check if an exported file or share file exists in the expected path
     /// Succeeds if no exported file exists, returns an error otherwise.
+    /// A sealed export file is not a plaintext mnemonic, so it doesn't count.
     pub fn check_if_not_exported(&self) -> FileIoResult<()> {
         if std::path::Path::new(&self.export_path()).exists() {
             return Err(Exists(self.export_path().clone()));
         }
+        // the root directory is created along with the kv store; if it doesn't exist, nothing was exported
+        if let Ok(entries) = std::fs::read_dir(&self.root) {
+            for entry in entries {
+                let entry = entry?;
+                if entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(EXPORT_SHARE_PREFIX)
+                {
+                    return Err(Exists(entry.path()));
+                }
+            }
+        }
         Ok(())
     }
 
-    /// Creates a file that contains an entropy in it's human-readable form
-    pub(super) fn entropy_to_file(&self, entropy: Entropy) -> FileIoResult<()> {
+    /// Writes an entropy in it's human-readable form in `lang` to `target`
+    pub(super) fn entropy_to_file(
+        &self,
+        entropy: Entropy,
+        lang: MnemonicLanguage,
+        target: ExportTarget,
+    ) -> FileIoResult<()> {
         // delegate zeroization for entropy; no need to worry about mnemonic, it is cleaned automatically
-        let mnemonic = bip39_from_entropy(entropy)?;
+        let mnemonic = bip39_from_entropy(entropy, lang)?;
         let phrase = mnemonic.phrase();
 
-        // if there is an existing exported file raise an error
+        self.write_export(target, self.export_path(), phrase.as_bytes())
+    }
+
+    /// Writes an entropy in it's human-readable form in `lang` to `target`,
+    /// sealed with `passphrase`. See [super::sealed] for the format.
+    pub(super) fn entropy_to_sealed_file(
+        &self,
+        entropy: Entropy,
+        lang: MnemonicLanguage,
+        passphrase: &Password,
+        target: ExportTarget,
+    ) -> FileIoResult<()> {
+        // delegate zeroization for entropy; no need to worry about mnemonic, it is cleaned automatically
+        let mnemonic = bip39_from_entropy(entropy, lang)?;
+        let sealed = seal(Password(mnemonic.phrase().to_owned()), passphrase)?;
+
+        self.write_export(target, self.sealed_export_path(), &sealed)
+    }
+
+    /// Writes `contents` to `target`; `default_path` is the file of [ExportTarget::Default]
+    fn write_export(
+        &self,
+        target: ExportTarget,
+        default_path: &Path,
+        contents: &[u8],
+    ) -> FileIoResult<()> {
+        let path = match target {
+            ExportTarget::Default => default_path,
+            ExportTarget::Path(path) => path,
+            ExportTarget::Stdout => {
+                let mut stdout = std::io::stdout();
+                stdout.write_all(contents)?;
+                stdout.flush()?;
+                info!("Mnemonic written to stdout");
+                return Ok(());
+            }
+        };
+
+        write_new_file(path, contents)?;
+        info!("Mnemonic written in file {:?}", path);
+        Ok(())
+    }
+
+    /// Returns true if the file at `path` is a sealed export file
+    pub(super) fn is_sealed_file(&self, path: &Path) -> FileIoResult<bool> {
+        let mut magic = vec![0u8; SEALED_MAGIC.len()];
+        let mut file = std::fs::File::open(path)?;
+        match file.read_exact(&mut magic) {
+            Ok(()) => Ok(is_sealed(&magic)),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Reads the phrase in the file at `path`.
+    /// Returns the entropy of the phrase along with its language.
+    pub(super) fn entropy_from_file(
+        &self,
+        path: &Path,
+    ) -> FileIoResult<(Entropy, MnemonicLanguage)> {
+        let contents = Password(std::fs::read_to_string(path)?);
+        // tolerate line endings and repeated spaces in hand-written files
+        let phrase = Password(contents.0.split_whitespace().collect::<Vec<_>>().join(" "));
+        Ok(bip39_from_phrase(phrase)?)
+    }
+
+    /// Reads the sealed file at `path` and unseals it with `passphrase`.
+    /// Returns the entropy of the phrase along with its language.
+    pub(super) fn entropy_from_sealed_file(
+        &self,
+        path: &Path,
+        passphrase: &Password,
+    ) -> FileIoResult<(Entropy, MnemonicLanguage)> {
+        let sealed = std::fs::read(path)?;
+        let phrase = unseal(&sealed, passphrase)?;
+        Ok(bip39_from_phrase(phrase)?)
+    }
+
+    /// Splits an entropy into the SLIP-39 shares of `scheme` and writes each share to its own file.
+    /// Along with the share, each file holds `lang`, which the seed of the mnemonic depends on.
+    pub(super) fn entropy_to_share_files(
+        &self,
+        entropy: Entropy,
+        lang: MnemonicLanguage,
+        scheme: ShareScheme,
+    ) -> FileIoResult<()> {
+        // if there are existing exported files raise an error
         self.check_if_not_exported()?;
 
-        let mut file = std::fs::File::create(&self.export_path())?;
-        file.write_all(phrase.as_bytes())?;
-        file.sync_all()?;
+        let shares = slip39_split(entropy, scheme)?;
+        for (i, share) in shares.iter().enumerate() {
+            let path = self.share_path(i + 1);
+            let contents = Password(format!(
+                "{}\n{}{}\n",
+                share.0,
+                SHARE_LANGUAGE_PREFIX,
+                lang.code()
+            ));
+
+            write_new_file(&path, contents.0.as_bytes())?;
+        }
 
-        info!("Mnemonic written in file {:?}", &self.export_path());
+        info!(
+            "Mnemonic written in {} share files {:?}, {}-of-{}. Hand each file to a different custodian.",
+            shares.len(),
+            self.share_path(1),
+            scheme.threshold,
+            scheme.count,
+        );
         Ok(())
     }
+
+    /// Reads the share files at `paths` and combines the shares into an entropy.
+    /// Returns the entropy along with the language of its mnemonic.
+    pub(super) fn entropy_from_share_files(
+        &self,
+        paths: &[PathBuf],
+    ) -> FileIoResult<(Entropy, MnemonicLanguage)> {
+        let mut shares = Vec::with_capacity(paths.len());
+        let mut language = None;
+
+        for path in paths {
+            let (share, share_language) = read_share_file(path)?;
+            if *language.get_or_insert(share_language) != share_language {
+                return Err(ShareLanguages);
+            }
+            shares.push(share);
+        }
+
+        let entropy = slip39_combine(shares)?;
+        Ok((entropy, language.unwrap_or_default()))
+    }
+}
+
+/// Creates a file at `path` that only its owner can read and write, and writes `contents` to it.
+/// Existing files are never overwritten.
+fn write_new_file(path: &Path, contents: &[u8]) -> FileIoResult<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path).map_err(|err| match err.kind() {
+        ErrorKind::AlreadyExists => Exists(path.to_path_buf()),
+        _ => err.into(),
+    })?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Reads a share file that was written by [FileIo::entropy_to_share_files].
+/// Files without a language line are of English mnemonics.
+fn read_share_file(path: &Path) -> FileIoResult<(Password, MnemonicLanguage)> {
+    let contents = Password(std::fs::read_to_string(path)?);
+
+    let mut share = None;
+    let mut language = MnemonicLanguage::default();
+    for line in contents.0.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        match line.strip_prefix(SHARE_LANGUAGE_PREFIX) {
+            Some(code) => {
+                language = MnemonicLanguage::from_code(code.trim())
+                    .ok_or_else(|| MalformedShare(path.to_path_buf()))?;
+            }
+            None if share.is_none() => share = Some(Password(line.to_owned())),
+            None => return Err(MalformedShare(path.to_path_buf())),
+        }
+    }
+
+    Ok((
+        share.ok_or_else(|| MalformedShare(path.to_path_buf()))?,
+        language,
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::mnemonic::bip39_bindings::{bip39_new_w24, tests::bip39_to_phrase};
+    use crate::mnemonic::bip39_bindings::tests::{bip39_new_w24, bip39_to_phrase};
     use std::io::Read;
     use testdir::testdir;
     use tracing_test::traced_test;
@@ -72,8 +311,13 @@ mod tests {
 
         let io = FileIo::new(testdir!());
         let filepath = io.export_path();
-        io.entropy_to_file(entropy.clone()).unwrap();
-        let expected_content = bip39_to_phrase(entropy).unwrap();
+        io.entropy_to_file(
+            entropy.clone(),
+            MnemonicLanguage::Japanese,
+            ExportTarget::Default,
+        )
+        .unwrap();
+        let expected_content = bip39_to_phrase(entropy, MnemonicLanguage::Japanese).unwrap();
 
         let mut file = std::fs::File::open(filepath).unwrap();
         let mut file_phrase = String::new();
@@ -82,4 +326,134 @@ mod tests {
 
         assert_eq!(file_content, expected_content.0);
     }
+
+    #[traced_test]
+    #[test]
+    fn test_export_path() {
+        let entropy = bip39_new_w24();
+        let testdir = testdir!();
+        let path = testdir.join("mnemonic.txt");
+
+        let io = FileIo::new(testdir.join("root"));
+        let target = ExportTarget::Path(&path);
+        io.entropy_to_file(entropy.clone(), MnemonicLanguage::French, target)
+            .unwrap();
+        assert!(!io.export_path().exists());
+
+        // only the owner can access the file
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // existing files are not overwritten
+        assert!(matches!(
+            io.entropy_to_file(entropy.clone(), MnemonicLanguage::English, target),
+            Err(Exists(_))
+        ));
+
+        assert!(!io.is_sealed_file(&path).unwrap());
+        let (read, lang) = io.entropy_from_file(&path).unwrap();
+        assert_eq!(read.0, entropy.0);
+        assert_eq!(lang, MnemonicLanguage::French);
+
+        // line endings and extra spaces of hand-written files are ignored
+        let phrase = std::fs::read_to_string(&path).unwrap();
+        let edited = testdir.join("edited.txt");
+        std::fs::write(&edited, format!("  {}\r\n", phrase.replace(' ', "   "))).unwrap();
+        let (read, _) = io.entropy_from_file(&edited).unwrap();
+        assert_eq!(read.0, entropy.0);
+
+        // short files are not sealed
+        std::fs::write(&edited, "").unwrap();
+        assert!(!io.is_sealed_file(&edited).unwrap());
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_share_files() {
+        let entropy = bip39_new_w24();
+        let scheme = ShareScheme {
+            threshold: 2,
+            count: 3,
+        };
+
+        let io = FileIo::new(testdir!());
+        assert!(io.check_if_not_exported().is_ok());
+        io.entropy_to_share_files(entropy.clone(), MnemonicLanguage::Korean, scheme)
+            .unwrap();
+        assert!(!io.export_path().exists());
+
+        // share files block the daemon like an export file
+        assert!(matches!(io.check_if_not_exported(), Err(Exists(_))));
+        assert!(io
+            .entropy_to_share_files(entropy.clone(), MnemonicLanguage::Korean, scheme)
+            .is_err());
+
+        let paths = vec![io.share_path(3), io.share_path(1)];
+        let (combined, lang) = io.entropy_from_share_files(&paths).unwrap();
+        assert_eq!(combined.0, entropy.0);
+        assert_eq!(lang, MnemonicLanguage::Korean);
+
+        // a single share is not enough
+        assert!(io.entropy_from_share_files(&paths[..1]).is_err());
+
+        // shares of mnemonics in different languages are rejected
+        let contents = std::fs::read_to_string(io.share_path(2)).unwrap();
+        std::fs::write(
+            io.share_path(2),
+            contents.replace(
+                &format!("{}ko", SHARE_LANGUAGE_PREFIX),
+                &format!("{}ja", SHARE_LANGUAGE_PREFIX),
+            ),
+        )
+        .unwrap();
+        assert!(matches!(
+            io.entropy_from_share_files(&[io.share_path(1), io.share_path(2)]),
+            Err(ShareLanguages)
+        ));
+    }
+
+    #[traced_test]
+    #[test]
+    fn test_sealed_file() {
+        let entropy = bip39_new_w24();
+        let passphrase = Password("export passphrase".to_owned());
+
+        let io = FileIo::new(testdir!());
+        io.entropy_to_sealed_file(
+            entropy.clone(),
+            MnemonicLanguage::Spanish,
+            &passphrase,
+            ExportTarget::Default,
+        )
+        .unwrap();
+        assert!(!io.export_path().exists());
+
+        // a sealed file does not block the daemon, but is not overwritten
+        assert!(io.check_if_not_exported().is_ok());
+        assert!(matches!(
+            io.entropy_to_sealed_file(
+                entropy.clone(),
+                MnemonicLanguage::Spanish,
+                &passphrase,
+                ExportTarget::Default
+            ),
+            Err(Exists(_))
+        ));
+
+        assert!(io.is_sealed_file(io.sealed_export_path()).unwrap());
+        let (unsealed, lang) = io
+            .entropy_from_sealed_file(io.sealed_export_path(), &passphrase)
+            .unwrap();
+        assert_eq!(unsealed.0, entropy.0);
+        assert_eq!(lang, MnemonicLanguage::Spanish);
+
+        let wrong_passphrase = Password("wrong passphrase".to_owned());
+        assert!(io
+            .entropy_from_sealed_file(io.sealed_export_path(), &wrong_passphrase)
+            .is_err());
+    }
 }
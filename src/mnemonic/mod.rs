@@ -1,15 +1,37 @@
-//! This module handles mnemonic-related commands. A kv-store is used to insert and retrieve an [crate::gg20::Entropy].
+//! This module handles mnemonic-related commands. A kv-store is used to insert and retrieve a [MnemonicRecord], the entropy of a mnemonic along with its language.
 //!
 //! Currently, the API supports the following [Cmd] commands:
 //!     [Cmd::Existing]: Starts the gRPC daemon existing mnemonic; Fails if mnemonic does not exist.
+//!     [Cmd::Auto]: Imports a mnemonic from the import file if it exists, otherwise creates one and exports it to the import file, if no mnemonic exists; then starts the gRPC daemon like [Cmd::Existing].
 //!     [Cmd::Create]: Creates a new mnemonic, inserts it in the kv-store, exports it to a file and exits; Fails if a mnemonic exists.
 //!     [Cmd::Import]: Prompts user to give a new mnemonic, inserts it in the kv-store and exits; Fails if a mnemonic exists or if the provided string is not a valid bip39 mnemonic.
 //!     [Cmd::Export]: Writes the existing mnemonic to a file and exits; Succeeds when there is an existing mnemonic, fails otherwise.
+//!     [Cmd::Rotate]: Rotates out the existing mnemonic for a new one, exports it to a file and exits.
+//!     [Cmd::List]: Lists the current and rotated out mnemonics, along with the keys that depend on them, and exits.
+//!     [Cmd::Prune]: Deletes a rotated out mnemonic that no key depends on and exits; see [origins].
+//!     [Cmd::Verify]: Checks that a backup of a mnemonic matches a stored mnemonic and exits; nothing is exported.
+//!
+//! Instead of a single phrase, a mnemonic can be exported as SLIP-39 shares, one file per share, and imported from them; see [ShareScheme].
+//! A phrase can also be exported sealed with an export passphrase, and imported from the sealed file; see [sealed].
 
 mod bip39_bindings;
 mod cmd_handler;
 mod file_io;
+mod origins;
+mod record;
 mod results;
+mod sealed;
+mod slip39_bindings;
 
-pub use cmd_handler::Cmd;
+pub use bip39_bindings::MnemonicLanguage;
+pub(crate) use cmd_handler::{
+    rotated_mnemonic_index, MnemonicCount, RotatedMnemonic, MNEMONIC_COUNT_KEY, MNEMONIC_KEY,
+};
+pub use cmd_handler::{Cmd, CmdOutput, MnemonicConfig, MnemonicSummary};
 pub use file_io::FileIo;
+pub(crate) use origins::{
+    mnemonic_fingerprint, KeyOrigin, KeyScheme, MnemonicFingerprint, MnemonicIndex,
+};
+pub(crate) use record::MnemonicRecord;
+pub(crate) use results::{file_io::FileIoError, mnemonic::InnerMnemonicError};
+pub use slip39_bindings::ShareScheme;
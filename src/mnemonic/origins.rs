@@ -0,0 +1,361 @@
+//! Tracks which mnemonics the keys of tofnd were generated with.
+//!
+//! A mnemonic is identified by its [MnemonicFingerprint], which is not secret. For every gg20 and multisig key,
+//! the fingerprints of the mnemonics it was generated or recovered with are stored as a [KeyOrigin],
+//! so that a rotated out mnemonic is only pruned when no key depends on it.
+//!
+//! Keys are only tracked from the version of tofnd that introduced origins. See [super::MnemonicRecord::tracks_keys].
+//!
+//! The [MnemonicIndex] of every fingerprint is stored as well, so that the seed of a key is found without
+//! re-deriving the key from every stored mnemonic. Mnemonics stored before indices were introduced are indexed
+//! once they are rotated out, or when a key of theirs is found by scanning all mnemonics.
+
+use super::{
+    cmd_handler::{mnemonic_key, rotated_mnemonic_index},
+    results::mnemonic::{InnerMnemonicError::*, InnerMnemonicResult},
+};
+use crate::kv_manager::{KvDomain, KvManager, KvType};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use tofn::gg20::keygen::SecretRecoveryKey;
+
+/// key uid of the multisig key whose public key identifies a mnemonic
+const FINGERPRINT_KEY_UID: &[u8] = b"tofnd mnemonic fingerprint";
+
+/// Non-secret identifier of a mnemonic: the first bytes of the SHA-256 hash of a public key derived from its seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct MnemonicFingerprint(pub(crate) [u8; 8]);
+
+impl fmt::Display for MnemonicFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+/// the fingerprint of the mnemonic of `seed`
+pub(crate) fn mnemonic_fingerprint(
+    seed: &SecretRecoveryKey,
+) -> InnerMnemonicResult<MnemonicFingerprint> {
+    let key_pair = tofn::ecdsa::keygen(seed, FINGERPRINT_KEY_UID).map_err(|_| Fingerprint)?;
+    let mut fingerprint = [0u8; 8];
+    fingerprint
+        .copy_from_slice(&Sha256::digest(&key_pair.encoded_verifying_key())[..fingerprint.len()]);
+    Ok(MnemonicFingerprint(fingerprint))
+}
+
+/// Protocols whose keys are derived from mnemonics. Key uids of different schemes may collide.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum KeyScheme {
+    Gg20,
+    Multisig,
+}
+
+impl KeyScheme {
+    /// the key of the origin of `key_uid` in the kv-store
+    fn origin_key(self, key_uid: &str) -> String {
+        let prefix = match self {
+            KeyScheme::Gg20 => "gg20",
+            KeyScheme::Multisig => "multisig",
+        };
+        format!("{}/{}", prefix, key_uid)
+    }
+}
+
+/// The mnemonics that a key was generated or recovered with.
+/// A multisig key uid is derived anew from every mnemonic it is used with, so it may have several origins.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct KeyOrigin {
+    pub(crate) mnemonics: Vec<MnemonicFingerprint>,
+}
+
+impl KvType for KeyOrigin {
+    const TYPE_ID: &'static str = "key_origin";
+    const VERSION: u32 = 1;
+    const DOMAIN: KvDomain = KvDomain::KeyOrigins;
+}
+
+/// The index of the mnemonic of a fingerprint in the kv-store: `None` for the current mnemonic,
+/// and `Some(n)` for the rotated out mnemonic `mnemonic_n`. Updated when the current mnemonic is rotated out.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct MnemonicIndex(pub(crate) Option<u32>);
+
+impl KvType for MnemonicIndex {
+    const TYPE_ID: &'static str = "mnemonic_index";
+    const VERSION: u32 = 1;
+    const DOMAIN: KvDomain = KvDomain::MnemonicMeta;
+}
+
+/// the key of the index of the mnemonic of `fingerprint` in the kv-store
+fn index_key(fingerprint: MnemonicFingerprint) -> String {
+    format!("index/{}", fingerprint)
+}
+
+/// implement key origin functions for KvManager
+impl KvManager {
+    /// Records that the key `key_uid` of `scheme` was generated or recovered with the mnemonic of `fingerprint`.
+    /// Has to succeed before the key is handed out, otherwise the mnemonic could be pruned while the key is in use.
+    pub(crate) async fn record_key_origin(
+        &self,
+        scheme: KeyScheme,
+        key_uid: &str,
+        fingerprint: MnemonicFingerprint,
+    ) -> InnerMnemonicResult<()> {
+        let key = scheme.origin_key(key_uid);
+
+        // concurrent sessions of the same key must not drop each other's fingerprints
+        let _guard = self.lock_key_origins().await;
+
+        let mut origin = match self.exists::<KeyOrigin>(&key).await? {
+            true => self.get::<KeyOrigin>(&key).await?,
+            false => KeyOrigin { mnemonics: vec![] },
+        };
+        if origin.mnemonics.contains(&fingerprint) {
+            return Ok(());
+        }
+        origin.mnemonics.push(fingerprint);
+
+        // replace the stored origin in one write, so that a crash can't lose the origins of a key
+        Ok(self.replace(&key, origin).await?)
+    }
+
+    /// Get the fingerprints of the mnemonics that the key `key_uid` of `scheme` was generated or recovered with
+    pub(crate) async fn key_origins(
+        &self,
+        scheme: KeyScheme,
+        key_uid: &str,
+    ) -> InnerMnemonicResult<Vec<MnemonicFingerprint>> {
+        let key = scheme.origin_key(key_uid);
+        Ok(match self.exists::<KeyOrigin>(&key).await? {
+            true => self.get::<KeyOrigin>(&key).await?.mnemonics,
+            false => vec![],
+        })
+    }
+
+    /// Forgets the origin of the key `key_uid` of `scheme`, once the key is purged
+    pub(crate) async fn forget_key_origin(
+        &self,
+        scheme: KeyScheme,
+        key_uid: &str,
+    ) -> InnerMnemonicResult<()> {
+        let _guard = self.lock_key_origins().await;
+        Ok(self
+            .delete::<KeyOrigin>(&scheme.origin_key(key_uid))
+            .await?)
+    }
+
+    /// Get the uids of all keys of `scheme` whose origin is recorded
+    pub(crate) async fn key_uids(&self, scheme: KeyScheme) -> InnerMnemonicResult<Vec<String>> {
+        let prefix = scheme.origin_key("");
+        let mut key_uids = vec![];
+        let mut keys = self.keys_with_prefix::<KeyOrigin>(&prefix);
+        while let Some(key) = keys.next().await {
+            key_uids.push(key?[prefix.len()..].to_owned());
+        }
+        Ok(key_uids)
+    }
+
+    /// Records that the mnemonic of `fingerprint` is stored under the index `index`
+    pub(crate) async fn record_mnemonic_index(
+        &self,
+        fingerprint: MnemonicFingerprint,
+        index: MnemonicIndex,
+    ) -> InnerMnemonicResult<()> {
+        let key = index_key(fingerprint);
+
+        let _guard = self.lock_key_origins().await;
+        if self.exists::<MnemonicIndex>(&key).await?
+            && self.get::<MnemonicIndex>(&key).await? == index
+        {
+            return Ok(());
+        }
+
+        // replace the stored index in one write, so that a crash can't lose the mnemonic of a fingerprint
+        Ok(self.replace(&key, index).await?)
+    }
+
+    /// Forgets the index of the mnemonic of `fingerprint`, once the mnemonic is deleted
+    pub(crate) async fn forget_mnemonic_index(
+        &self,
+        fingerprint: MnemonicFingerprint,
+    ) -> InnerMnemonicResult<()> {
+        let _guard = self.lock_key_origins().await;
+        Ok(self
+            .delete::<MnemonicIndex>(&index_key(fingerprint))
+            .await?)
+    }
+
+    /// Get the kv-store key of the mnemonic of `fingerprint`, if its index is known
+    pub(crate) async fn mnemonic_of_fingerprint(
+        &self,
+        fingerprint: MnemonicFingerprint,
+    ) -> InnerMnemonicResult<Option<String>> {
+        let key = index_key(fingerprint);
+        Ok(match self.exists::<MnemonicIndex>(&key).await? {
+            true => Some(mnemonic_key(self.get::<MnemonicIndex>(&key).await?.0)),
+            false => None,
+        })
+    }
+
+    /// Get the seeds of the mnemonics that the key `key_uid` of `scheme` was generated or recovered with,
+    /// along with the kv-store keys of the mnemonics. Mnemonics whose index is not known are skipped.
+    pub(crate) async fn origin_seeds(
+        &self,
+        scheme: KeyScheme,
+        key_uid: &str,
+    ) -> InnerMnemonicResult<Vec<(String, SecretRecoveryKey)>> {
+        let mut seeds = vec![];
+        for fingerprint in self.key_origins(scheme, key_uid).await? {
+            if let Some(key) = self.mnemonic_of_fingerprint(fingerprint).await? {
+                let seed = self.get_seed(&key).await?;
+                seeds.push((key, seed));
+            }
+        }
+        Ok(seeds)
+    }
+
+    /// Records that the key `key_uid` of `scheme` was found to be derived from `seed`, the seed of the mnemonic
+    /// stored under `mnemonic`, so that it is looked up directly from now on.
+    pub(crate) async fn record_key_seed(
+        &self,
+        scheme: KeyScheme,
+        key_uid: &str,
+        mnemonic: &str,
+        seed: &SecretRecoveryKey,
+    ) -> InnerMnemonicResult<()> {
+        let fingerprint = mnemonic_fingerprint(seed)?;
+        self.record_key_origin(scheme, key_uid, fingerprint).await?;
+        self.record_mnemonic_index(fingerprint, MnemonicIndex(rotated_mnemonic_index(mnemonic)))
+            .await
+    }
+
+    /// Get the keys that were generated or recovered with the mnemonic of `fingerprint`
+    pub(crate) async fn keys_of_mnemonic(
+        &self,
+        fingerprint: MnemonicFingerprint,
+    ) -> InnerMnemonicResult<Vec<String>> {
+        let mut dependents = vec![];
+        let mut keys = self.keys_with_prefix::<KeyOrigin>("");
+        while let Some(key) = keys.next().await {
+            let key = key?;
+            if self
+                .get::<KeyOrigin>(&key)
+                .await?
+                .mnemonics
+                .contains(&fingerprint)
+            {
+                dependents.push(key);
+            }
+        }
+        Ok(dependents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        encrypted_sled::get_test_password,
+        gg20::types::Password,
+        mnemonic::bip39_bindings::{bip39_seed, tests::bip39_new_w24, MnemonicLanguage},
+    };
+    use std::convert::TryInto;
+    use testdir::testdir;
+    use tracing_test::traced_test;
+
+    fn new_fingerprint() -> MnemonicFingerprint {
+        let seed = bip39_seed(
+            bip39_new_w24(),
+            MnemonicLanguage::English,
+            Password(String::new()),
+        )
+        .unwrap();
+        let seed: SecretRecoveryKey = seed.as_bytes().try_into().unwrap();
+
+        // the fingerprint is deterministic
+        let fingerprint = mnemonic_fingerprint(&seed).unwrap();
+        assert_eq!(mnemonic_fingerprint(&seed).unwrap(), fingerprint);
+        fingerprint
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_key_origins() {
+        let kv = KvManager::new(testdir!(), get_test_password()).unwrap();
+        let (first, second) = (new_fingerprint(), new_fingerprint());
+        assert_ne!(first, second);
+        assert_eq!(first.to_string().len(), 16);
+
+        kv.record_key_origin(KeyScheme::Gg20, "key", first)
+            .await
+            .unwrap();
+        kv.record_key_origin(KeyScheme::Multisig, "key", first)
+            .await
+            .unwrap();
+        // recording an origin twice is a no-op
+        kv.record_key_origin(KeyScheme::Multisig, "key", first)
+            .await
+            .unwrap();
+        // a multisig key uid may be used with several mnemonics
+        kv.record_key_origin(KeyScheme::Multisig, "key", second)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            kv.keys_of_mnemonic(first).await.unwrap(),
+            vec!["gg20/key", "multisig/key"]
+        );
+        assert_eq!(
+            kv.keys_of_mnemonic(second).await.unwrap(),
+            vec!["multisig/key"]
+        );
+
+        // concurrent sessions of a key keep each other's origins
+        let (third, fourth) = (new_fingerprint(), new_fingerprint());
+        let (res1, res2) = tokio::join!(
+            kv.record_key_origin(KeyScheme::Multisig, "key", third),
+            kv.record_key_origin(KeyScheme::Multisig, "key", fourth),
+        );
+        res1.unwrap();
+        res2.unwrap();
+        assert_eq!(
+            kv.key_origins(KeyScheme::Multisig, "key").await.unwrap(),
+            vec![first, second, third, fourth]
+        );
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn test_mnemonic_indices() {
+        let kv = KvManager::new(testdir!(), get_test_password()).unwrap();
+        let fingerprint = new_fingerprint();
+        assert_eq!(kv.mnemonic_of_fingerprint(fingerprint).await.unwrap(), None);
+
+        kv.record_mnemonic_index(fingerprint, MnemonicIndex(None))
+            .await
+            .unwrap();
+        assert_eq!(
+            kv.mnemonic_of_fingerprint(fingerprint).await.unwrap(),
+            Some("mnemonic".to_owned())
+        );
+
+        // the current mnemonic is rotated out
+        kv.record_mnemonic_index(fingerprint, MnemonicIndex(Some(3)))
+            .await
+            .unwrap();
+        assert_eq!(
+            kv.mnemonic_of_fingerprint(fingerprint).await.unwrap(),
+            Some("mnemonic_3".to_owned())
+        );
+
+        kv.forget_mnemonic_index(fingerprint).await.unwrap();
+        assert_eq!(kv.mnemonic_of_fingerprint(fingerprint).await.unwrap(), None);
+
+        // keys without a recorded origin have no seeds
+        assert!(kv
+            .origin_seeds(KeyScheme::Gg20, "key")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
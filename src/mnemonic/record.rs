@@ -0,0 +1,80 @@
+//! The record of a mnemonic in the kv-store.
+
+use super::bip39_bindings::{MnemonicLanguage, PassphraseFingerprint};
+use crate::{
+    gg20::types::Entropy, // TODO: move from gg20::types
+    kv_manager::{error::InnerKvError, unknown_version, KvDomain, KvType},
+};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tofn::sdk::api::deserialize;
+
+/// A mnemonic as it is stored in the kv-store.
+/// The language is needed to reconstruct the exact phrase, which the seed is derived from.
+/// The length of the entropy is recorded so that a truncated entropy is detected.
+/// If the mnemonic is protected by a BIP39 passphrase, a fingerprint of its seed is recorded to verify the passphrase.
+/// The keys of a mnemonic are only tracked if it was created by this kv-store; see [super::origins].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct MnemonicRecord {
+    pub(crate) entropy: Entropy,
+    pub(crate) language: MnemonicLanguage,
+    /// length of the entropy in bytes
+    pub(crate) entropy_len: u32,
+    /// fingerprint of the seed under the passphrase; `None` if no passphrase is used
+    pub(crate) passphrase_fingerprint: Option<PassphraseFingerprint>,
+    /// unix time in seconds at which the mnemonic was created or imported;
+    /// `None` if it was stored by an older version of tofnd
+    pub(crate) created_at: Option<u64>,
+    /// whether the mnemonic was imported; keys of an imported mnemonic may exist elsewhere
+    pub(crate) imported: bool,
+}
+
+impl MnemonicRecord {
+    /// a mnemonic created now
+    pub(crate) fn new(entropy: Entropy, language: MnemonicLanguage) -> Self {
+        let entropy_len = entropy.0.len() as u32;
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .ok();
+        MnemonicRecord {
+            entropy,
+            language,
+            entropy_len,
+            passphrase_fingerprint: None,
+            created_at,
+            imported: false,
+        }
+    }
+
+    /// keys are only tracked for mnemonics that were created by this kv-store since tracking was introduced
+    pub(crate) fn tracks_keys(&self) -> bool {
+        self.created_at.is_some() && !self.imported
+    }
+
+    /// number of words of the phrase; every 4 bytes of entropy add 3 words
+    pub(crate) fn word_count(&self) -> u32 {
+        self.entropy_len / 4 * 3
+    }
+}
+
+impl KvType for MnemonicRecord {
+    // kept from version 1, which stored the bare entropy
+    const TYPE_ID: &'static str = "mnemonic_entropy";
+    const VERSION: u32 = 2;
+    const DOMAIN: KvDomain = KvDomain::Mnemonics;
+    const UNENVELOPED_VERSION: Option<u32> = Some(1);
+
+    fn upgrade(version: u32, payload: &[u8]) -> Result<Self, InnerKvError> {
+        match version {
+            // version 1 stores the entropy of an English mnemonic, whose creation time is unknown
+            1 => deserialize(payload)
+                .map(|entropy| MnemonicRecord {
+                    created_at: None,
+                    ..MnemonicRecord::new(entropy, MnemonicLanguage::English)
+                })
+                .ok_or(InnerKvError::DeserializationErr),
+            _ => Err(unknown_version::<Self>(version)),
+        }
+    }
+}
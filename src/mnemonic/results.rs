@@ -15,21 +15,67 @@ pub(super) mod bip39 {
         FromEntropy,
         #[error("invalid phrase")]
         FromPhrase,
+        #[error("invalid word count {0}; expected one of 12, 15, 18, 21, 24")]
+        WordCount(usize),
+        #[error("entropy has {found} bytes, but {expected} bytes were recorded")]
+        EntropyLength { expected: usize, found: usize },
     }
     pub type Bip39Result<Success> = Result<Success, Bip39Error>;
 }
 
+pub(super) mod slip39 {
+    #[derive(thiserror::Error, Debug)]
+    pub enum Slip39Error {
+        #[error("invalid share scheme {}-of-{}", .0.threshold, .0.count)]
+        Scheme(crate::mnemonic::ShareScheme),
+        #[error("cannot split entropy: {0}")]
+        Split(String),
+        #[error("cannot combine shares: {0}")]
+        Combine(String),
+    }
+    pub type Slip39Result<Success> = Result<Success, Slip39Error>;
+}
+
+pub(super) mod sealed {
+    #[derive(thiserror::Error, Debug)]
+    pub enum SealError {
+        #[error("malformed sealed file")]
+        Header,
+        #[error("unknown sealed file version {0}")]
+        UnknownVersion(u32),
+        #[error("unsupported scrypt parameters log_n = {log_n}, r = {r}, p = {p}")]
+        UnsupportedParams { log_n: u8, r: u32, p: u32 },
+        #[error("scrypt params error: {0}")]
+        ScryptParams(#[from] scrypt::errors::InvalidParams),
+        #[error("scrypt error: {0}")]
+        Scrypt(#[from] scrypt::errors::InvalidOutputLen),
+        #[error("encryption error: {0}")]
+        Encryption(String),
+        #[error("cannot decrypt sealed file: wrong export passphrase, or the file was modified")]
+        Decryption,
+    }
+    pub type SealResult<Success> = Result<Success, SealError>;
+}
+
 pub(super) mod file_io {
     #[derive(thiserror::Error, Debug)]
     pub enum FileIoError {
         #[error("Bip39 error: {0}")]
         Bip39(#[from] super::bip39::Bip39Error),
+        #[error("Slip39 error: {0}")]
+        Slip39(#[from] super::slip39::Slip39Error),
+        #[error("Sealed file error: {0}")]
+        Sealed(#[from] super::sealed::SealError),
         #[error("File IO error {0}")]
         FileIo(#[from] std::io::Error),
         #[error(
-            "File {0} already exists. Remove file to use `-m existing` or `-m export` commands."
+            "File {0} already exists. Remove file to use `tofnd run` or `tofnd mnemonic export`."
         )]
         Exists(std::path::PathBuf),
+        #[error("Share file {0} is malformed")]
+        MalformedShare(std::path::PathBuf),
+        #[error("Shares belong to mnemonics in different languages")]
+        ShareLanguages,
     }
     pub type FileIoResult<Success> = Result<Success, FileIoError>;
 }
@@ -47,6 +93,30 @@ pub(super) mod mnemonic {
         IntoSecretRecoveryKey(#[from] std::array::TryFromSliceError),
         #[error("Password error: {0}")]
         PasswordErr(String),
+        #[error("Mnemonic is protected by a passphrase, but no passphrase was provided")]
+        MissingPassphrase,
+        #[error("Wrong mnemonic passphrase")]
+        WrongPassphrase,
+        #[error("Cannot derive mnemonic fingerprint")]
+        Fingerprint,
+        #[error("No mnemonic index given; use `--mnemonic-index`")]
+        MissingIndex,
+        #[error(
+            "Keys of mnemonic '{0}' are not tracked, so it is not known whether keys depend on it"
+        )]
+        UntrackedMnemonic(String),
+        #[error("Mnemonic '{mnemonic}' is used by keys {keys:?}")]
+        MnemonicInUse { mnemonic: String, keys: Vec<String> },
+        #[error("Rotating the mnemonic of a running daemon needs sealed exports with an export passphrase file, and no shares or stdout")]
+        InsecureExport,
+        #[error("Backup does not match any stored mnemonic")]
+        NoMatchingMnemonic,
+        #[error("Backup matches the entropy of mnemonic '{mnemonic}', but its phrase is in {backup:?} instead of {stored:?}, which gives another seed")]
+        BackupLanguage {
+            mnemonic: String,
+            backup: crate::mnemonic::MnemonicLanguage,
+            stored: crate::mnemonic::MnemonicLanguage,
+        },
     }
     pub type InnerMnemonicResult<Success> = Result<Success, InnerMnemonicError>;
 
@@ -56,6 +126,8 @@ pub(super) mod mnemonic {
         WrongCommand(String),
         #[error("Cannot not use existing mnemonic: {0}")]
         ExistingErr(InnerMnemonicError),
+        #[error("Cannot set up mnemonic: {0}")]
+        AutoErr(InnerMnemonicError),
         #[error("Cannot create mnemonic: {0}")]
         CreateErr(InnerMnemonicError),
         #[error("Cannot import mnemonic: {0}")]
@@ -64,6 +136,12 @@ pub(super) mod mnemonic {
         ExportErr(InnerMnemonicError),
         #[error("Cannot rotate mnemonic: {0}")]
         RotateErr(InnerMnemonicError),
+        #[error("Cannot list mnemonics: {0}")]
+        ListErr(InnerMnemonicError),
+        #[error("Cannot prune mnemonic: {0}")]
+        PruneErr(InnerMnemonicError),
+        #[error("Cannot verify mnemonic: {0}")]
+        VerifyErr(InnerMnemonicError),
     }
     pub type MnemonicResult<Success> = Result<Success, MnemonicError>;
     pub type SeedResult<Success> = Result<Success, InnerMnemonicError>;
@@ -0,0 +1,211 @@
+//! Seals exported phrases with an export passphrase, so that they can be kept on disk.
+//!
+//! A sealed file consists of a header followed by the phrase, encrypted with [XChaCha20Poly1305].
+//! The key is derived from the export passphrase with [scrypt]. The header holds:
+//!   [SEALED_MAGIC], the format version (u32 LE), the scrypt parameters log_n (u8), r and p (u32 LE),
+//!   the scrypt salt (32 bytes) and the nonce (24 bytes).
+//! The whole header is authenticated as associated data. Only the scrypt parameters of new sealed files are
+//! accepted, because the header is only authenticated after the key is derived with them.
+
+use std::convert::TryInto;
+
+use chacha20poly1305::aead::{AeadInPlace, NewAead};
+use chacha20poly1305::{self, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use super::results::sealed::{SealError::*, SealResult};
+use crate::gg20::types::Password;
+
+/// first bytes of every sealed file
+pub(super) const SEALED_MAGIC: &[u8] = b"tofnd sealed mnemonic\n";
+
+/// version of the sealed format
+const SEALED_VERSION: u32 = 1;
+
+// scrypt parameters of new sealed files; same as the kv store encryption
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = SEALED_MAGIC.len() + 4 + 1 + 4 + 4 + SALT_LEN + NONCE_LEN;
+
+/// Returns true if `bytes` start like a sealed file
+pub(super) fn is_sealed(bytes: &[u8]) -> bool {
+    bytes.starts_with(SEALED_MAGIC)
+}
+
+/// Seals `phrase` with `passphrase`; takes ownership of phrase and zeroizes it before exit
+pub(super) fn seal(phrase: Password, passphrase: &Password) -> SealResult<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let mut nonce = XNonce::default();
+    rand::thread_rng().fill_bytes(nonce.as_mut_slice());
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + phrase.0.len() + 16);
+    sealed.extend_from_slice(SEALED_MAGIC);
+    sealed.extend_from_slice(&SEALED_VERSION.to_le_bytes());
+    sealed.push(SCRYPT_LOG_N);
+    sealed.extend_from_slice(&SCRYPT_R.to_le_bytes());
+    sealed.extend_from_slice(&SCRYPT_P.to_le_bytes());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(nonce.as_slice());
+
+    let cipher = cipher(passphrase, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+    let mut buffer = phrase.0.as_bytes().to_vec();
+    if let Err(err) = cipher.encrypt_in_place(&nonce, &sealed, &mut buffer) {
+        buffer.zeroize();
+        return Err(Encryption(err.to_string()));
+    }
+
+    sealed.extend_from_slice(&buffer);
+    Ok(sealed)
+}
+
+/// Unseals the phrase of `sealed` with `passphrase`
+pub(super) fn unseal(sealed: &[u8], passphrase: &Password) -> SealResult<Password> {
+    if !is_sealed(sealed) || sealed.len() < HEADER_LEN {
+        return Err(Header);
+    }
+    let (header, ciphertext) = sealed.split_at(HEADER_LEN);
+
+    let mut fields = &header[SEALED_MAGIC.len()..];
+    let mut take = |len: usize| {
+        let (field, rest) = fields.split_at(len);
+        fields = rest;
+        field
+    };
+
+    let version = u32::from_le_bytes(take(4).try_into().map_err(|_| Header)?);
+    if version != SEALED_VERSION {
+        return Err(UnknownVersion(version));
+    }
+    let log_n = take(1)[0];
+    let r = u32::from_le_bytes(take(4).try_into().map_err(|_| Header)?);
+    let p = u32::from_le_bytes(take(4).try_into().map_err(|_| Header)?);
+    // a modified header could make key derivation take arbitrary time and memory
+    if (log_n, r, p) != (SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P) {
+        return Err(UnsupportedParams { log_n, r, p });
+    }
+    let salt = take(SALT_LEN);
+    let nonce = XNonce::from_slice(take(NONCE_LEN));
+
+    let cipher = cipher(passphrase, salt, log_n, r, p)?;
+    let mut buffer = ciphertext.to_vec();
+    // a wrong passphrase and a modified file are indistinguishable
+    cipher
+        .decrypt_in_place(nonce, header, &mut buffer)
+        .map_err(|_| Decryption)?;
+
+    match String::from_utf8(buffer) {
+        Ok(phrase) => Ok(Password(phrase)),
+        Err(err) => {
+            err.into_bytes().zeroize();
+            Err(Header)
+        }
+    }
+}
+
+/// derive the cipher of `passphrase` and `salt` with [scrypt]
+fn cipher(
+    passphrase: &Password,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> SealResult<XChaCha20Poly1305> {
+    let mut key = chacha20poly1305::Key::default();
+    scrypt::scrypt(
+        passphrase.0.as_bytes(),
+        salt,
+        &scrypt::Params::new(log_n, r, p)?,
+        key.as_mut_slice(),
+    )?;
+
+    // zeroize key since we are no longer using it after creating cipher
+    let cipher = XChaCha20Poly1305::new(&key);
+    key.zeroize();
+    Ok(cipher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mnemonic::results::sealed::SealError;
+
+    fn phrase() -> Password {
+        Password("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about".to_owned())
+    }
+
+    #[test]
+    fn test_seal_unseal() {
+        let passphrase = Password("export passphrase".to_owned());
+        let sealed = seal(phrase(), &passphrase).unwrap();
+        assert!(is_sealed(&sealed));
+        assert_eq!(sealed.len(), HEADER_LEN + phrase().0.len() + 16);
+
+        assert_eq!(unseal(&sealed, &passphrase).unwrap().0, phrase().0);
+
+        // the salt and nonce are random
+        assert_ne!(seal(phrase(), &passphrase).unwrap(), sealed);
+    }
+
+    #[test]
+    fn test_unseal_fail() {
+        let passphrase = Password("export passphrase".to_owned());
+        let sealed = seal(phrase(), &passphrase).unwrap();
+
+        let wrong_passphrase = Password("wrong passphrase".to_owned());
+        assert!(matches!(
+            unseal(&sealed, &wrong_passphrase),
+            Err(SealError::Decryption)
+        ));
+
+        // the header is authenticated
+        let mut modified = sealed.clone();
+        modified[HEADER_LEN - 1] ^= 1;
+        assert!(matches!(
+            unseal(&modified, &passphrase),
+            Err(SealError::Decryption)
+        ));
+
+        // scrypt parameters other than the ones of new files are rejected before the key is derived
+        let params_offset = SEALED_MAGIC.len() + 4;
+        let mut modified = sealed.clone();
+        modified[params_offset] = 40;
+        assert!(matches!(
+            unseal(&modified, &passphrase),
+            Err(SealError::UnsupportedParams { log_n: 40, .. })
+        ));
+        let mut modified = sealed.clone();
+        modified[params_offset + 1..params_offset + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            unseal(&modified, &passphrase),
+            Err(SealError::UnsupportedParams { r: u32::MAX, .. })
+        ));
+        let mut modified = sealed.clone();
+        modified[params_offset + 5] = 2;
+        assert!(matches!(
+            unseal(&modified, &passphrase),
+            Err(SealError::UnsupportedParams { p: 2, .. })
+        ));
+
+        let mut modified = sealed.clone();
+        modified[SEALED_MAGIC.len()] = 2;
+        assert!(matches!(
+            unseal(&modified, &passphrase),
+            Err(SealError::UnknownVersion(2))
+        ));
+
+        assert!(matches!(
+            unseal(phrase().0.as_bytes(), &passphrase),
+            Err(SealError::Header)
+        ));
+        assert!(matches!(
+            unseal(&sealed[..HEADER_LEN - 1], &passphrase),
+            Err(SealError::Header)
+        ));
+    }
+}
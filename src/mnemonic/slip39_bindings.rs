@@ -0,0 +1,119 @@
+//! This module provides wrappers for splitting the entropy of a mnemonic into SLIP-39 shares
+//! and combining them again, using the sssmc39 https://crates.io/crates/sssmc39 library.
+//! https://github.com/satoshilabs/slips/blob/master/slip-0039.md
+//!
+//! The entropy is split in a single group of shares. Shares are not encrypted with a SLIP-39
+//! passphrase; a mnemonic is protected by its BIP39 passphrase instead.
+//!
+//! Zeroization:
+//!   All functions that accept and/or return structs that implement zeroization:
+//!   [crate::gg20::Password], [crate::gg20::Entropy]
+
+use super::results::slip39::{Slip39Error::*, Slip39Result};
+use crate::gg20::types::{Entropy, Password};
+use sssmc39::{combine_mnemonics, generate_mnemonics};
+use zeroize::Zeroize;
+
+// shares are not encrypted; see module docs
+const SLIP39_PASSPHRASE: &str = "";
+
+// shares are only encrypted with the empty passphrase, so the minimum number of
+// PBKDF2 iterations (10000 * 2^0) is used
+const ITERATION_EXPONENT: u8 = 0;
+
+/// Any `threshold` of the `count` shares of a mnemonic recover its entropy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShareScheme {
+    pub threshold: u8,
+    pub count: u8,
+}
+
+/// split `entropy` into the shares of `scheme`; takes ownership of entropy and zeroizes it before exit
+pub(super) fn slip39_split(entropy: Entropy, scheme: ShareScheme) -> Slip39Result<Vec<Password>> {
+    // SLIP-39 only allows a threshold of 1 for a single share
+    if scheme.threshold == 0
+        || scheme.threshold > scheme.count
+        || (scheme.threshold == 1 && scheme.count > 1)
+    {
+        return Err(Scheme(scheme));
+    }
+
+    let groups = generate_mnemonics(
+        1,
+        &[(scheme.threshold, scheme.count)],
+        &entropy.0,
+        SLIP39_PASSPHRASE,
+        ITERATION_EXPONENT,
+    )
+    .map_err(|e| Split(e.to_string()))?;
+
+    let mut shares = groups
+        .first()
+        .ok_or_else(|| Split("no group of shares".to_owned()))?
+        .mnemonic_list()
+        .map_err(|e| Split(e.to_string()))?;
+
+    let phrases = shares
+        .iter()
+        .map(|words| Password(words.join(" ")))
+        .collect();
+    shares.zeroize();
+    Ok(phrases)
+}
+
+/// combine `shares` into the entropy they were split from; takes ownership of shares and zeroizes them before exit
+pub(super) fn slip39_combine(shares: Vec<Password>) -> Slip39Result<Entropy> {
+    let mut mnemonics: Vec<Vec<String>> = shares
+        .iter()
+        .map(|share| share.0.split_whitespace().map(str::to_owned).collect())
+        .collect();
+
+    let result =
+        combine_mnemonics(&mnemonics, SLIP39_PASSPHRASE).map_err(|e| Combine(e.to_string()));
+    mnemonics.zeroize();
+    Ok(Entropy(result?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mnemonic::bip39_bindings::{bip39_new, WORD_COUNTS};
+
+    #[test]
+    fn test_split_combine() {
+        let scheme = ShareScheme {
+            threshold: 2,
+            count: 3,
+        };
+
+        for word_count in WORD_COUNTS {
+            let entropy = bip39_new(word_count).unwrap();
+            let shares = slip39_split(entropy.clone(), scheme).unwrap();
+            assert_eq!(shares.len(), 3);
+
+            // any 2 shares recover the entropy
+            for (i, j) in [(0, 1), (0, 2), (2, 1)] {
+                let combined = slip39_combine(vec![shares[i].clone(), shares[j].clone()]).unwrap();
+                assert_eq!(combined.0, entropy.0);
+            }
+
+            // a single share does not
+            assert!(slip39_combine(vec![shares[0].clone()]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_schemes() {
+        let entropy = bip39_new(24).unwrap();
+        for (threshold, count, ok) in [
+            (1, 1, true),
+            (3, 5, true),
+            (1, 2, false),
+            (0, 2, false),
+            (3, 2, false),
+        ] {
+            let res = slip39_split(entropy.clone(), ShareScheme { threshold, count });
+            assert_eq!(res.is_ok(), ok, "{}-of-{}", threshold, count);
+        }
+    }
+}
@@ -7,13 +7,13 @@ use super::service::MultisigService;
 use tracing::debug;
 
 // error handling
-use crate::{proto, TofndResult};
+use crate::{error::ServiceResult, proto};
 
 impl MultisigService {
     pub(super) async fn handle_key_presence(
         &self,
         request: proto::KeyPresenceRequest,
-    ) -> TofndResult<proto::key_presence_response::Response> {
+    ) -> ServiceResult<proto::key_presence_response::Response> {
         // check if mnemonic is available
         let _ = self
             .find_matching_seed(&request.key_uid, &request.pub_key)
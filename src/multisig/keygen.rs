@@ -1,15 +1,28 @@
 use super::service::MultisigService;
-use crate::{proto::KeygenRequest, TofndResult};
+use crate::{
+    error::{ServiceError::InvalidArgument, ServiceResult},
+    mnemonic::{mnemonic_fingerprint, KeyScheme},
+    proto::KeygenRequest,
+};
 use tofn::ecdsa::keygen;
 
-use anyhow::anyhow;
-
 impl MultisigService {
-    pub(super) async fn handle_keygen(&self, request: &KeygenRequest) -> TofndResult<Vec<u8>> {
+    pub(super) async fn handle_keygen(&self, request: &KeygenRequest) -> ServiceResult<Vec<u8>> {
+        // the origin is recorded before the mnemonic can be rotated out
+        let _guard = self.kv_manager.read_mnemonics().await;
         let secret_recovery_key = self.kv_manager.seed().await?;
 
         let key_pair = keygen(&secret_recovery_key, request.key_uid.as_bytes())
-            .map_err(|_| anyhow!("Cannot generate keypair"))?;
+            .map_err(|_| InvalidArgument("Cannot generate keypair".to_string()))?;
+
+        // the public key is handed out, so its mnemonic must not be pruned
+        self.kv_manager
+            .record_key_origin(
+                KeyScheme::Multisig,
+                &request.key_uid,
+                mnemonic_fingerprint(&secret_recovery_key)?,
+            )
+            .await?;
 
         Ok(key_pair.encoded_verifying_key().to_vec())
     }
@@ -31,7 +31,10 @@ impl proto::multisig_server::Multisig for MultisigService {
                 res
             }
             Err(err) => {
-                error!("Unable to complete key presence check: {}", err);
+                error!(
+                    "Unable to complete key presence check: {}",
+                    err.to_response_string()
+                );
                 proto::key_presence_response::Response::Fail
             }
         };
@@ -59,9 +62,9 @@ impl proto::multisig_server::Multisig for MultisigService {
                     "[{}] Multisig Keygen with key id [{}] failed: {}",
                     request.party_uid,
                     request.key_uid,
-                    err.to_string()
+                    err.to_response_string()
                 );
-                proto::keygen_response::KeygenResponse::Error(err.to_string())
+                proto::keygen_response::KeygenResponse::Error(err.to_response_string())
             }
         };
 
@@ -89,9 +92,9 @@ impl proto::multisig_server::Multisig for MultisigService {
                     request.party_uid,
                     request.key_uid,
                     request.msg_to_sign,
-                    err.to_string()
+                    err.to_response_string()
                 );
-                proto::sign_response::SignResponse::Error(err.to_string())
+                proto::sign_response::SignResponse::Error(err.to_response_string())
             }
         };
 
@@ -1,67 +1,105 @@
 use super::service::MultisigService;
-use crate::{proto::SignRequest, TofndResult};
+use crate::{
+    error::{ServiceError::*, ServiceResult},
+    mnemonic::KeyScheme,
+    proto::SignRequest,
+};
 use std::convert::TryInto;
+use tracing::info;
 
-use anyhow::anyhow;
 use tofn::{
     ecdsa::{keygen, sign},
     multisig::keygen::SecretRecoveryKey,
 };
 
 impl MultisigService {
-    pub(super) async fn handle_sign(&self, request: &SignRequest) -> TofndResult<Vec<u8>> {
+    pub(super) async fn handle_sign(&self, request: &SignRequest) -> ServiceResult<Vec<u8>> {
         // re-generate secret key from seed, then sign
         let secret_recovery_key = self
             .find_matching_seed(&request.key_uid, &request.pub_key)
             .await?;
 
         let key_pair = keygen(&secret_recovery_key, request.key_uid.as_bytes())
-            .map_err(|_| anyhow!("key re-generation failed"))?;
+            .map_err(|_| InvalidArgument("key re-generation failed".to_string()))?;
 
         let signature = sign(
             key_pair.signing_key(),
-            &request.msg_to_sign.as_slice().try_into()?,
+            &request
+                .msg_to_sign
+                .as_slice()
+                .try_into()
+                .map_err(|err| InvalidArgument(format!("msg_to_sign: {:?}", err)))?,
         )
-        .map_err(|_| anyhow!("sign failed"))?;
+        .map_err(|_| Internal("sign failed".to_string()))?;
 
         Ok(signature)
     }
 
     /// Given a `key_uid` and `pub_key`, find the matching mnemonic.
     /// If `pub_key` is [None], use the currently active mnemonic.
+    /// The mnemonics recorded for `key_uid` are tried first. Keys generated before mnemonics were recorded
+    /// are searched for in all mnemonics, and their mnemonic is recorded once found.
     pub(super) async fn find_matching_seed(
         &self,
         key_uid: &str,
         pub_key: &[u8],
-    ) -> TofndResult<SecretRecoveryKey> {
+    ) -> ServiceResult<SecretRecoveryKey> {
+        // mnemonics are not rotated while they are searched
+        let _guard = self.kv_manager.read_mnemonics().await;
+
         if pub_key.is_empty() {
-            return self
-                .kv_manager
-                .seed()
-                .await
-                .map_err(|_| anyhow!("could not find current mnemonic"));
+            return Ok(self.kv_manager.seed().await?);
         }
 
-        let seed_key_iter = self
+        let origin_seeds = self
             .kv_manager
-            .seed_key_iter()
-            .await
-            .map_err(|_e| anyhow!("could not iterate over mnemonic keys"))?;
+            .origin_seeds(KeyScheme::Multisig, key_uid)
+            .await?;
+
+        for (seed_key, secret_recovery_key) in origin_seeds {
+            if derives_pub_key(&secret_recovery_key, key_uid, pub_key)? {
+                info!("Found mnemonic '{}' of key {:?}", seed_key, key_uid);
+                return Ok(secret_recovery_key);
+            }
+        }
+
+        let seed_key_iter = self.kv_manager.seed_key_iter().await?;
 
         for seed_key in seed_key_iter {
             let secret_recovery_key = self.kv_manager.get_seed(&seed_key).await?;
 
-            let key_pair = keygen(&secret_recovery_key, key_uid.as_bytes())
-                .map_err(|_| anyhow!("key re-generation failed"))?;
-
-            if pub_key == key_pair.encoded_verifying_key() {
+            if derives_pub_key(&secret_recovery_key, key_uid, pub_key)? {
+                info!(
+                    "Found mnemonic '{}' of key {:?} by scanning all mnemonics",
+                    seed_key, key_uid
+                );
+                self.kv_manager
+                    .record_key_seed(
+                        KeyScheme::Multisig,
+                        key_uid,
+                        &seed_key,
+                        &secret_recovery_key,
+                    )
+                    .await?;
                 return Ok(secret_recovery_key);
             }
         }
 
-        Err(anyhow!(
+        Err(KeyNotFound(format!(
             "could not find a matching mnemonic for key {:?}",
             key_uid
-        ))
+        )))
     }
 }
+
+/// Returns true if the key of `key_uid` derived from `secret_recovery_key` has the public key `pub_key`
+fn derives_pub_key(
+    secret_recovery_key: &SecretRecoveryKey,
+    key_uid: &str,
+    pub_key: &[u8],
+) -> ServiceResult<bool> {
+    let key_pair = keygen(secret_recovery_key, key_uid.as_bytes())
+        .map_err(|_| InvalidArgument("key re-generation failed".to_string()))?;
+
+    Ok(pub_key == key_pair.encoded_verifying_key())
+}
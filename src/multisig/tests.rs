@@ -2,6 +2,7 @@ use crate::{
     addr,
     encrypted_sled::get_test_password,
     kv_manager::KvManager,
+    mnemonic::{mnemonic_fingerprint, KeyScheme},
     tests::{DEFAULT_TEST_IP, DEFAULT_TEST_PORT},
 };
 use tokio::{
@@ -12,7 +13,7 @@ use tokio::{
 use tokio_stream::wrappers::TcpListenerStream;
 use tonic::transport::Channel;
 
-use super::service::new_service;
+use super::service::{new_service, MultisigService};
 
 use testdir::testdir;
 use tracing::error;
@@ -34,7 +35,10 @@ async fn spin_test_service_and_client() -> (MultisigClient<Channel>, Sender<()>)
     // create a kv_manager
     let kv_manager = KvManager::new(root, get_test_password())
         .unwrap()
-        .handle_mnemonic(&crate::mnemonic::Cmd::Create)
+        .handle_mnemonic(
+            &crate::mnemonic::Cmd::Create,
+            &crate::mnemonic::MnemonicConfig::default(),
+        )
         .await
         .unwrap();
 
@@ -196,7 +200,7 @@ async fn test_multisig_truncated_msg_fail() {
     }
     assert!(matches!(
         response.sign_response.unwrap(),
-        SignResponse::Error(_)
+        SignResponse::Error(err) if err.starts_with("[INVALID_ARGUMENT]")
     ));
 
     shutdown_sender.send(()).unwrap();
@@ -221,3 +225,63 @@ async fn test_key_presence() {
 
     shutdown_sender.send(()).unwrap();
 }
+
+#[traced_test]
+#[tokio::test]
+async fn test_find_matching_seed() {
+    let root = testdir!();
+    let export_cfg = |name: &str| crate::mnemonic::MnemonicConfig {
+        export_path: Some(root.join(name)),
+        ..crate::mnemonic::MnemonicConfig::default()
+    };
+
+    let kv_manager = KvManager::new(root.join("kv"), get_test_password())
+        .unwrap()
+        .handle_mnemonic(&crate::mnemonic::Cmd::Create, &export_cfg("created"))
+        .await
+        .unwrap();
+    let service = MultisigService {
+        kv_manager: kv_manager.clone(),
+    };
+
+    // a key whose mnemonic was not recorded, like the keys of older versions of tofnd
+    let key = "unrecorded key";
+    let first_seed = kv_manager.seed().await.unwrap();
+    let pub_key = tofn::ecdsa::keygen(&first_seed, key.as_bytes())
+        .unwrap()
+        .encoded_verifying_key()
+        .to_vec();
+
+    let kv_manager = kv_manager
+        .handle_mnemonic(&crate::mnemonic::Cmd::Rotate, &export_cfg("rotated"))
+        .await
+        .unwrap();
+
+    // the key is found by scanning all mnemonics, and its mnemonic is recorded
+    let seed = service.find_matching_seed(key, &pub_key).await.unwrap();
+    assert_eq!(format!("{:?}", seed), format!("{:?}", first_seed));
+    let fingerprint = mnemonic_fingerprint(&first_seed).unwrap();
+    assert_eq!(
+        kv_manager
+            .key_origins(KeyScheme::Multisig, key)
+            .await
+            .unwrap(),
+        vec![fingerprint]
+    );
+    assert_eq!(
+        kv_manager
+            .origin_seeds(KeyScheme::Multisig, key)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(seed_key, _)| seed_key)
+            .collect::<Vec<_>>(),
+        vec!["mnemonic_1"]
+    );
+
+    // a key of another mnemonic is not found
+    assert!(service
+        .find_matching_seed("another key", &pub_key)
+        .await
+        .is_err());
+}
@@ -0,0 +1,106 @@
+//! Reports the results of commands that exit tofnd.
+//! With [OutputFormat::Text], results are logged. With [OutputFormat::Json], a single json object is printed to stdout,
+//! so that scripts can parse it; logs are written to stderr.
+
+use serde::{Serialize, Serializer};
+use tracing::{error, info};
+
+use crate::{config::OutputFormat, TofndResult};
+
+/// The json object reported for a command
+#[derive(Serialize)]
+struct Report<'a, T: Serialize> {
+    /// the command, e.g. `mnemonic rotate`
+    command: &'a str,
+    success: bool,
+    /// the fields of the result of a successful command
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    result: Option<&'a T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Reports the `result` of `command` in `format`. `result` has to serialize to a json object.
+/// Returns the error of a failed command, so that tofnd exits with a non-zero code.
+pub fn report<T: Serialize>(
+    format: OutputFormat,
+    command: &str,
+    result: TofndResult<T>,
+) -> TofndResult<()> {
+    match format {
+        OutputFormat::Text => match &result {
+            Ok(_) => info!("Tofnd exited after using command <{}>.", command),
+            Err(err) => error!("Command <{}> failed: {:#}", command, err),
+        },
+        OutputFormat::Json => {
+            let report = match &result {
+                Ok(result) => Report {
+                    command,
+                    success: true,
+                    result: Some(result),
+                    error: None,
+                },
+                Err(err) => Report {
+                    command,
+                    success: false,
+                    result: None,
+                    error: Some(format!("{:#}", err)),
+                },
+            };
+            println!("{}", serde_json::to_string(&report)?);
+        }
+    }
+
+    result.map(|_| ())
+}
+
+/// Serializes `bytes` as a hex string
+pub fn serialize_hex<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    serializer.serialize_str(&hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use serde_json::{json, to_value};
+
+    #[test]
+    fn test_report() {
+        let report = Report {
+            command: "mnemonic verify",
+            success: true,
+            result: Some(&json!({ "result": "verified", "mnemonic": "mnemonic_1" })),
+            error: None,
+        };
+        assert_eq!(
+            to_value(&report).unwrap(),
+            json!({
+                "command": "mnemonic verify",
+                "success": true,
+                "result": "verified",
+                "mnemonic": "mnemonic_1",
+            })
+        );
+
+        let report = Report::<serde_json::Value> {
+            command: "db reservations list",
+            success: false,
+            result: None,
+            error: Some("cannot open kv store".to_owned()),
+        };
+        assert_eq!(
+            to_value(&report).unwrap(),
+            json!({
+                "command": "db reservations list",
+                "success": false,
+                "error": "cannot open kv store",
+            })
+        );
+
+        // failed commands still fail after they are reported
+        let result: TofndResult<serde_json::Value> = Err(anyhow!("failed"));
+        assert!(report(OutputFormat::Json, "mnemonic list", result).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+//! Tests of the [crate::proto::gg20_keys_server::Gg20Keys] service
+
+use super::{
+    admin_request, basic_keygen, check_sign_results, clean_up, execute_key_presence,
+    execute_recover, execute_sign, gather_recover_info, init_parties_from_test_case,
+    open_party_kv_manager, restart_party, shutdown_party, TestCase, MSG_TO_SIGN, SLEEP_TIME,
+};
+use crate::{
+    admin::service::ADMIN_TOKEN_KEY,
+    error::ERROR_CODE_KEY,
+    gg20::{
+        delete::purge_deleted_keys,
+        types::{Gg20KeyUsage, Gg20Tombstone, PartyInfo},
+    },
+    keys::{handle_keys_cmd, KeysCmd},
+    kv_manager::KvManager,
+    mnemonic::KeyScheme,
+    proto::{
+        get_key_info_response::KeygenMode, DeleteKeyRequest, GetKeyInfoRequest,
+        GetRecoveryInfoRequest, UndeleteKeyRequest,
+    },
+};
+
+use testdir::testdir;
+use tokio::time::{sleep, Duration};
+use tonic::{
+    metadata::MetadataValue,
+    Code::{InvalidArgument, NotFound, Unauthenticated},
+    Request,
+};
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn delete_and_undelete_key() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![1, 1, 1], 1, vec![0, 1]);
+    let key_uid = "delete-test-key";
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (mut parties, _, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids.clone(), key_uid).await;
+    assert!(success);
+    let pub_key = gather_recover_info(&keygen_results)[0].pub_key.clone();
+
+    let delete_request = |pub_key: Vec<u8>| DeleteKeyRequest {
+        key_uid: key_uid.to_string(),
+        pub_key,
+    };
+    let undelete_request = || UndeleteKeyRequest {
+        key_uid: key_uid.to_string(),
+    };
+
+    // keys are only deleted by admins
+    let mut wrong_token = admin_request(delete_request(pub_key.clone()));
+    wrong_token
+        .metadata_mut()
+        .insert(ADMIN_TOKEN_KEY, MetadataValue::from_static("wrong token"));
+    for request in [Request::new(delete_request(pub_key.clone())), wrong_token] {
+        let status = parties[0].keys_client.delete_key(request).await;
+        assert_eq!(status.unwrap_err().code(), Unauthenticated);
+    }
+
+    // the key is not deleted without its public key
+    let status = parties[0]
+        .keys_client
+        .delete_key(admin_request(delete_request(vec![42; 33])))
+        .await;
+    assert_eq!(status.unwrap_err().code(), InvalidArgument);
+
+    for party in parties.iter_mut() {
+        let response = party
+            .keys_client
+            .delete_key(admin_request(delete_request(pub_key.clone())))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.purge_after > response.deleted_at);
+    }
+    let mut parties = execute_key_presence(parties, key_uid.into(), false).await;
+
+    // a deleted key can't be deleted again
+    let status = parties[0]
+        .keys_client
+        .delete_key(admin_request(delete_request(pub_key.clone())))
+        .await;
+    let status = status.unwrap_err();
+    assert_eq!(status.code(), NotFound);
+    assert_eq!(
+        status.metadata().get(ERROR_CODE_KEY).unwrap(),
+        "KEY_NOT_FOUND"
+    );
+
+    // and undeleted by admins
+    let status = parties[0]
+        .keys_client
+        .undelete_key(undelete_request())
+        .await;
+    assert_eq!(status.unwrap_err().code(), Unauthenticated);
+
+    for party in parties.iter_mut() {
+        party
+            .keys_client
+            .undelete_key(admin_request(undelete_request()))
+            .await
+            .unwrap();
+    }
+    let mut parties = execute_key_presence(parties, key_uid.into(), true).await;
+
+    // the tombstone is gone once the key is undeleted
+    let status = parties[0]
+        .keys_client
+        .undelete_key(admin_request(undelete_request()))
+        .await;
+    assert_eq!(status.unwrap_err().code(), NotFound);
+
+    // an undeleted key signs as before
+    let (parties, results) = execute_sign(
+        parties,
+        &party_uids,
+        &test_case.signer_indices,
+        key_uid,
+        "delete-test-sig",
+        &MSG_TO_SIGN,
+        false,
+    )
+    .await;
+    let results = results.into_iter().map(|r| r.unwrap()).collect();
+    assert!(check_sign_results(results, &test_case.expected_sign_faults));
+
+    clean_up(parties).await;
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn purge_after_undelete_window() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![1, 1, 1], 1, vec![0, 1]);
+    let key_uid = "purge-test-key";
+    let purge_index = 0;
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (parties, _, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids.clone(), key_uid).await;
+    assert!(success);
+    let pub_key = gather_recover_info(&keygen_results)[0].pub_key.clone();
+
+    // the key signs once, so that its usage is stored
+    let (mut parties, results) = execute_sign(
+        parties,
+        &party_uids,
+        &test_case.signer_indices,
+        key_uid,
+        "purge-test-sig",
+        &MSG_TO_SIGN,
+        false,
+    )
+    .await;
+    let results = results.into_iter().map(|r| r.unwrap()).collect();
+    assert!(check_sign_results(results, &test_case.expected_sign_faults));
+    sleep(Duration::from_secs(SLEEP_TIME)).await;
+
+    parties[purge_index]
+        .keys_client
+        .delete_key(admin_request(DeleteKeyRequest {
+            key_uid: key_uid.to_string(),
+            pub_key,
+        }))
+        .await
+        .unwrap();
+
+    let (party_options, party_root) = shutdown_party(parties, purge_index).await;
+    let kv_manager = open_party_kv_manager(party_root, KvManager::new).await;
+    assert!(kv_manager.exists::<Gg20Tombstone>(key_uid).await.unwrap());
+    assert!(!kv_manager
+        .key_origins(KeyScheme::Gg20, key_uid)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(kv_manager.exists::<Gg20KeyUsage>(key_uid).await.unwrap());
+
+    // with a zero-length undelete window, the key is purged right away, along with its origin and usage
+    assert_eq!(
+        purge_deleted_keys(&kv_manager, Duration::ZERO)
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(!kv_manager.exists::<Gg20Tombstone>(key_uid).await.unwrap());
+    assert!(!kv_manager.exists::<PartyInfo>(key_uid).await.unwrap());
+    assert!(kv_manager
+        .key_origins(KeyScheme::Gg20, key_uid)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(!kv_manager.exists::<Gg20KeyUsage>(key_uid).await.unwrap());
+    drop(kv_manager);
+
+    clean_up(party_options.into_iter().flatten().collect()).await;
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn get_key_info() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![2, 1, 1], 2, vec![0, 1]);
+    let key_uid = "info-test-key";
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (mut parties, _, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids.clone(), key_uid).await;
+    assert!(success);
+    let keygen_outputs = gather_recover_info(&keygen_results);
+
+    for (index, party) in parties.iter_mut().enumerate() {
+        let info = party
+            .keys_client
+            .get_key_info(GetKeyInfoRequest {
+                key_uid: key_uid.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        // the public parts of the keygen output are returned as they were at keygen
+        assert_eq!(info.pub_key, keygen_outputs[index].pub_key);
+        assert_eq!(info.all_shares, keygen_outputs[index].group_recover_info);
+        assert_eq!(info.threshold, test_case.threshold as u32);
+        assert_eq!(info.party_uids, party_uids);
+        assert_eq!(info.party_share_counts, test_case.share_counts);
+        assert_eq!(info.my_party_index, index as u32);
+
+        // metadata is recorded at keygen; test parties use unsafe primes
+        assert!(info.created_at > 0);
+        assert_eq!(info.keygen_mode, KeygenMode::Unsafe as i32);
+        assert_eq!(info.tofnd_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.sign_count, 0);
+        assert_eq!(info.last_signed_at, 0);
+    }
+
+    let (mut parties, results) = execute_sign(
+        parties,
+        &party_uids,
+        &test_case.signer_indices,
+        key_uid,
+        "info-test-sig",
+        &MSG_TO_SIGN,
+        false,
+    )
+    .await;
+    let results = results.into_iter().map(|r| r.unwrap()).collect();
+    assert!(check_sign_results(results, &test_case.expected_sign_faults));
+
+    // usage is recorded in the background once the signature is sent
+    sleep(Duration::from_secs(SLEEP_TIME)).await;
+    for (index, party) in parties.iter_mut().enumerate() {
+        let info = party
+            .keys_client
+            .get_key_info(GetKeyInfoRequest {
+                key_uid: key_uid.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        let signed = test_case.signer_indices.contains(&index);
+        assert_eq!(info.sign_count, signed as u64);
+        assert_eq!(info.last_signed_at >= info.created_at, signed);
+    }
+
+    let status = parties[0]
+        .keys_client
+        .get_key_info(GetKeyInfoRequest {
+            key_uid: "unknown-key".to_string(),
+        })
+        .await;
+    assert_eq!(status.unwrap_err().code(), NotFound);
+
+    clean_up(parties).await;
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn rebuild_secret_key_shares() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![2, 1, 1], 2, vec![0, 1]);
+    let key_uid = "rebuild-test-key";
+    let rebuild_index = 0;
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (parties, _, _, success) = basic_keygen(&test_case, parties, party_uids, key_uid).await;
+    assert!(success);
+
+    // the shares that recovery info is rebuilt from are the ones stored at keygen
+    let (party_options, party_root) = shutdown_party(parties, rebuild_index).await;
+    let kv_manager = open_party_kv_manager(party_root, KvManager::new).await;
+    kv_manager
+        .get::<PartyInfo>(key_uid)
+        .await
+        .unwrap()
+        .assert_secret_key_shares_round_trip();
+    drop(kv_manager);
+
+    clean_up(party_options.into_iter().flatten().collect()).await;
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn recover_with_rebuilt_recovery_info() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![2, 1, 1], 2, vec![0, 1]);
+    let key_uid = "recovery-info-test-key";
+    let recover_index = 0;
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (mut parties, keygen_init, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids.clone(), key_uid).await;
+    assert!(success);
+    let mut keygen_outputs = gather_recover_info(&keygen_results);
+
+    let recovery_info_request = || GetRecoveryInfoRequest {
+        key_uid: key_uid.to_string(),
+    };
+
+    // recovery info is only served to admins
+    let status = parties[recover_index]
+        .keys_client
+        .get_recovery_info(recovery_info_request())
+        .await;
+    assert_eq!(status.unwrap_err().code(), Unauthenticated);
+
+    let output = parties[recover_index]
+        .keys_client
+        .get_recovery_info(admin_request(recovery_info_request()))
+        .await
+        .unwrap()
+        .into_inner()
+        .output
+        .unwrap();
+    assert_eq!(output.pub_key, keygen_outputs[recover_index].pub_key);
+    assert_eq!(
+        output.group_recover_info,
+        keygen_outputs[recover_index].group_recover_info
+    );
+
+    // the party loses its shares and recovers them from the rebuilt output instead of the keygen output
+    let parties = restart_party(
+        &dir,
+        parties,
+        recover_index,
+        true,
+        key_uid.to_string(),
+        #[cfg(feature = "malicious")]
+        &test_case.malicious_data,
+    )
+    .await;
+    keygen_outputs[recover_index] = output;
+    let parties = execute_recover(parties, recover_index, keygen_init, keygen_outputs).await;
+
+    // the recovered shares sign as before
+    let (parties, results) = execute_sign(
+        parties,
+        &party_uids,
+        &test_case.signer_indices,
+        key_uid,
+        "recovery-info-test-sig",
+        &MSG_TO_SIGN,
+        false,
+    )
+    .await;
+    let results = results.into_iter().map(|r| r.unwrap()).collect();
+    assert!(check_sign_results(results, &test_case.expected_sign_faults));
+
+    clean_up(parties).await;
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn keys_cmd_lists_gg20_keys() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![2, 1, 1], 2, vec![0, 1]);
+    let key_uid = "keys-cmd-test-key";
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (parties, _, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids.clone(), key_uid).await;
+    assert!(success);
+    let pub_key = gather_recover_info(&keygen_results)[0].pub_key.clone();
+
+    // `tofnd keys` opens the kvstore of a stopped party read-only
+    let (party_options, party_root) = shutdown_party(parties, 0).await;
+    let kv_manager = open_party_kv_manager(party_root, KvManager::open_read_only).await;
+
+    for cmd in [KeysCmd::List, KeysCmd::Show(key_uid.to_string())] {
+        let inventory = handle_keys_cmd(&kv_manager, &cmd).await.unwrap();
+        assert!(inventory.multisig.is_empty());
+
+        let report = serde_json::to_value(&inventory).unwrap();
+        let key = &report["gg20"][0];
+        assert_eq!(report["gg20"].as_array().unwrap().len(), 1);
+        assert_eq!(key["key_uid"], key_uid);
+        let pub_key_hex: String = pub_key.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(key["pub_key"], pub_key_hex);
+        assert_eq!(key["threshold"], test_case.threshold);
+        assert_eq!(key["party_uids"], serde_json::json!(party_uids));
+        assert_eq!(
+            key["share_counts"],
+            serde_json::json!(test_case.share_counts)
+        );
+        assert_eq!(key["index"], 0);
+        assert_eq!(key["local_shares"], 2);
+        assert_eq!(key["sign_count"], 0);
+        // the key was generated with the current mnemonic
+        assert_eq!(key["mnemonics"][0]["mnemonic"], "mnemonic");
+    }
+
+    // nothing is written to a kvstore that is opened read-only
+    assert!(kv_manager.delete::<PartyInfo>(key_uid).await.is_err());
+    assert!(kv_manager.exists::<PartyInfo>(key_uid).await.unwrap());
+    drop(kv_manager);
+
+    clean_up(party_options.into_iter().flatten().collect()).await;
+}
@@ -0,0 +1,127 @@
+//! Tests of keys generated with unsafe primes on parties with other flags
+
+use super::{
+    basic_keygen, check_sign_results, clean_up, delete_party_export, delete_party_shares,
+    execute_recover, execute_sign, gather_recover_info, init_parties_from_test_case, reinit_party,
+    reinit_party_as, shutdown_party, InitParty, TestCase, MSG_TO_SIGN,
+};
+use crate::{
+    error::ERROR_CODE_KEY,
+    proto::{get_key_info_response::KeygenMode, GetKeyInfoRequest},
+};
+
+use testdir::testdir;
+use tonic::Code::FailedPrecondition;
+use tracing_test::traced_test;
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn unsafe_key_signs_only_if_allowed() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![1, 1, 1], 1, vec![0, 1]);
+    let key_uid = "unsafe-sign-test-key";
+    let restart_index = 0;
+
+    // test parties generate keys with unsafe primes
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (parties, _, _, success) =
+        basic_keygen(&test_case, parties, party_uids.clone(), key_uid).await;
+    assert!(success);
+
+    // restart a party without '--allow-unsafe-keys'
+    let (party_options, party_root) = shutdown_party(parties, restart_index).await;
+    delete_party_export(party_root);
+    let mut init_party = InitParty::new(
+        restart_index,
+        #[cfg(feature = "malicious")]
+        &test_case.malicious_data,
+    );
+    init_party.allow_unsafe_keys = false;
+    let parties = reinit_party_as(party_options, init_party, &dir).await;
+
+    // the party refuses the sign before exchanging any message, so it is the only signer
+    let (parties, mut results) = execute_sign(
+        parties,
+        &party_uids,
+        &[restart_index],
+        key_uid,
+        "unsafe-sign-test-sig",
+        &MSG_TO_SIGN,
+        false,
+    )
+    .await;
+    let status = results.remove(0).unwrap_err();
+    assert_eq!(status.code(), FailedPrecondition);
+    assert_eq!(status.metadata().get(ERROR_CODE_KEY).unwrap(), "UNSAFE_KEY");
+
+    // the key signs once the party allows unsafe keys again
+    let (party_options, _) = shutdown_party(parties, restart_index).await;
+    let parties = reinit_party(
+        party_options,
+        restart_index,
+        &dir,
+        #[cfg(feature = "malicious")]
+        &test_case.malicious_data,
+    )
+    .await;
+    let (parties, results) = execute_sign(
+        parties,
+        &party_uids,
+        &test_case.signer_indices,
+        key_uid,
+        "unsafe-sign-test-sig",
+        &MSG_TO_SIGN,
+        false,
+    )
+    .await;
+    let results = results.into_iter().map(|r| r.unwrap()).collect();
+    assert!(check_sign_results(results, &test_case.expected_sign_faults));
+
+    clean_up(parties).await;
+}
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn recover_unsafe_key_with_safe_keygen() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![1, 1, 1], 1, vec![0, 1]);
+    let key_uid = "unsafe-recover-test-key";
+    let recover_index = 0;
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (parties, keygen_init, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids, key_uid).await;
+    assert!(success);
+    let keygen_outputs = gather_recover_info(&keygen_results);
+
+    // the party loses its shares, with no tombstone to tell their primes,
+    // and restarts without '--unsafe' nor '--allow-unsafe-keys'
+    let (party_options, party_root) = shutdown_party(parties, recover_index).await;
+    delete_party_export(party_root.clone());
+    delete_party_shares(party_root, key_uid).await;
+    let mut init_party = InitParty::new(
+        recover_index,
+        #[cfg(feature = "malicious")]
+        &test_case.malicious_data,
+    );
+    init_party.safe_keygen = true;
+    init_party.allow_unsafe_keys = false;
+    let parties = reinit_party_as(party_options, init_party, &dir).await;
+
+    // the safe primes of new keys fail with the mnemonic recorded for the key,
+    // which then recovers the unsafe primes before any other mnemonic is tried
+    let mut parties = execute_recover(parties, recover_index, keygen_init, keygen_outputs).await;
+
+    // the recovered key still records its unsafe primes
+    let info = parties[recover_index]
+        .keys_client
+        .get_key_info(GetKeyInfoRequest {
+            key_uid: key_uid.to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(info.keygen_mode, KeygenMode::Unsafe as i32);
+
+    clean_up(parties).await;
+}
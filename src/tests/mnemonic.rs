@@ -1,9 +1,18 @@
 //! mnemonic tests at the TofndParty level
 
-use super::{InitParty, TofndParty};
+use super::{
+    basic_keygen, clean_up, delete_party_export, execute_recover, gather_recover_info,
+    init_parties_from_test_case, open_party_kv_manager, reinit_party, shutdown_party, InitParty,
+    TestCase, TofndParty,
+};
 
-use crate::mnemonic::Cmd;
+use crate::{
+    gg20::types::PartyInfo,
+    kv_manager::KvManager,
+    mnemonic::{Cmd, KeyScheme, MnemonicConfig},
+};
 use testdir::testdir;
+use tracing_test::traced_test;
 
 #[cfg(feature = "malicious")]
 use super::MaliciousData;
@@ -44,3 +53,74 @@ async fn mnemonic_export_panic() {
     // Export should fail
     let _ = TofndParty::new(init_party, Cmd::Export, &dir).await;
 }
+
+#[traced_test]
+#[tokio::test(flavor = "multi_thread")]
+async fn recover_with_rotated_out_mnemonic() {
+    let dir = testdir!();
+    let test_case = TestCase::new(3, vec![1, 1, 1], 1, vec![0, 1]);
+    let key_uid = "rotated-mnemonic-test-key";
+    let recover_index = 0;
+
+    let (parties, party_uids) = init_parties_from_test_case(&test_case, &dir).await;
+    let (parties, keygen_init, keygen_results, success) =
+        basic_keygen(&test_case, parties, party_uids, key_uid).await;
+    assert!(success);
+
+    // the party rotates its mnemonic, and then loses its shares along with the origin of the key
+    let (party_options, party_root) = shutdown_party(parties, recover_index).await;
+    delete_party_export(party_root.clone());
+    let kv_manager = open_party_kv_manager(party_root.clone(), KvManager::new)
+        .await
+        .handle_mnemonic(
+            &Cmd::Rotate,
+            &MnemonicConfig {
+                export_path: Some(dir.join("rotated")),
+                ..MnemonicConfig::default()
+            },
+        )
+        .await
+        .unwrap();
+    kv_manager.delete::<PartyInfo>(key_uid).await.unwrap();
+    kv_manager
+        .forget_key_origin(KeyScheme::Gg20, key_uid)
+        .await
+        .unwrap();
+    drop(kv_manager);
+
+    // the current mnemonic doesn't recover the key, so the rotated out one is tried
+    let parties = reinit_party(
+        party_options,
+        recover_index,
+        &dir,
+        #[cfg(feature = "malicious")]
+        &test_case.malicious_data,
+    )
+    .await;
+    let parties = execute_recover(
+        parties,
+        recover_index,
+        keygen_init,
+        gather_recover_info(&keygen_results),
+    )
+    .await;
+
+    // the key was recovered with the rotated out mnemonic, and its origin is recorded again
+    let (party_options, party_root) = shutdown_party(parties, recover_index).await;
+    let kv_manager = open_party_kv_manager(party_root, KvManager::new).await;
+    let origins = kv_manager
+        .key_origins(KeyScheme::Gg20, key_uid)
+        .await
+        .unwrap();
+    assert_eq!(origins.len(), 1);
+    assert_eq!(
+        kv_manager
+            .mnemonic_of_fingerprint(origins[0])
+            .await
+            .unwrap(),
+        Some("mnemonic_1".to_owned())
+    );
+    drop(kv_manager);
+
+    clean_up(party_options.into_iter().flatten().collect()).await;
+}
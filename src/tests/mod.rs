@@ -11,12 +11,17 @@ use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 use testdir::testdir;
 use tokio::time::{sleep, Duration};
-use tonic::Code::InvalidArgument;
+use tonic::Code::{AlreadyExists, InvalidArgument};
+
+use crate::admin::service::ADMIN_TOKEN_KEY;
+use crate::error::{ERROR_CODE_KEY, RETRYABLE_KEY};
 
 mod mock;
 mod tofnd_party;
 
+mod gg20_keys;
 mod honest_test_cases;
+mod keygen_modes;
 #[cfg(feature = "malicious")]
 mod malicious;
 #[cfg(feature = "malicious")]
@@ -25,6 +30,8 @@ use malicious::{MaliciousData, PartyMaliciousData};
 mod mnemonic;
 mod socket_address;
 
+use crate::encrypted_sled::{get_test_password, Password};
+use crate::kv_manager::{error::KvResult, KvManager, GG20_SHARES_TREE};
 use crate::mnemonic::Cmd::{self, Create};
 use proto::message_out::CriminalList;
 use tracing::{info, warn};
@@ -50,6 +57,16 @@ const SLEEP_TIME: u64 = 1;
 const MAX_TRIES: u32 = 3;
 pub const DEFAULT_TEST_IP: &str = "0.0.0.0";
 pub const DEFAULT_TEST_PORT: u16 = 0; // use port 0 and let the OS decide
+const ADMIN_TOKEN: &str = "test admin token";
+
+/// a request of `message` that carries the admin token of the test parties
+fn admin_request<T>(message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    request
+        .metadata_mut()
+        .insert(ADMIN_TOKEN_KEY, ADMIN_TOKEN.parse().unwrap());
+    request
+}
 
 struct TestCase {
     uid_count: usize,
@@ -225,6 +242,28 @@ async fn shutdown_party(
     (party_options, party_root)
 }
 
+// opens the kv-store of a party that was shut down with `open`, e.g. [KvManager::new]
+async fn open_party_kv_manager(
+    party_root: PathBuf,
+    open: fn(PathBuf, Password) -> KvResult<KvManager>,
+) -> KvManager {
+    // sled can't reopen a database right after it is closed, see TofndParty::new
+    let mut tries = 0;
+    loop {
+        match open(party_root.clone(), get_test_password()) {
+            Ok(kv_manager) => return kv_manager,
+            Err(err) => {
+                tries += 1;
+                warn!("({}/3) unable to start kv manager: {}", tries, err);
+            }
+        };
+        sleep(Duration::from_secs(SLEEP_TIME)).await;
+        if tries == MAX_TRIES {
+            panic!("could not start kv manager");
+        }
+    }
+}
+
 // deletes the share kv-store of a party's db path
 fn delete_party_export(mut mnemonic_path: PathBuf) {
     mnemonic_path.push("export");
@@ -251,7 +290,11 @@ async fn delete_party_shares(mut party_db_path: PathBuf, key: &str) {
         }
     };
 
-    match db.remove(key) {
+    let shares = db
+        .open_tree(GG20_SHARES_TREE)
+        .expect("Cannot open shares tree");
+
+    match shares.remove(key) {
         Ok(_) => {}
         Err(err) => {
             panic!("Could not remove key {} from kvstore: {}", key, err)
@@ -262,7 +305,7 @@ async fn delete_party_shares(mut party_db_path: PathBuf, key: &str) {
 // reinitializes i-th party
 // pass malicious data if we are running in malicious mode
 async fn reinit_party(
-    mut party_options: Vec<Option<TofndParty>>,
+    party_options: Vec<Option<TofndParty>>,
     party_index: usize,
     testdir: &Path,
     #[cfg(feature = "malicious")] malicious_data: &MaliciousData,
@@ -274,6 +317,17 @@ async fn reinit_party(
         malicious_data,
     );
 
+    reinit_party_as(party_options, init_party, testdir).await
+}
+
+// restart a shut down party with the flags of `init_party`
+async fn reinit_party_as(
+    mut party_options: Vec<Option<TofndParty>>,
+    init_party: InitParty,
+    testdir: &Path,
+) -> Vec<TofndParty> {
+    let party_index = init_party.party_index;
+
     // here we assume that the party already has a mnemonic, so we pass Cmd::Existing
     party_options[party_index] = Some(TofndParty::new(init_party, Cmd::Existing, testdir).await);
 
@@ -486,9 +540,11 @@ async fn keygen_init_fail(test_case: &TestCase, dir: &Path) {
     )
     .await;
 
-    // all results must be Err(Status) with Code::InvalidArgument
+    // all results must be Err(Status) with Code::AlreadyExists
     for result in results {
-        assert_eq!(result.err().unwrap().code(), InvalidArgument);
+        let status = result.err().unwrap();
+        assert_eq!(status.code(), AlreadyExists);
+        assert_eq!(status.metadata().get(ERROR_CODE_KEY).unwrap(), "KEY_EXISTS");
     }
 
     clean_up(parties).await;
@@ -521,7 +577,9 @@ async fn sign_init_fail(test_case: &TestCase, dir: &Path) {
 
     // all results must be Err(Status) with Code::InvalidArgument
     for result in results {
-        assert_eq!(result.err().unwrap().code(), InvalidArgument);
+        let status = result.err().unwrap();
+        assert_eq!(status.code(), InvalidArgument);
+        assert_eq!(status.metadata().get(RETRYABLE_KEY).unwrap(), "false");
     }
 
     clean_up(parties).await;
@@ -531,6 +589,9 @@ async fn sign_init_fail(test_case: &TestCase, dir: &Path) {
 // needs to include malicious when we are running in malicious mode
 struct InitParty {
     party_index: usize,
+    // flags of the party, `--unsafe` and `--allow-unsafe-keys` by default
+    safe_keygen: bool,
+    allow_unsafe_keys: bool,
     #[cfg(feature = "malicious")]
     malicious_data: PartyMaliciousData,
 }
@@ -596,6 +657,8 @@ impl InitParty {
 
         InitParty {
             party_index: my_index,
+            safe_keygen: false,
+            allow_unsafe_keys: true,
             #[cfg(feature = "malicious")]
             malicious_data,
         }
@@ -5,15 +5,15 @@
 
 use super::{
     mock::SenderReceiver, Deliverer, GrpcKeygenResult, GrpcSignResult, InitParty, Party,
-    DEFAULT_TEST_IP, DEFAULT_TEST_PORT, MAX_TRIES,
+    ADMIN_TOKEN, DEFAULT_TEST_IP, DEFAULT_TEST_PORT, MAX_TRIES,
 };
 use crate::{
     addr,
-    config::Config,
+    config::{Config, OutputFormat},
     encrypted_sled::{get_test_password, PasswordMethod},
-    gg20,
+    gg20::{self, types::Password},
     kv_manager::KvManager,
-    mnemonic::Cmd,
+    mnemonic::{Cmd, MnemonicConfig},
     proto,
     tests::SLEEP_TIME,
 };
@@ -38,6 +38,7 @@ use gg20::service::malicious::Behaviours;
 pub(super) struct TofndParty {
     tofnd_path: PathBuf,
     client: proto::gg20_client::Gg20Client<tonic::transport::Channel>,
+    pub(super) keys_client: proto::gg20_keys_client::Gg20KeysClient<tonic::transport::Channel>,
     server_handle: JoinHandle<()>,
     server_shutdown_sender: oneshot::Sender<()>,
     server_port: u16,
@@ -63,11 +64,18 @@ impl TofndParty {
 
         let cfg = Config {
             mnemonic_cmd,
+            mnemonic_config: MnemonicConfig::default(),
             ip: server_ip.to_string(),
             port: server_port,
-            safe_keygen: false,
+            safe_keygen: init_party.safe_keygen,
+            allow_unsafe_keys: init_party.allow_unsafe_keys,
             tofnd_path,
             password_method: PasswordMethod::NoPassword,
+            reservations_cmd: None,
+            keys_cmd: None,
+            admin_token_file: None,
+            undelete_window: Duration::from_secs(60 * 60),
+            output: OutputFormat::Text,
             #[cfg(feature = "malicious")]
             behaviours: Behaviours {
                 keygen: init_party.malicious_data.keygen_behaviour.clone(),
@@ -95,15 +103,25 @@ impl TofndParty {
                 panic!("could not start kv manager");
             }
         };
-        let kv_manager = kv_manager.handle_mnemonic(&cfg.mnemonic_cmd).await.unwrap();
+        let kv_manager = kv_manager
+            .handle_mnemonic(&cfg.mnemonic_cmd, &cfg.mnemonic_config)
+            .await
+            .unwrap();
 
+        let my_keys_service = gg20::service::new_keys_service(
+            cfg.clone(),
+            kv_manager.clone(),
+            Some(Password(ADMIN_TOKEN.to_string())),
+        );
         let my_service = gg20::service::new_service(cfg.clone(), kv_manager);
 
         let proto_service = proto::gg20_server::Gg20Server::new(my_service);
+        let proto_keys_service = proto::gg20_keys_server::Gg20KeysServer::new(my_keys_service);
         // let (startup_sender, startup_receiver) = tokio::sync::oneshot::channel::<()>();
         let server_handle = tokio::spawn(async move {
             tonic::transport::Server::builder()
                 .add_service(proto_service)
+                .add_service(proto_keys_service)
                 .serve_with_incoming_shutdown(TcpListenerStream::new(incoming), async {
                     shutdown_receiver.await.unwrap();
                 })
@@ -125,10 +143,15 @@ impl TofndParty {
         let client = proto::gg20_client::Gg20Client::connect(format!("http://{}", server_addr))
             .await
             .unwrap();
+        let keys_client =
+            proto::gg20_keys_client::Gg20KeysClient::connect(format!("http://{}", server_addr))
+                .await
+                .unwrap();
 
         TofndParty {
             tofnd_path: cfg.tofnd_path,
             client,
+            keys_client,
             server_handle,
             server_shutdown_sender,
             server_port,